@@ -144,7 +144,8 @@ pub struct Config {
     pub discord_auth: DiscordAuth,
     pub meta: Meta,
     pub sites: Sites,
-    pub surreal: Surreal,
+    /// SurrealDB is only used by the limits module when the bot is built with the surreal feature
+    pub surreal: Option<Surreal>,
     pub object_storage: ObjectStorage,
 
     #[serde(skip)]
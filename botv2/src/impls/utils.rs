@@ -3,6 +3,15 @@ use crate::Error;
 use std::collections::HashMap;
 use once_cell::sync::Lazy;
 
+/// Discord allows a maximum of 25 fields per embed
+pub const MAX_EMBED_FIELDS: usize = 25;
+
+/// Discord allows a maximum of 1024 characters per embed field value
+pub const MAX_EMBED_FIELD_VALUE_LENGTH: usize = 1024;
+
+/// Discord allows a maximum of 6000 characters across the title, description, field names and field values of an embed
+pub const MAX_EMBED_LENGTH: usize = 6000;
+
 pub fn get_icon_of_state(state: &str) -> String {
     match state {
         "pending" => ":hourglass:",
@@ -141,7 +150,12 @@ pub fn parse_duration_string(s: &str) -> Result<(u64, Unit), Error> {
     // Keep looping adding up each number until we hit a non-number which gets added to unit
     for c in s.chars() {
         if c.is_numeric() {
-            number = number * 10 + c.to_digit(10).ok_or("Cannot convert to integer")? as u64;
+            let digit = c.to_digit(10).ok_or("Cannot convert to integer")? as u64;
+
+            number = number
+                .checked_mul(10)
+                .and_then(|n| n.checked_add(digit))
+                .ok_or("Duration is too large")?;
         } else {
             unit.push(c);
         }
@@ -213,4 +227,12 @@ mod test {
         assert_eq!(parse_numeric_list::<i32>("1,2,3,4,5,", &[]).unwrap(), vec![1, 2, 3, 4, 5]);
         assert_eq!(parse_numeric_list_to_str::<serenity::all::ChannelId>("1,2", &[(",", "")]).unwrap(), vec!["1", "2"]);
     }
+
+    #[test]
+    fn test_parse_duration_string() {
+        let (number, unit) = parse_duration_string("10m").unwrap();
+        assert_eq!(number, 10);
+        assert!(unit == Unit::Minutes);
+        assert!(parse_duration_string("99999999999999999999999s").is_err());
+    }
 }
\ No newline at end of file
@@ -4,15 +4,18 @@ use crate::impls::cache::CacheHttpImpl;
 /// To edit/add responses, add them both to bot.rs and to splashcore/animusmagic/types.go
 use crate::silverpelt::{
     canonical_module::CanonicalModule,
-    permissions::PermissionResult,
-    silverpelt_cache::SILVERPELT_CACHE,
+    module_registry::ModuleRegistry,
+    gwevent::schema::{event_schemas, EventSchema},
+    permissions::{PermissionResult, PermissionTrace},
+    silverpelt_cache::{invalidate_guild_cache, SILVERPELT_CACHE},
 };
+use super::client::AnimusMagicClient;
 use splashcore_rs::animusmagic_protocol::AnimusErrorResponse;
 use crate::silverpelt;
 
 use sqlx::PgPool;
 use serde::{Deserialize, Serialize};
-use serenity::all::{GuildId, Role, RoleId, UserId};
+use serenity::all::{ChannelId, GuildId, Role, RoleId, UserId};
 
 #[derive(Serialize, Deserialize, Clone)]
 pub enum BotAnimusResponse {
@@ -21,6 +24,8 @@ pub enum BotAnimusResponse {
     },
     /// Modules event contains module related data
     Modules { modules: Vec<CanonicalModule> },
+    /// EventSchemas event contains the schemas of all expanded gateway events
+    EventSchemas { events: Vec<EventSchema> },
     /// GuildsExist event contains a list of u8s, where 1 means the guild exists and 0 means it doesn't
     GuildsExist { guilds_exist: Vec<u8> },
     /// BaseGuildUserInfo event is described in AnimusMessage
@@ -40,12 +45,18 @@ pub enum BotAnimusResponse {
         perm_res: PermissionResult,
         is_ok: bool,
     },
+    /// Returns a trace explaining a command permission check
+    ExplainCommandPermission {
+        trace: PermissionTrace,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone)]
 pub enum BotAnimusMessage {
     /// Ask the bot for module data
     Modules {},
+    /// Ask the bot for the schemas (field names and types) of all expanded gateway events
+    EventSchemas {},
     /// Given a list of guild IDs, return whether or not they exist on the bot
     GuildsExist { guilds: Vec<GuildId> },
     /// Given a guild ID and a user ID, check:
@@ -56,24 +67,45 @@ pub enum BotAnimusMessage {
     /// - The bots highest role
     BaseGuildUserInfo { guild_id: GuildId, user_id: UserId },
     /// Given a guild id, a user id and a command name, check if the user has permission to run the command
-    CheckCommandPermission { guild_id: GuildId, user_id: UserId, command: String, custom_resolved_kittycat_perms: Option<Vec<String>> },
+    ///
+    /// If a channel id is provided, channel/category scoped command overrides are also taken into account
+    CheckCommandPermission { guild_id: GuildId, user_id: UserId, #[serde(default)] channel_id: Option<ChannelId>, command: String, custom_resolved_kittycat_perms: Option<Vec<String>> },
+    /// Given a guild id, a user id and a command name, explain why the user can or cannot run the command
+    ExplainCommandPermission { guild_id: GuildId, user_id: UserId, #[serde(default)] channel_id: Option<ChannelId>, command: String, custom_resolved_kittycat_perms: Option<Vec<String>> },
     /// Toggles a module within the bot clearing any cache in the process
+    ///
+    /// When a guild is provided, the cache of the guild is also invalidated on all other clusters, unless
+    /// the message itself was sent to all clusters
     ToggleModule { guild_id: Option<GuildId>, module: String, enabled: bool },
+    /// Drops all cached module states, command permissions and cooldowns of a guild
+    ///
+    /// This is broadcasted to all clusters (cluster u16::MAX) as a notification whenever the configuration of a guild changes
+    InvalidateGuildCache { guild_id: GuildId },
+    /// Invalidates the cached command permissions of a member on this cluster
+    ///
+    /// This is broadcasted to all clusters (cluster u16::MAX) as a notification whenever the roles or permission overrides of a member change
+    InvalidateMemberCache { guild_id: GuildId, user_id: UserId },
+    /// Reloads the modules of this cluster from the built-in modules, leaving out the given modules (by ID or name)
+    ///
+    /// This allows pulling a module (and rolling it back out) without a restart. To apply to all clusters, send it to cluster u16::MAX
+    ReloadModules { #[serde(default)] excluded_modules: Vec<String> },
 }
 
 impl BotAnimusMessage {
-    pub async fn response(self, pool: &PgPool, cache_http: &CacheHttpImpl) -> Result<BotAnimusResponse, AnimusErrorResponse> {
+    /// Handles the message, returning the response to send back
+    ///
+    /// `broadcast` is whether the message was sent to all clusters. Messages that were broadcasted must not be
+    /// broadcasted again, as every cluster would otherwise repeat the broadcast
+    pub async fn response(self, pool: &PgPool, cache_http: &CacheHttpImpl, animus_magic_ipc: &AnimusMagicClient, broadcast: bool) -> Result<BotAnimusResponse, AnimusErrorResponse> {
         match self {
             Self::Modules {} => {
-                let mut modules = Vec::new();
-
-                for idm in SILVERPELT_CACHE.canonical_module_cache.iter() {
-                    let module = idm.value();
-                    modules.push(module.clone());
-                }
+                let modules = SILVERPELT_CACHE.registry().canonical_modules().cloned().collect();
 
                 Ok(BotAnimusResponse::Modules { modules })
             }
+            Self::EventSchemas {} => {
+                Ok(BotAnimusResponse::EventSchemas { events: event_schemas() })
+            }
             Self::GuildsExist { guilds } => {
                 let mut guilds_exist = Vec::with_capacity(guilds.len());
 
@@ -128,7 +160,7 @@ impl BotAnimusMessage {
                     bot_roles,
                 })
             },
-            Self::CheckCommandPermission { guild_id, user_id, command, custom_resolved_kittycat_perms } => {
+            Self::CheckCommandPermission { guild_id, user_id, channel_id, command, custom_resolved_kittycat_perms } => {
                 // Check COMMAND_ID_MODULE_MAP
                 let base_command = command.split_whitespace().next().unwrap();
                 
@@ -137,6 +169,7 @@ impl BotAnimusMessage {
                     &command,
                     guild_id,
                     user_id,
+                    channel_id,
                     pool,
                     cache_http,
                     &None,
@@ -151,29 +184,40 @@ impl BotAnimusMessage {
                     is_ok,
                 })
             },
+            Self::ExplainCommandPermission { guild_id, user_id, channel_id, command, custom_resolved_kittycat_perms } => {
+                let Some(base_command) = command.split_whitespace().next() else {
+                    return Err("No command was provided".into());
+                };
+
+                let trace = silverpelt::cmd::explain_command(
+                    base_command,
+                    &command,
+                    guild_id,
+                    user_id,
+                    channel_id,
+                    pool,
+                    cache_http,
+                    custom_resolved_kittycat_perms
+                )
+                .await;
+
+                Ok(BotAnimusResponse::ExplainCommandPermission {
+                    trace,
+                })
+            },
             Self::ToggleModule { guild_id, module, enabled } => {
                 if let Some(guild_id) = guild_id {
-                    if enabled {
-                        SILVERPELT_CACHE
-                            .module_enabled_cache
-                            .insert((guild_id, module.clone()), true)
-                            .await;
+                    if broadcast {
+                        SILVERPELT_CACHE.invalidate_guild_local(guild_id);
                     } else {
-                        SILVERPELT_CACHE
-                            .module_enabled_cache
-                            .insert((guild_id, module.clone()), false)
-                            .await;
+                        // Other clusters may have the old module state cached as well
+                        invalidate_guild_cache(animus_magic_ipc, guild_id).await;
                     }
 
-                    tokio::spawn(async move {
-                        if let Err(err) = SILVERPELT_CACHE.command_permission_cache.invalidate_entries_if(move |k, _| {
-                            k.0 == guild_id
-                        }) {
-                            log::error!("Failed to invalidate command permission cache for guild {}: {}", guild_id, err);
-                        } else {
-                            log::info!("Invalidated cache for guild {}", guild_id);
-                        }
-                    });
+                    SILVERPELT_CACHE
+                        .module_enabled_cache
+                        .insert((guild_id, module.clone()), enabled)
+                        .await;
                 } else {
                     // Global enable/disable the module by iterating the entire cache
                     for (k, v) in SILVERPELT_CACHE.module_enabled_cache.iter() {
@@ -202,6 +246,51 @@ impl BotAnimusMessage {
                     message: "".to_string()
                 })
             }
+            Self::InvalidateGuildCache { guild_id } => {
+                SILVERPELT_CACHE.invalidate_guild_local(guild_id);
+
+                Ok(BotAnimusResponse::Ok {
+                    message: "".to_string()
+                })
+            }
+            Self::InvalidateMemberCache { guild_id, user_id } => {
+                SILVERPELT_CACHE.invalidate_member_local(guild_id, user_id);
+
+                Ok(BotAnimusResponse::Ok {
+                    message: "".to_string()
+                })
+            }
+            Self::ReloadModules { excluded_modules } => {
+                // Resolve module names against all built-in modules, as excluded modules may not be loaded currently
+                let all_modules = ModuleRegistry::new(crate::modules::modules())?;
+
+                let mut excluded = Vec::new();
+
+                for module in excluded_modules.iter() {
+                    let Some(module) = all_modules.module(module).or_else(|| all_modules.module_id_by_name(module).and_then(|id| all_modules.module(id))) else {
+                        return Err(format!("Unknown module {}", module).into());
+                    };
+
+                    if !module.toggleable {
+                        return Err(format!("Module {} cannot be unloaded", module.id).into());
+                    }
+
+                    excluded.push(module.id);
+                }
+
+                let modules = crate::modules::modules()
+                    .into_iter()
+                    .filter(|m| !excluded.contains(&m.id))
+                    .collect::<Vec<_>>();
+
+                let loaded = modules.len();
+
+                SILVERPELT_CACHE.reload_modules(modules)?;
+
+                Ok(BotAnimusResponse::Ok {
+                    message: format!("Loaded {} modules", loaded)
+                })
+            }
         }
     }
 }
@@ -236,6 +236,49 @@ impl AnimusMagicClient {
                     });
                 }
 
+                AnimusOp::Notify => {
+                    if meta.to != AnimusTarget::Bot && meta.to != AnimusTarget::Wildcard {
+                        continue; // Not for us, to != Bot and != wildcard
+                    }
+
+                    if meta.cluster_id_to != MEWLD_ARGS.cluster_id && meta.cluster_id_to != u16::MAX {
+                        continue; // Not for us, cluster_id != ours and != wildcard
+                    }
+
+                    if meta.from == AnimusTarget::Bot && meta.cluster_id_from == MEWLD_ARGS.cluster_id {
+                        continue; // Sent by us, the sender handles the notification itself
+                    }
+
+                    let cache_http = cache_http.clone();
+                    let pool = pool.clone();
+
+                    let client = AnimusMagicClient {
+                        redis_pool: self.redis_pool.clone(),
+                        rx_map: self.rx_map.clone(),
+                    };
+
+                    // Notifications expect no response, so errors are only logged
+                    tokio::spawn(async move {
+                        let payload = &binary[meta.payload_offset..];
+
+                        let msg = match AnimusMessage::from_payload(payload, AnimusTarget::Bot) {
+                            Ok(AnimusMessage::Bot(msg)) => msg,
+                            Ok(AnimusMessage::Jobserver(_)) => {
+                                log::warn!("Invalid notification recieved on channel {} [invalid message type]", message.channel);
+                                return;
+                            }
+                            Err(e) => {
+                                log::warn!("Invalid notification recieved on channel {} [request extract error] {}", message.channel, e);
+                                return;
+                            }
+                        };
+
+                        if let Err(e) = msg.response(&pool, &cache_http, &client, true).await {
+                            log::warn!("Failed to handle notification on channel {}: {}", message.channel, e.message);
+                        }
+                    });
+                }
+
                 AnimusOp::Request | AnimusOp::Probe => {
                     // Ensure requeest op, and that the cluster id is either the same as ours or the wildcard u16::MAX
                     if meta.to != AnimusTarget::Bot && meta.to != AnimusTarget::Wildcard {
@@ -343,7 +386,7 @@ impl AnimusMagicClient {
                             }
                         };
 
-                        let data = match msg.response(&pool, &cache_http).await {
+                        let data = match msg.response(&pool, &cache_http, &client, meta.cluster_id_to == u16::MAX).await {
                             Ok(data) => data,
                             Err(e) => {
                                 log::warn!(
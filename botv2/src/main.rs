@@ -22,9 +22,6 @@ use poise::serenity_prelude::FullEvent;
 use poise::CreateReply;
 use sqlx::postgres::PgPoolOptions;
 use std::io::Write;
-use surrealdb::engine::remote::ws::{Client, Ws};
-use surrealdb::opt::auth::Root;
-use surrealdb::Surreal;
 
 type Error = Box<dyn std::error::Error + Send + Sync>;
 type Context<'a> = poise::Context<'a, Data, Error>;
@@ -36,7 +33,6 @@ pub struct Data {
     pub object_store: Arc<config::ObjectStore>,
     pub animus_magic_ipc: Arc<ipc::animus_magic::client::AnimusMagicClient>,
     pub shards_ready: Arc<dashmap::DashMap<u16, bool>>,
-    pub surreal_cache: Surreal<Client>,
 }
 
 async fn on_error(error: poise::FrameworkError<'_, Data, Error>) {
@@ -217,12 +213,29 @@ async fn event_listener<'a>(
         }
     };
 
+    if let Err(e) = silverpelt::events::event_listener(ctx.serenity_context, event, event_guild_id).await {
+        error!("Error in silverpelt event listener: {}", e);
+    }
+
     let data = ctx.user_data();
+
+    let event_name: &'static str = event.into();
     
-    for (module, evts) in SILVERPELT_CACHE
-        .module_event_listeners_cache
-        .iter()
-    {
+    let registry = SILVERPELT_CACHE.registry();
+
+    for module_data in registry.modules() {
+        let module = module_data.id;
+        let evts = &module_data.event_handlers;
+
+        if evts.is_empty() {
+            continue;
+        }
+
+        // Skip modules that do not listen to this event
+        if !module_data.event_filter.is_empty() && !module_data.event_filter.contains(&event_name) {
+            continue;
+        }
+
         let module_enabled = match is_module_enabled(
             &data.pool,
             event_guild_id,
@@ -322,6 +335,8 @@ async fn main() {
 
     let framework_opts = poise::FrameworkOptions {
         initialize_owners: true,
+        // Cooldowns are handled by silverpelt as they can be configured per guild
+        manual_cooldowns: true,
         prefix_options: poise::PrefixFrameworkOptions {
             prefix: Some("%".into()),
             ..poise::PrefixFrameworkOptions::default()
@@ -428,6 +443,7 @@ async fn main() {
                     &command.qualified_name,
                     guild_id,
                     ctx.author().id,
+                    Some(ctx.channel_id()),
                     &data.pool,
                     &CacheHttpImpl::from_ctx(ctx.serenity_context()),
                     &Some(ctx),
@@ -479,24 +495,6 @@ async fn main() {
     .build_pool(REDIS_MAX_CONNECTIONS.try_into().unwrap())
     .expect("Could not initialize Redis pool");
 
-    info!("Connecting to surreal");
-
-    let surreal_config = config::CONFIG.surreal.clone();
-    let surreal_client = Surreal::new::<Ws>(surreal_config.url)
-        .await
-        .expect("Couldnt initialize surreal");
-    let _ = surreal_client
-        .signin(Root {
-            username: surreal_config.username.as_str(),
-            password: surreal_config.password.as_str(),
-        })
-        .await;
-    surreal_client
-        .use_ns("antiraid")
-        .use_db("splashtail")
-        .await
-        .expect("Couldnt use namespace and database");
-
     let data = Data {
         mewld_ipc: Arc::new(ipc::mewld::MewldIpcClient {
             redis_pool: pool.clone(),
@@ -518,7 +516,6 @@ async fn main() {
             .await
             .expect("Could not initialize connection"),
         shards_ready: Arc::new(dashmap::DashMap::new()),
-        surreal_cache: surreal_client,
     };
 
     info!("Initializing bot state");
@@ -0,0 +1,54 @@
+use super::core::Sink;
+use crate::Context;
+
+pub async fn sinks_autocomplete<'a>(
+    ctx: Context<'_>,
+    partial: &'a str,
+) -> Vec<serenity::all::AutocompleteChoice<'a>> {
+    let data = ctx.data();
+
+    let Some(guild_id) = ctx.guild_id() else {
+        return Vec::new();
+    };
+
+    let Ok(sinks) = Sink::fetch(&data.pool, guild_id).await else {
+        return Vec::new();
+    };
+
+    let mut choices = Vec::new();
+
+    for sink in sinks.iter() {
+        if sink.id.starts_with(partial) || sink.channel_id.to_string().starts_with(partial) {
+            choices.push(serenity::all::AutocompleteChoice::new(
+                format!("#{} ({})", sink.channel_id, sink.id),
+                sink.id.clone(),
+            ));
+        }
+    }
+
+    choices
+}
+
+/// Autocompletes a comma-separated list of event names
+pub async fn events_autocomplete<'a>(
+    _ctx: Context<'_>,
+    partial: &'a str,
+) -> Vec<serenity::all::AutocompleteChoice<'a>> {
+    // Only autocomplete the last event in the list
+    let (prefix, last) = match partial.rsplit_once(',') {
+        Some((prefix, last)) => (format!("{},", prefix), last.trim()),
+        None => (String::new(), partial.trim()),
+    };
+
+    let last = last.to_uppercase();
+
+    crate::silverpelt::gwevent::core::event_list()
+        .iter()
+        .filter(|e| e.starts_with(&last))
+        .take(25)
+        .map(|e| {
+            let value = format!("{}{}", prefix, e);
+            serenity::all::AutocompleteChoice::new(value.clone(), value)
+        })
+        .collect()
+}
@@ -0,0 +1,294 @@
+use super::core::Sink;
+use crate::impls::utils::MAX_EMBED_FIELDS;
+use crate::silverpelt::gwevent::{filter::FieldFilter, schema::event_schemas};
+use crate::{Context, Error};
+use poise::{serenity_prelude::CreateEmbed, CreateReply};
+use serenity::all::{ChannelType, GuildChannel, Mentionable};
+
+/// Audit logs base command
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only,
+    subcommands("auditlogs_list", "auditlogs_add", "auditlogs_remove")
+)]
+pub async fn auditlogs(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Parses a comma-separated list of event names, returning None if no events are given (all events)
+fn parse_events(events: Option<String>) -> Result<Option<Vec<String>>, Error> {
+    let Some(events) = events else {
+        return Ok(None);
+    };
+
+    let event_list = crate::silverpelt::gwevent::core::event_list();
+
+    let mut parsed = Vec::new();
+
+    for event in events.split(',') {
+        let event = event.trim().to_uppercase();
+
+        if event.is_empty() {
+            continue;
+        }
+
+        if !event_list.contains(&event.as_str()) {
+            return Err(format!("``{}`` is not a valid event", event).into());
+        }
+
+        if !parsed.contains(&event) {
+            parsed.push(event);
+        }
+    }
+
+    if parsed.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(parsed))
+}
+
+/// Parses a semicolon-separated list of field filters, ensuring that every filtered field exists on at least
+/// one of the given events (or any event if no events are given)
+fn parse_filters(
+    filters: Option<String>,
+    events: Option<&[String]>,
+) -> Result<Vec<FieldFilter>, Error> {
+    let Some(filters) = filters else {
+        return Ok(Vec::new());
+    };
+
+    let schemas = event_schemas();
+
+    let mut parsed = Vec::new();
+
+    for filter in filters.split(';') {
+        if filter.trim().is_empty() {
+            continue;
+        }
+
+        let filter: FieldFilter = filter.parse()?;
+
+        let field_exists = schemas
+            .iter()
+            .filter(|s| events.map(|e| e.contains(&s.event)).unwrap_or(true))
+            .any(|s| s.field(&filter.field).is_some());
+
+        if !field_exists {
+            return Err(format!(
+                "``{}`` is not a field of any of the events of the sink",
+                filter.field
+            )
+            .into());
+        }
+
+        parsed.push(filter);
+    }
+
+    Ok(parsed)
+}
+
+/// Lists all audit log sinks of the server
+#[poise::command(prefix_command, slash_command, guild_only, rename = "list")]
+pub async fn auditlogs_list(ctx: Context<'_>) -> Result<(), Error> {
+    let sinks = Sink::from_database(
+        &ctx.data().pool,
+        ctx.guild_id().ok_or("Could not get guild id")?,
+    )
+    .await?;
+
+    if sinks.is_empty() {
+        ctx.say("No audit log sinks setup for this server, use ``/auditlogs add`` to add one!")
+            .await?;
+        return Ok(());
+    }
+
+    let mut reply = CreateReply::new();
+
+    for chunk in sinks.chunks(MAX_EMBED_FIELDS) {
+        let mut embed = CreateEmbed::default()
+            .title("Audit Log Sinks")
+            .color(0x00ff00);
+
+        for sink in chunk {
+            embed = embed.field(
+                sink.id.clone(),
+                format!(
+                    "Channel: {}\nEvents: {}\nFilters: {}\nCreated by {} <t:{}:R>",
+                    sink.channel_id.mention(),
+                    match sink.events {
+                        Some(ref events) => events
+                            .iter()
+                            .map(|e| format!("``{}``", e))
+                            .collect::<Vec<String>>()
+                            .join(", "),
+                        None => "All events (except high-volume events)".to_string(),
+                    },
+                    if sink.filters.is_empty() {
+                        "None".to_string()
+                    } else {
+                        sink.filters
+                            .iter()
+                            .map(|f| format!("``{}``", f))
+                            .collect::<Vec<String>>()
+                            .join(", ")
+                    },
+                    sink.created_by.mention(),
+                    sink.created_at.timestamp()
+                ),
+                false,
+            );
+        }
+
+        reply = reply.embed(embed);
+    }
+
+    ctx.send(reply).await?;
+
+    Ok(())
+}
+
+/// Adds a channel to send audit log events to
+#[poise::command(prefix_command, slash_command, guild_only, rename = "add")]
+pub async fn auditlogs_add(
+    ctx: Context<'_>,
+    #[description = "The channel to send audit log events to"] channel: GuildChannel,
+    #[description = "Comma-separated events, e.g. CHANNEL_CREATE,CHANNEL_DELETE. Omit for all except high-volume events"]
+    #[autocomplete = "super::autocompletes::events_autocomplete"]
+    events: Option<String>,
+    #[description = "Semicolon-separated field filters events must match, e.g. channel_id == 1234; user_id != 5678"]
+    filters: Option<String>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("Could not get guild id")?;
+
+    if channel.guild_id != guild_id {
+        return Err("The channel provided does not exist in this server".into());
+    }
+
+    if !matches!(channel.kind, ChannelType::Text | ChannelType::News) {
+        return Err("Audit log events can only be sent to text or announcement channels".into());
+    }
+
+    let events = parse_events(events)?;
+    let filters = parse_filters(filters, events.as_deref())?
+        .iter()
+        .map(|f| f.to_string())
+        .collect::<Vec<String>>();
+
+    let sink = sqlx::query!(
+        "
+            INSERT INTO auditlogs__sinks (
+                guild_id,
+                channel_id,
+                events,
+                filters,
+                created_by
+            )
+            VALUES (
+                $1,
+                $2,
+                $3,
+                $4,
+                $5
+            )
+            RETURNING id
+        ",
+        guild_id.to_string(),
+        channel.id.to_string(),
+        events.as_deref(),
+        &filters,
+        ctx.author().id.to_string()
+    )
+    .fetch_one(&ctx.data().pool)
+    .await?;
+
+    Sink::invalidate(guild_id).await;
+
+    ctx.say(format!(
+        "Added audit log sink ``{}`` sending {} to {}",
+        sink.id,
+        match events {
+            Some(events) => format!("{} event(s)", events.len()),
+            None => "all events (except high-volume events such as MESSAGE)".to_string(),
+        },
+        channel.id.mention()
+    ))
+    .await?;
+
+    Ok(())
+}
+
+/// Removes an audit log sink
+#[poise::command(prefix_command, slash_command, guild_only, rename = "remove")]
+pub async fn auditlogs_remove(
+    ctx: Context<'_>,
+    #[description = "The ID of the sink to remove"]
+    #[autocomplete = "super::autocompletes::sinks_autocomplete"]
+    sink_id: String,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("Could not get guild id")?;
+
+    let res = sqlx::query!(
+        "
+            DELETE FROM auditlogs__sinks
+            WHERE guild_id = $1
+            AND id = $2
+        ",
+        guild_id.to_string(),
+        sink_id
+    )
+    .execute(&ctx.data().pool)
+    .await?;
+
+    if res.rows_affected() == 0 {
+        return Err("Could not find sink".into());
+    }
+
+    Sink::invalidate(guild_id).await;
+
+    ctx.say("Removed audit log sink successfully").await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_events() {
+        assert_eq!(parse_events(None).unwrap(), None);
+        assert_eq!(parse_events(Some(" , ".to_string())).unwrap(), None);
+        assert_eq!(
+            parse_events(Some(
+                "channel_create, CHANNEL_DELETE,channel_create".to_string()
+            ))
+            .unwrap(),
+            Some(vec![
+                "CHANNEL_CREATE".to_string(),
+                "CHANNEL_DELETE".to_string()
+            ])
+        );
+        assert!(parse_events(Some("CHANNEL_CREATE,NOT_AN_EVENT".to_string())).is_err());
+    }
+
+    #[test]
+    fn test_parse_filters() {
+        assert!(parse_filters(None, None).unwrap().is_empty());
+
+        let filters =
+            parse_filters(Some("channel_id == 1; user_id != 2;".to_string()), None).unwrap();
+        assert_eq!(
+            filters.iter().map(|f| f.to_string()).collect::<Vec<_>>(),
+            vec!["channel_id == 1", "user_id != 2"]
+        );
+
+        // Fields must exist on one of the events of the sink
+        let events = vec!["GUILD_ROLE_CREATE".to_string()];
+        assert!(parse_filters(Some("role_name ~= mod".to_string()), Some(&events)).is_ok());
+        assert!(parse_filters(Some("channel_id == 1".to_string()), Some(&events)).is_err());
+        assert!(parse_filters(Some("not_a_field == 1".to_string()), None).is_err());
+        assert!(parse_filters(Some("channel_id 1".to_string()), None).is_err());
+    }
+}
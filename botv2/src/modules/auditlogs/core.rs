@@ -0,0 +1,300 @@
+use crate::impls::utils::{MAX_EMBED_FIELDS, MAX_EMBED_FIELD_VALUE_LENGTH, MAX_EMBED_LENGTH};
+use crate::silverpelt::gwevent::{
+    core::{ExpandedEvent, FieldType},
+    filter::FieldFilter,
+};
+use crate::Error;
+use moka::future::Cache;
+use once_cell::sync::Lazy;
+use poise::serenity_prelude::{ChannelId, CreateEmbed, GuildId, UserId};
+use serde::{Deserialize, Serialize};
+use sqlx::{
+    types::chrono::{DateTime, Utc},
+    PgPool,
+};
+use std::sync::Arc;
+
+/// Events that are only sent to sinks that explicitly list them, as sending every
+/// occurence of them would flood the sink channel
+pub const HIGH_VOLUME_EVENTS: &[&str] = &[
+    "GUILD_MEMBERS_CHUNK",
+    "INTERACTION_CREATE",
+    "MESSAGE",
+    "PRESENCE_REPLACE",
+    "PRESENCE_UPDATE",
+    "REACTION_ADD",
+    "REACTION_REMOVE",
+    "TYPING_START",
+    "VOICE_STATE_UPDATE",
+];
+
+/// Cache of the sinks of a guild, as sinks are needed on every event
+static SINKS_CACHE: Lazy<Cache<GuildId, Arc<Vec<Sink>>>> = Lazy::new(|| {
+    Cache::builder()
+        .time_to_live(std::time::Duration::from_secs(300))
+        .build()
+});
+
+/// A sink is a channel that audit log events are sent to
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Sink {
+    /// The ID of the sink
+    pub id: String,
+    /// The ID of the guild this sink is for
+    pub guild_id: GuildId,
+    /// The channel events are sent to
+    pub channel_id: ChannelId,
+    /// The events sent to the sink. If None, all events except `HIGH_VOLUME_EVENTS` are sent
+    pub events: Option<Vec<String>>,
+    /// The field filters an event must all match to be sent to the sink
+    pub filters: Vec<FieldFilter>,
+    /// When the sink was created
+    pub created_at: DateTime<Utc>,
+    /// The user who created the sink
+    pub created_by: UserId,
+}
+
+impl Sink {
+    /// Fetch all sinks of a guild from the database
+    pub async fn from_database(pool: &PgPool, guild_id: GuildId) -> Result<Vec<Self>, Error> {
+        let rec = sqlx::query!(
+            "
+                SELECT id, channel_id, events, filters, created_at, created_by FROM auditlogs__sinks
+                WHERE guild_id = $1
+            ",
+            guild_id.to_string()
+        )
+        .fetch_all(pool)
+        .await?;
+
+        let mut sinks = Vec::new();
+
+        for r in rec {
+            sinks.push(Self {
+                id: r.id,
+                guild_id,
+                channel_id: r.channel_id.parse()?,
+                events: r.events,
+                filters: r
+                    .filters
+                    .iter()
+                    .map(|f| f.parse())
+                    .collect::<Result<Vec<FieldFilter>, Error>>()?,
+                created_at: r.created_at,
+                created_by: r.created_by.parse()?,
+            });
+        }
+
+        Ok(sinks)
+    }
+
+    /// Fetch all sinks of a guild, using the cache if possible
+    pub async fn fetch(pool: &PgPool, guild_id: GuildId) -> Result<Arc<Vec<Self>>, Error> {
+        if let Some(sinks) = SINKS_CACHE.get(&guild_id).await {
+            return Ok(sinks);
+        }
+
+        let sinks = Arc::new(Self::from_database(pool, guild_id).await?);
+
+        SINKS_CACHE.insert(guild_id, sinks.clone()).await;
+
+        Ok(sinks)
+    }
+
+    /// Invalidates the cached sinks of a guild. Must be called whenever the sinks of a guild change
+    pub async fn invalidate(guild_id: GuildId) {
+        SINKS_CACHE.invalidate(&guild_id).await;
+    }
+
+    /// Returns whether the sink should receive the given event
+    pub fn wants(&self, event: &ExpandedEvent) -> bool {
+        let wants_event = match self.events {
+            Some(ref events) => events.iter().any(|e| e == event.event),
+            None => !HIGH_VOLUME_EVENTS.contains(&event.event),
+        };
+
+        wants_event && self.filters.iter().all(|f| f.matches(event))
+    }
+}
+
+/// Converts an event name such as `GUILD_MEMBER_ADDITION` to a title such as `Guild Member Addition`
+fn event_title(event: &str) -> String {
+    event
+        .split('_')
+        .map(|word| {
+            let word = word.to_lowercase();
+            let mut chars = word.chars();
+
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// Formats a field value for display in an embed, mentioning users, channels and roles
+fn format_field_value(value: &FieldType) -> String {
+    let mentions = |prefix: &str| {
+        value
+            .to_strings()
+            .iter()
+            .map(|id| format!("<{}{}>", prefix, id))
+            .collect::<Vec<String>>()
+            .join(", ")
+    };
+
+    match value {
+        FieldType::UserIds(_) | FieldType::Users(_) => mentions("@"),
+        FieldType::Channels(_) => mentions("#"),
+        FieldType::Roles(_) => mentions("@&"),
+        _ => {
+            let values = value.to_strings();
+
+            if values.is_empty() {
+                return "None".to_string();
+            }
+
+            format!("``{}``", values.join(", ").replace('`', "'"))
+        }
+    }
+}
+
+/// Truncates a string to at most `max` characters, marking truncated strings with an ellipsis
+fn truncate(value: String, max: usize) -> String {
+    if value.chars().count() <= max {
+        return value;
+    }
+
+    value
+        .chars()
+        .take(max.saturating_sub(3))
+        .collect::<String>()
+        + "..."
+}
+
+/// Creates an embed for an expanded event
+pub fn create_event_embed<'a>(event: &ExpandedEvent) -> CreateEmbed<'a> {
+    let title = event_title(event.event);
+
+    // Fields are added until the embed reaches the maximum total length
+    let mut remaining = MAX_EMBED_LENGTH - title.chars().count();
+
+    let mut embed = CreateEmbed::default()
+        .title(title)
+        .color(0x3498db)
+        .timestamp(poise::serenity_prelude::Timestamp::now());
+
+    for (name, field) in event
+        .fields
+        .iter()
+        .filter(|(name, _)| *name != "guild_id")
+        .take(MAX_EMBED_FIELDS)
+    {
+        let value = field
+            .value
+            .iter()
+            .map(format_field_value)
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        if value.is_empty() {
+            continue;
+        }
+
+        let name = event_title(name);
+        let name_length = name.chars().count();
+
+        // Leave room for at least a few characters of the value
+        if remaining < name_length + 4 {
+            break;
+        }
+
+        let value = truncate(
+            value,
+            MAX_EMBED_FIELD_VALUE_LENGTH.min(remaining - name_length),
+        );
+
+        remaining -= name_length + value.chars().count();
+
+        embed = embed.field(name, value, true);
+    }
+
+    embed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::silverpelt::gwevent::core::Field;
+    use indexmap::IndexMap;
+    use serenity::all::{ChannelId, RoleId, UserId};
+
+    #[test]
+    fn test_event_title() {
+        assert_eq!(
+            event_title("GUILD_MEMBER_ADDITION"),
+            "Guild Member Addition"
+        );
+        assert_eq!(event_title("MESSAGE"), "Message");
+        assert_eq!(event_title("channel_id"), "Channel Id");
+        assert_eq!(event_title(""), "");
+    }
+
+    #[test]
+    fn test_format_field_value() {
+        assert_eq!(
+            format_field_value(&vec![UserId::new(1), UserId::new(2)].into()),
+            "<@1>, <@2>"
+        );
+        assert_eq!(format_field_value(&ChannelId::new(1).into()), "<#1>");
+        assert_eq!(format_field_value(&RoleId::new(1).into()), "<@&1>");
+        assert_eq!(format_field_value(&true.into()), "``true``");
+        assert_eq!(
+            format_field_value(&"a `code` block".to_string().into()),
+            "``a 'code' block``"
+        );
+        assert_eq!(format_field_value(&FieldType::Strings(vec![])), "None");
+    }
+
+    #[test]
+    fn test_create_event_embed() {
+        let mut fields = IndexMap::new();
+
+        for i in 0..30 {
+            fields.insert(format!("field_{}", i), Field::new("a".repeat(2000).into()));
+        }
+
+        let embed = serde_json::to_value(create_event_embed(&ExpandedEvent {
+            event: "MESSAGE_UPDATE",
+            fields,
+        }))
+        .unwrap();
+
+        let fields = embed["fields"].as_array().unwrap();
+        let length = |v: &serde_json::Value| v.as_str().unwrap().chars().count();
+
+        assert!(fields.len() <= MAX_EMBED_FIELDS);
+        assert!(fields
+            .iter()
+            .all(|f| length(&f["value"]) <= MAX_EMBED_FIELD_VALUE_LENGTH));
+        assert!(
+            length(&embed["title"])
+                + fields
+                    .iter()
+                    .map(|f| length(&f["name"]) + length(&f["value"]))
+                    .sum::<usize>()
+                <= MAX_EMBED_LENGTH
+        );
+    }
+
+    #[test]
+    fn test_high_volume_events() {
+        let event_list = crate::silverpelt::gwevent::core::event_list();
+
+        for event in HIGH_VOLUME_EVENTS {
+            assert!(event_list.contains(event), "{} is not an event", event);
+        }
+    }
+}
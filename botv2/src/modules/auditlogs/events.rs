@@ -0,0 +1,61 @@
+use log::warn;
+use poise::serenity_prelude::{CreateMessage, FullEvent};
+
+use super::core::{create_event_embed, Sink};
+use crate::silverpelt::gwevent::core::ExpandedEvent;
+use crate::{silverpelt::EventHandlerContext, Data, Error};
+
+pub async fn event_listener(
+    ctx: &serenity::client::Context,
+    event: &FullEvent,
+    ectx: EventHandlerContext,
+) -> Result<(), Error> {
+    let user_data = ctx.data::<Data>();
+
+    let sinks = Sink::fetch(&user_data.pool, ectx.guild_id).await?;
+
+    if sinks.is_empty() {
+        return Ok(());
+    }
+
+    let Some(expanded) = ExpandedEvent::new(event) else {
+        return Ok(());
+    };
+
+    // Ignore events caused by the bot itself to avoid logging our own audit log messages
+    let current_user_id = ctx.cache.current_user().id;
+
+    if expanded.field_matches("user_id", &current_user_id.to_string())
+        || expanded.field_matches("message_author", &current_user_id.to_string())
+    {
+        return Ok(());
+    }
+
+    let embed = create_event_embed(&expanded);
+
+    for sink in sinks.iter() {
+        if !sink.wants(&expanded) {
+            continue;
+        }
+
+        // Never log events about the sink channel itself, this avoids feedback loops
+        if expanded.field_matches("channel_id", &sink.channel_id.to_string())
+            || expanded.field_matches("message_channel_id", &sink.channel_id.to_string())
+        {
+            continue;
+        }
+
+        if let Err(e) = sink
+            .channel_id
+            .send_message(&ctx.http, CreateMessage::new().embed(embed.clone()))
+            .await
+        {
+            warn!(
+                "Failed to send audit log event to sink {} in guild {}: {}",
+                sink.id, ectx.guild_id, e
+            );
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,34 @@
+mod autocompletes;
+mod cmds;
+mod core;
+mod events;
+
+use indexmap::indexmap;
+
+pub fn module() -> crate::silverpelt::Module {
+    crate::silverpelt::Module {
+        id: "auditlogs",
+        name: "Audit Logs",
+        description: "Customizable and comprehensive audit logging module supporting sending events to multiple channels with event whitelists.",
+        toggleable: true,
+        commands_configurable: true,
+        virtual_module: false,
+        web_hidden: false,
+        is_default_enabled: false,
+        depends_on: vec![],
+        conflicts_with: vec![],
+        commands: vec![(
+            cmds::auditlogs(),
+            indexmap! {
+                "list" => crate::silverpelt::CommandExtendedData::kittycat_or_admin("auditlogs", "list"),
+                "add" => crate::silverpelt::CommandExtendedData::kittycat_or_admin("auditlogs", "add"),
+                "remove" => crate::silverpelt::CommandExtendedData::kittycat_or_admin("auditlogs", "remove"),
+            },
+        )],
+        config_options: vec![],
+        event_filter: vec![], // Audit logs can be sent for any event
+        event_handlers: vec![Box::new(move |ctx, fe, ectx| {
+            Box::pin(async move { events::event_listener(ctx, fe, ectx).await })
+        })],
+    }
+}
@@ -0,0 +1,14 @@
+CREATE EXTENSION IF NOT EXISTS "uuid-ossp";
+
+-- Stores the channels (sinks) audit log events are sent to in a guild
+CREATE TABLE auditlogs__sinks (
+    id TEXT PRIMARY KEY DEFAULT uuid_generate_v4(),
+    guild_id TEXT NOT NULL REFERENCES guilds(id) ON DELETE CASCADE ON UPDATE CASCADE,
+    channel_id TEXT NOT NULL,
+    -- The events to send to the sink, if NULL all events are sent
+    events TEXT[],
+    -- Field filters (such as channel_id == 1234) an event must match to be sent to the sink
+    filters TEXT[] NOT NULL DEFAULT '{}',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    created_by TEXT NOT NULL
+);
@@ -42,9 +42,8 @@ async fn _embed_help(
         let cat_name = {
             if let Some(cat_name) = category_id {
                 // Get the module from the name
-                let cat_module = SILVERPELT_CACHE
-                    .module_id_cache
-                    .get(&cat_name);
+                let registry = SILVERPELT_CACHE.registry();
+                let cat_module = registry.module(&cat_name);
 
                 if let Some(cat_module) = cat_module {
                     cat_module.name.to_string()
@@ -12,6 +12,8 @@ pub fn module() -> crate::silverpelt::Module {
         virtual_module: false,
         web_hidden: false,
         is_default_enabled: true,
+        depends_on: vec![],
+        conflicts_with: vec![],
         commands: vec![
             (help::help(), crate::silverpelt::CommandExtendedData::none()),
             (
@@ -24,6 +26,8 @@ pub fn module() -> crate::silverpelt::Module {
             ),
             (ping::ping(), crate::silverpelt::CommandExtendedData::none()),
         ],
+        config_options: vec![],
+        event_filter: vec![],
         event_handlers: vec![], // Core has no event listeners
     }
 }
@@ -14,6 +14,8 @@ pub fn module() -> crate::silverpelt::Module {
         virtual_module: false,
         web_hidden: false,
         is_default_enabled: false,
+        depends_on: vec![],
+        conflicts_with: vec![],
         commands: vec![
             (core::gitlogs(), indexmap! {
                 "" => crate::silverpelt::CommandExtendedData::kittycat_or_admin("gitlogs", "*"),
@@ -27,6 +29,8 @@ pub fn module() -> crate::silverpelt::Module {
                 "eventmod" => crate::silverpelt::CommandExtendedData::kittycat_or_admin("gitlogs", "eventmod"),
             })
         ],
+        config_options: vec![],
+        event_filter: vec![],
         event_handlers: vec![],
     }
 }
@@ -1,4 +1,4 @@
-use crate::modules::limits::core::Limit;
+use crate::modules::limits::core::{Exemption, Limit};
 use crate::Context;
 
 pub async fn limits_autocomplete<'a>(
@@ -16,7 +16,11 @@ pub async fn limits_autocomplete<'a>(
 
     let guild_id = guild_id.unwrap();
 
-    let limits = Limit::fetch(&data.surreal_cache, &data.pool, guild_id).await;
+    let Ok(store) = super::store::store().await else {
+        return Vec::new();
+    };
+
+    let limits = Limit::fetch(store, &data.pool, guild_id).await;
 
     if let Ok(limits) = limits {
         let mut choices = Vec::new();
@@ -35,3 +39,38 @@ pub async fn limits_autocomplete<'a>(
 
     Vec::new()
 }
+
+pub async fn exemptions_autocomplete<'a>(
+    ctx: Context<'_>,
+    partial: &'a str,
+) -> Vec<serenity::all::AutocompleteChoice<'a>> {
+    let data = ctx.data();
+
+    let Some(guild_id) = ctx.guild_id() else {
+        return Vec::new();
+    };
+
+    let Ok(store) = super::store::store().await else {
+        return Vec::new();
+    };
+
+    let Ok(exemptions) = Exemption::fetch(store, &data.pool, guild_id).await else {
+        return Vec::new();
+    };
+
+    let mut choices = Vec::new();
+
+    for exemption in exemptions {
+        if exemption.id.starts_with(partial) || exemption.target.starts_with(partial) {
+            choices.push(serenity::all::AutocompleteChoice::new(
+                format!(
+                    "{} {} ({})",
+                    exemption.exemption_type, exemption.target, exemption.id
+                ),
+                exemption.id,
+            ));
+        }
+    }
+
+    choices
+}
@@ -0,0 +1,379 @@
+use super::core::{Limit, UserAction};
+use poise::serenity_prelude::UserId;
+use serde::{Deserialize, Serialize};
+use sqlx::types::chrono::{DateTime, Duration, Utc};
+use std::collections::HashMap;
+
+/// The outcome of recording an action in a sliding window
+#[derive(Debug, PartialEq)]
+pub enum WindowOutcome {
+    /// The action was ignored as the same user-target pair was already handled within the repeat rate
+    Ignored,
+    /// The action was recorded
+    Recorded {
+        /// The IDs of the limits that were hit by the action
+        hits: Vec<String>,
+        /// The actions that caused the hits, with `limits_hit` set to the IDs of the limits each action contributed to
+        cause: Vec<UserAction>,
+    },
+}
+
+/// A sliding window of the actions of a single user for a single limit type
+///
+/// An action counts towards a limit while it is less than `limit_time` seconds old, and a limit
+/// is hit once more than `limit_per` actions are counted. Once hit, the actions that caused the hit
+/// no longer count towards that limit (but still count towards other limits of the same type)
+#[derive(Default, Clone, Serialize, Deserialize)]
+pub struct ActionWindow {
+    /// The recorded actions, oldest first
+    actions: Vec<UserAction>,
+    /// Limit ID to the time the limit was last hit, actions at or before this time no longer count towards the limit
+    resets: HashMap<String, DateTime<Utc>>,
+}
+
+impl ActionWindow {
+    /// Records an action, returning the limits it hit
+    ///
+    /// `limits` must be the limits of the same type as the action. The action is ignored if an action
+    /// on the same target was recorded less than `repeat_rate` seconds before it
+    pub fn record(&mut self, action: UserAction, limits: &[Limit], repeat_rate: i64) -> WindowOutcome {
+        let now = action.created_at;
+
+        // Forget everything that can no longer count towards a limit or the repeat rate
+        let retain_for = limits
+            .iter()
+            .map(|l| l.limit_time)
+            .chain(std::iter::once(repeat_rate))
+            .max()
+            .unwrap_or_default();
+
+        self.actions
+            .retain(|a| now - a.created_at < Duration::seconds(retain_for));
+        self.resets
+            .retain(|limit_id, _| limits.iter().any(|l| &l.limit_id == limit_id));
+
+        if self.actions.iter().any(|a| {
+            a.target == action.target && now - a.created_at < Duration::seconds(repeat_rate)
+        }) {
+            return WindowOutcome::Ignored;
+        }
+
+        self.actions.push(action);
+
+        let mut hits = Vec::new();
+        let mut cause: Vec<UserAction> = Vec::new();
+
+        for limit in limits {
+            let reset = self.resets.get(&limit.limit_id).copied();
+
+            let counted = self
+                .actions
+                .iter()
+                .filter(|a| now - a.created_at < Duration::seconds(limit.limit_time))
+                .filter(|a| reset.map(|r| a.created_at > r).unwrap_or(true))
+                .collect::<Vec<_>>();
+
+            if counted.len() <= limit.limit_per.max(0) as usize {
+                continue;
+            }
+
+            for action in counted {
+                match cause.iter_mut().find(|c| c.action_id == action.action_id) {
+                    Some(c) => c.limits_hit.push(limit.limit_id.clone()),
+                    None => {
+                        let mut action = action.clone();
+                        action.limits_hit.push(limit.limit_id.clone());
+                        cause.push(action);
+                    }
+                }
+            }
+
+            self.resets.insert(limit.limit_id.clone(), now);
+            hits.push(limit.limit_id.clone());
+        }
+
+        WindowOutcome::Recorded { hits, cause }
+    }
+}
+
+/// A user who would have hit a simulated limit
+#[derive(Debug, PartialEq)]
+pub struct SimulatedHit {
+    /// The user who would have hit the limit
+    pub user_id: UserId,
+    /// How many times the user would have hit the limit
+    pub hits: usize,
+    /// When the user would have first hit the limit
+    pub first_hit: DateTime<Utc>,
+}
+
+/// Replays past actions against a limit, returning the users who would have hit it in the order they first hit it
+///
+/// Actions of other limit types than the limit are ignored
+pub fn simulate(limit: &Limit, mut actions: Vec<UserAction>) -> Vec<SimulatedHit> {
+    actions.retain(|a| a.limit_type == limit.limit_type);
+    actions.sort_by_key(|a| a.created_at);
+
+    let repeat_rate = limit.limit_type.default_user_target_repeat_rate();
+    let limits = std::slice::from_ref(limit);
+
+    let mut windows: HashMap<UserId, ActionWindow> = HashMap::new();
+    let mut simulated: Vec<SimulatedHit> = Vec::new();
+
+    for mut action in actions {
+        let user_id = action.user_id;
+        let created_at = action.created_at;
+
+        // The limits hit when the action was recorded are not relevant to the simulated limit
+        action.limits_hit.clear();
+
+        let outcome = windows
+            .entry(user_id)
+            .or_default()
+            .record(action, limits, repeat_rate);
+
+        match outcome {
+            WindowOutcome::Recorded { hits, .. } if !hits.is_empty() => {}
+            _ => continue,
+        }
+
+        match simulated.iter_mut().find(|s| s.user_id == user_id) {
+            Some(s) => s.hits += 1,
+            None => simulated.push(SimulatedHit {
+                user_id,
+                hits: 1,
+                first_hit: created_at,
+            }),
+        }
+    }
+
+    simulated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::limits::core::{LimitMode, UserLimitActions, UserLimitTypes};
+    use poise::serenity_prelude::GuildId;
+
+    fn limit(limit_id: &str, limit_per: i32, limit_time: i64) -> Limit {
+        Limit {
+            guild_id: GuildId::new(1),
+            limit_id: limit_id.to_string(),
+            limit_name: limit_id.to_string(),
+            limit_type: UserLimitTypes::ChannelAdd,
+            limit_action: UserLimitActions::KickUser,
+            limit_per,
+            limit_time,
+            limit_tiers: vec![],
+            escalation_window: 86400,
+            timeout_duration: 600,
+            quarantine_role: None,
+            rollback: false,
+            mode: LimitMode::Enforce,
+        }
+    }
+
+    fn action(id: &str, target: &str, at_ms: i64) -> UserAction {
+        UserAction {
+            action_id: id.to_string(),
+            limit_type: UserLimitTypes::ChannelAdd,
+            created_at: DateTime::from_timestamp(1_700_000_000, 0).unwrap()
+                + Duration::milliseconds(at_ms),
+            user_id: UserId::new(2),
+            guild_id: GuildId::new(1),
+            action_data: serde_json::json!({}),
+            limits_hit: Vec::new(),
+            target: target.to_string(),
+        }
+    }
+
+    fn hits(outcome: &WindowOutcome) -> Vec<String> {
+        match outcome {
+            WindowOutcome::Recorded { hits, .. } => hits.clone(),
+            WindowOutcome::Ignored => panic!("action was ignored"),
+        }
+    }
+
+    #[test]
+    fn test_limit_hit_only_when_exceeded() {
+        let limits = [limit("l", 2, 10)];
+        let mut window = ActionWindow::default();
+
+        assert!(hits(&window.record(action("a", "1", 0), &limits, 0)).is_empty());
+        assert!(hits(&window.record(action("b", "2", 1000), &limits, 0)).is_empty());
+
+        let outcome = window.record(action("c", "3", 2000), &limits, 0);
+        assert_eq!(hits(&outcome), vec!["l"]);
+
+        let WindowOutcome::Recorded { cause, .. } = outcome else {
+            unreachable!()
+        };
+        assert_eq!(
+            cause.iter().map(|a| a.action_id.as_str()).collect::<Vec<_>>(),
+            vec!["a", "b", "c"]
+        );
+        assert!(cause.iter().all(|a| a.limits_hit == vec!["l"]));
+    }
+
+    #[test]
+    fn test_window_boundary() {
+        let limits = [limit("l", 1, 10)];
+
+        // An action exactly limit_time old no longer counts
+        let mut window = ActionWindow::default();
+        window.record(action("a", "1", 0), &limits, 0);
+        assert!(hits(&window.record(action("b", "2", 10_000), &limits, 0)).is_empty());
+
+        // An action just under limit_time old still counts
+        let mut window = ActionWindow::default();
+        window.record(action("a", "1", 0), &limits, 0);
+        assert_eq!(hits(&window.record(action("b", "2", 9_999), &limits, 0)), vec!["l"]);
+    }
+
+    #[test]
+    fn test_hit_resets_limit() {
+        let limits = [limit("short", 1, 10), limit("long", 3, 100)];
+        let mut window = ActionWindow::default();
+
+        window.record(action("a", "1", 0), &limits, 0);
+        assert_eq!(hits(&window.record(action("b", "2", 1000), &limits, 0)), vec!["short"]);
+
+        // The actions that hit the short limit do not count towards it again, but still count towards the long one
+        assert!(hits(&window.record(action("c", "3", 2000), &limits, 0)).is_empty());
+
+        let outcome = window.record(action("d", "4", 3000), &limits, 0);
+        assert_eq!(hits(&outcome), vec!["short", "long"]);
+
+        let WindowOutcome::Recorded { cause, .. } = outcome else {
+            unreachable!()
+        };
+        let d = cause.iter().find(|a| a.action_id == "d").unwrap();
+        assert_eq!(d.limits_hit, vec!["short", "long"]);
+        let a = cause.iter().find(|a| a.action_id == "a").unwrap();
+        assert_eq!(a.limits_hit, vec!["long"]);
+    }
+
+    #[test]
+    fn test_repeat_rate() {
+        let limits = [limit("l", 1, 10)];
+        let mut window = ActionWindow::default();
+
+        window.record(action("a", "1", 0), &limits, 2);
+
+        // Same target within the repeat rate is ignored
+        assert_eq!(window.record(action("b", "1", 1999), &limits, 2), WindowOutcome::Ignored);
+
+        // A different target is not
+        assert_eq!(hits(&window.record(action("c", "2", 1999), &limits, 2)), vec!["l"]);
+
+        // Same target exactly at the repeat rate is counted again
+        let mut window = ActionWindow::default();
+        window.record(action("a", "1", 0), &limits, 2);
+        assert_eq!(hits(&window.record(action("b", "1", 2000), &limits, 2)), vec!["l"]);
+
+        // No repeat rate means nothing is ignored
+        let mut window = ActionWindow::default();
+        window.record(action("a", "1", 0), &limits, 0);
+        assert_eq!(hits(&window.record(action("b", "1", 0), &limits, 0)), vec!["l"]);
+    }
+
+    #[test]
+    fn test_old_actions_are_pruned() {
+        let limits = [limit("l", 5, 10)];
+        let mut window = ActionWindow::default();
+
+        window.record(action("a", "1", 0), &limits, 0);
+        window.record(action("b", "2", 5000), &limits, 0);
+        assert_eq!(window.actions.len(), 2);
+
+        window.record(action("c", "3", 12_000), &limits, 0);
+        assert_eq!(
+            window.actions.iter().map(|a| a.action_id.as_str()).collect::<Vec<_>>(),
+            vec!["b", "c"]
+        );
+    }
+
+    #[test]
+    fn test_simulate() {
+        let limit = limit("l", 1, 10);
+
+        let mut other_user = action("c", "3", 500);
+        other_user.user_id = UserId::new(3);
+
+        let mut other_type = action("d", "4", 600);
+        other_type.limit_type = UserLimitTypes::RoleAdd;
+
+        // Out of order, with actions of another user and limit type mixed in
+        let actions = vec![
+            action("b", "2", 1000),
+            other_user,
+            action("a", "1", 0),
+            other_type,
+            action("e", "5", 20_000),
+            action("f", "6", 21_000),
+            action("g", "7", 22_000),
+        ];
+
+        assert_eq!(
+            simulate(&limit, actions),
+            vec![SimulatedHit {
+                user_id: UserId::new(2),
+                hits: 2,
+                first_hit: action("b", "2", 1000).created_at,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_simulate_replays_actions_without_hits() {
+        // Build the stored history of a guild with a lax limit, where every action is stored and the
+        // actions that caused a hit are updated with the limits they hit
+        let lax = [limit("lax", 3, 10)];
+        let mut windows: HashMap<UserId, ActionWindow> = HashMap::new();
+        let mut history: Vec<UserAction> = Vec::new();
+
+        for (id, user_id, at_ms) in [
+            ("a", 2, 0),
+            ("b", 2, 1000),
+            ("c", 3, 0),
+            ("d", 3, 1000),
+            ("e", 3, 2000),
+            ("f", 3, 3000),
+        ] {
+            let mut action = action(id, id, at_ms);
+            action.user_id = UserId::new(user_id);
+            history.push(action.clone());
+
+            if let WindowOutcome::Recorded { cause, .. } =
+                windows.entry(action.user_id).or_default().record(action, &lax, 0)
+            {
+                for caused in cause {
+                    let stored = history.iter_mut().find(|a| a.action_id == caused.action_id).unwrap();
+                    stored.limits_hit = caused.limits_hit;
+                }
+            }
+        }
+
+        // Only user 3 hit the lax limit, user 2 never hit anything
+        assert!(history.iter().filter(|a| a.user_id == UserId::new(2)).all(|a| a.limits_hit.is_empty()));
+        assert!(history.iter().filter(|a| a.user_id == UserId::new(3)).all(|a| a.limits_hit == vec!["lax"]));
+
+        // A stricter limit shows that user 2 would have been hit as well
+        assert_eq!(
+            simulate(&limit("strict", 1, 10), history),
+            vec![
+                SimulatedHit {
+                    user_id: UserId::new(2),
+                    hits: 1,
+                    first_hit: action("b", "b", 1000).created_at,
+                },
+                SimulatedHit {
+                    user_id: UserId::new(3),
+                    hits: 2,
+                    first_hit: action("d", "d", 1000).created_at,
+                },
+            ]
+        );
+    }
+}
@@ -1,18 +1,54 @@
-use crate::impls::utils::{parse_pg_interval, secs_to_pg_interval};
-use crate::modules::limits::core::Limit;
+use super::incidents::Resolution;
+use crate::impls::cache::CacheHttpImpl;
+use crate::impls::utils::{
+    parse_duration_string, parse_pg_interval, secs_to_pg_interval, MAX_EMBED_FIELDS,
+};
+use crate::modules::limits::core::{
+    parse_limit_tiers, Exemption, ExemptionType, Limit, LimitMode, LimitModeChoices, UserAction,
+    UserLimitActions, UserLimitTypes,
+};
+use crate::tasks::prune_limit_actions::LIMIT_ACTION_RETENTION_DAYS;
 use crate::{Context, Error};
 use poise::{serenity_prelude::CreateEmbed, CreateReply};
 use serenity::{
-    all::{Mentionable, UserId},
+    all::{ApplicationId, Mentionable, Role, User, UserId},
     builder::CreateAttachment,
 };
 
+/// The maximum number of users listed in the results of a simulation
+const MAX_SIMULATED_USERS: usize = 25;
+
+/// Discord allows timeouts of at most 28 days
+const MAX_TIMEOUT_DURATION: i64 = 28 * 24 * 60 * 60;
+
+/// Parses a duration such as 10m or 1d into seconds
+fn parse_duration_secs(duration: &str) -> Result<i64, Error> {
+    let (number, unit) = parse_duration_string(&duration.replace(' ', ""))
+        .map_err(|_| format!("``{}`` is not a valid duration", duration))?;
+
+    if number == 0 {
+        return Err("Durations must be greater than zero".into());
+    }
+
+    Ok((number * unit.to_seconds()) as i64)
+}
+
 /// Limits base command
 #[poise::command(
     prefix_command,
     slash_command,
     guild_only,
-    subcommands("limits_add", "limits_view", "limits_remove", "limits_hit")
+    subcommands(
+        "limits_add",
+        "limits_view",
+        "limits_remove",
+        "limits_escalation",
+        "limits_exempt",
+        "limits_hit",
+        "limits_incident",
+        "limits_mode",
+        "limits_simulate"
+    )
 )]
 pub async fn limits(_ctx: Context<'_>) -> Result<(), Error> {
     Ok(())
@@ -31,14 +67,72 @@ pub async fn limits_add(
     limit_time_unit: crate::impls::utils::Unit,
     #[description = "The action to take when the limit is hit"]
     limit_action: crate::modules::limits::core::UserLimitActionsChoices,
+    #[description = "How long Timeout User times out for, e.g. 10m. Defaults to 10 minutes"]
+    timeout_duration: Option<String>,
+    #[description = "The role Quarantine User gives to the user"] quarantine_role: Option<Role>,
+    #[description = "Whether to recreate the channels/roles deleted by users who hit a Channel/Role Remove limit"]
+    rollback: Option<bool>,
+    #[description = "Whether to take actions on hits or only record them. Defaults to enforce"]
+    mode: Option<LimitModeChoices>,
 ) -> Result<(), Error> {
     let limit_type = limit_type.resolve();
     let limit_action = limit_action.resolve();
+    let mode = mode.map(|m| m.resolve()).unwrap_or_default();
 
     let guild_id = ctx.guild_id().ok_or("Could not get guild id")?;
 
+    if limit_per < 1 {
+        return Err("The amount of times the limit can be hit must be at least 1".into());
+    }
+
+    // Actions that did not hit a limit are pruned after the retention period, so they cannot be counted in longer intervals
+    let max_limit_time = LIMIT_ACTION_RETENTION_DAYS * 86400;
+
+    let limit_time = limit_time
+        .checked_mul(limit_time_unit.to_seconds_i64())
+        .filter(|t| (1..=max_limit_time).contains(t))
+        .ok_or_else(|| {
+            format!(
+                "The time interval must be between 1 second and {} days",
+                LIMIT_ACTION_RETENTION_DAYS
+            )
+        })?;
+
+    let timeout_duration = match timeout_duration {
+        Some(timeout_duration) => parse_duration_secs(&timeout_duration)?,
+        None => 600,
+    };
+
+    if timeout_duration > MAX_TIMEOUT_DURATION {
+        return Err("Timeout duration must be less than 28 days".into());
+    }
+
+    if let Some(ref quarantine_role) = quarantine_role {
+        if quarantine_role.guild_id != guild_id {
+            return Err("The quarantine role provided does not exist in this server".into());
+        }
+    }
+
+    if limit_action == UserLimitActions::QuarantineUser && quarantine_role.is_none() {
+        return Err("A quarantine role must be provided to quarantine users".into());
+    }
+
+    let rollback = rollback.unwrap_or_default();
+
+    if rollback
+        && !matches!(
+            limit_type,
+            UserLimitTypes::ChannelRemove | UserLimitTypes::RoleRemove
+        )
+    {
+        return Err(
+            "Only Channel Remove and Role Remove limits can roll back deleted channels/roles"
+                .into(),
+        );
+    }
+
     // Add limit to db
-    let limit = sqlx::query!(
+    sqlx::query!(
         "
             INSERT INTO limits__guild_limits (
                 guild_id,
@@ -46,7 +140,11 @@ pub async fn limits_add(
                 limit_type,
                 limit_action,
                 limit_per,
-                limit_time
+                limit_time,
+                timeout_duration,
+                quarantine_role,
+                rollback,
+                mode
             )
             VALUES (
                 $1, 
@@ -54,36 +152,32 @@ pub async fn limits_add(
                 $3, 
                 $4, 
                 $5,
-                make_interval(secs => $6)
+                make_interval(secs => $6),
+                make_interval(secs => $7),
+                $8,
+                $9,
+                $10
             )
-            RETURNING limit_id
         ",
         guild_id.to_string(),
         limit_name,
         limit_type.to_string(),
         limit_action.to_string(),
         limit_per,
-        (limit_time * limit_time_unit.to_seconds_i64()) as f64
+        limit_time as f64,
+        timeout_duration as f64,
+        quarantine_role.map(|r| r.id.to_string()),
+        rollback,
+        mode.to_string()
     )
-    .fetch_one(&ctx.data().pool)
+    .execute(&ctx.data().pool)
     .await?;
 
-    let limit_id = limit.limit_id;
-
-    let _ = &ctx
-        .data()
-        .surreal_cache
-        .create::<Vec<Limit>>("guild_limits")
-        .content(Limit {
-            limit_id,
-            guild_id,
-            limit_name,
-            limit_type,
-            limit_action,
-            limit_per,
-            limit_time: (limit_time * limit_time_unit.to_seconds_i64()),
-        })
+    super::store::store()
+        .await?
+        .invalidate_guild(guild_id)
         .await?;
+
     ctx.say("Added limit successfully").await?;
 
     Ok(())
@@ -93,7 +187,7 @@ pub async fn limits_add(
 #[poise::command(prefix_command, slash_command, guild_only, rename = "view")]
 pub async fn limits_view(ctx: Context<'_>) -> Result<(), Error> {
     let limits = Limit::fetch(
-        &ctx.data().surreal_cache,
+        super::store::store().await?,
         &ctx.data().pool,
         ctx.guild_id().ok_or("Could not get guild id")?,
     )
@@ -122,15 +216,41 @@ pub async fn limits_view(ctx: Context<'_>) -> Result<(), Error> {
             embeds.push(CreateEmbed::default().title("Limits").color(0x00ff00));
         }
 
+        let escalation = if limit.limit_tiers.is_empty() {
+            String::new()
+        } else {
+            format!(
+                "\n**Escalation** (hits within {window}): {tiers}",
+                window = parse_pg_interval(secs_to_pg_interval(limit.escalation_window)),
+                tiers = limit
+                    .limit_tiers
+                    .iter()
+                    .enumerate()
+                    .map(|(i, tier)| format!(
+                        "{}. ``{}``",
+                        i + 1,
+                        tier.iter()
+                            .map(|a| a.to_cond())
+                            .collect::<Vec<String>>()
+                            .join(", ")
+                    ))
+                    .collect::<Vec<String>>()
+                    .join(" "),
+            )
+        };
+
         embeds[i] = embeds[i].clone().field(
             limit.limit_name,
             format!(
-                "If over {amount} ``{cond}`` triggered between {time} interval: ``{then}`` [{id}]",
+                "If over {amount} ``{cond}`` triggered between {time} interval: ``{then}`` [{id}]{mode}{rollback}{escalation}",
                 amount = limit.limit_per,
                 cond = limit.limit_type.to_cond(),
                 time = parse_pg_interval(secs_to_pg_interval(limit.limit_time)),
                 then = limit.limit_action.to_cond(),
-                id = limit.limit_id
+                id = limit.limit_id,
+                mode = if limit.mode == LimitMode::Monitor { "\n**Monitor mode, no actions are taken**" } else { "" },
+                rollback = if limit.rollback { "\n**Rolls back deleted channels/roles**" } else { "" },
+                escalation = escalation
             ),
             false,
         );
@@ -185,15 +305,471 @@ pub async fn limits_remove(
     .execute(&ctx.data().pool)
     .await?;
 
-    let _ = &ctx.data().surreal_cache.query("delete guild_limits where guild_id=type::string($guild_id) and limit_id=type::string($limit_id) return none")
-        .bind(("guild_id", ctx.guild_id().ok_or("Could not get guild id")?.to_string()))
-        .bind(("limit_id", limit_id))
+    super::store::store()
+        .await?
+        .invalidate_guild(ctx.guild_id().ok_or("Could not get guild id")?)
         .await?;
+
     ctx.say("Removed limit successfully").await?;
 
     Ok(())
 }
 
+/// Set the escalation tiers of a limit, these are the actions to take on repeated hits
+#[poise::command(prefix_command, slash_command, guild_only, rename = "escalation")]
+pub async fn limits_escalation(
+    ctx: Context<'_>,
+    #[description = "The limit id to set escalation tiers for"]
+    #[autocomplete = "super::autocompletes::limits_autocomplete"]
+    limit_id: String,
+    #[description = "Tiers separated by ; of comma-separated actions, e.g. timeout_user;ban_user. Omit to clear"]
+    tiers: Option<String>,
+    #[description = "The time previous hits are counted in, e.g. 1d. Defaults to 1 day"]
+    window: Option<String>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("Could not get guild id")?;
+
+    let tiers = match tiers {
+        Some(tiers) => parse_limit_tiers(&tiers)?,
+        None => Vec::new(),
+    };
+
+    let window = match window {
+        Some(window) => parse_duration_secs(&window)?,
+        None => 86400,
+    };
+
+    let Some(limit) = sqlx::query!(
+        "
+            SELECT quarantine_role FROM limits__guild_limits
+            WHERE guild_id = $1
+            AND limit_id = $2
+        ",
+        guild_id.to_string(),
+        limit_id
+    )
+    .fetch_optional(&ctx.data().pool)
+    .await?
+    else {
+        return Err("Could not find limit".into());
+    };
+
+    if limit.quarantine_role.is_none()
+        && tiers
+            .iter()
+            .any(|t| t.contains(&UserLimitActions::QuarantineUser))
+    {
+        return Err("This limit has no quarantine role, so users cannot be quarantined".into());
+    }
+
+    let limit_tiers = tiers
+        .iter()
+        .map(|t| t.iter().map(|a| a.to_string()).collect::<Vec<String>>())
+        .collect::<Vec<Vec<String>>>();
+
+    sqlx::query!(
+        "
+            UPDATE limits__guild_limits
+            SET limit_tiers = $3, escalation_window = make_interval(secs => $4)
+            WHERE guild_id = $1
+            AND limit_id = $2
+        ",
+        guild_id.to_string(),
+        limit_id,
+        serde_json::to_value(limit_tiers)?,
+        window as f64
+    )
+    .execute(&ctx.data().pool)
+    .await?;
+
+    super::store::store()
+        .await?
+        .invalidate_guild(guild_id)
+        .await?;
+
+    if tiers.is_empty() {
+        ctx.say("Escalation tiers cleared successfully").await?;
+    } else {
+        ctx.say(format!(
+            "Escalation tiers updated successfully, {} tier(s) are now used",
+            tiers.len()
+        ))
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Set whether a limit takes actions when hit or only records hits
+#[poise::command(prefix_command, slash_command, guild_only, rename = "mode")]
+pub async fn limits_mode(
+    ctx: Context<'_>,
+    #[description = "The limit id to set the mode of"]
+    #[autocomplete = "super::autocompletes::limits_autocomplete"]
+    limit_id: String,
+    #[description = "Enforce takes the actions of the limit, monitor only records hits"]
+    mode: LimitModeChoices,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("Could not get guild id")?;
+    let mode = mode.resolve();
+
+    let res = sqlx::query!(
+        "
+            UPDATE limits__guild_limits
+            SET mode = $3
+            WHERE guild_id = $1
+            AND limit_id = $2
+        ",
+        guild_id.to_string(),
+        limit_id,
+        mode.to_string()
+    )
+    .execute(&ctx.data().pool)
+    .await?;
+
+    if res.rows_affected() == 0 {
+        return Err("Could not find limit".into());
+    }
+
+    super::store::store()
+        .await?
+        .invalidate_guild(guild_id)
+        .await?;
+
+    let msg = match mode {
+        LimitMode::Enforce => "Limit is now enforced",
+        LimitMode::Monitor => {
+            "Limit is now in monitor mode, hits will be recorded without taking any actions"
+        }
+    };
+
+    ctx.say(msg).await?;
+
+    Ok(())
+}
+
+/// Replay the recorded actions of this server against a proposed limit to see who would have hit it
+#[poise::command(prefix_command, slash_command, guild_only, rename = "simulate")]
+pub async fn limits_simulate(
+    ctx: Context<'_>,
+    #[description = "The type of limit to simulate"]
+    limit_type: crate::modules::limits::core::UserLimitTypesChoices,
+    #[description = "The amount of times the limit can be hit"] limit_per: i32,
+    #[description = "The time interval infractions are counted in"] limit_time: i64,
+    #[description = "The time unit for the time interval [seconds/minutes/hours/days]"]
+    limit_time_unit: crate::impls::utils::Unit,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("Could not get guild id")?;
+    let limit_type = limit_type.resolve();
+
+    if limit_per < 1 {
+        return Err("The amount of times the limit can be hit must be at least 1".into());
+    }
+
+    // Actions that did not hit a limit are only kept for the retention period, so longer intervals cannot be simulated
+    let max_limit_time = LIMIT_ACTION_RETENTION_DAYS * 86400;
+
+    let limit_time = limit_time
+        .checked_mul(limit_time_unit.to_seconds_i64())
+        .filter(|t| (1..=max_limit_time).contains(t))
+        .ok_or_else(|| {
+            format!(
+                "The time interval must be between 1 second and {} days",
+                LIMIT_ACTION_RETENTION_DAYS
+            )
+        })?;
+
+    let limit = Limit {
+        guild_id,
+        limit_id: "simulated".to_string(),
+        limit_name: "Simulated Limit".to_string(),
+        limit_type,
+        limit_action: UserLimitActions::AlertOnly,
+        limit_per,
+        limit_time,
+        limit_tiers: Vec::new(),
+        escalation_window: 0,
+        timeout_duration: 0,
+        quarantine_role: None,
+        rollback: false,
+        mode: LimitMode::Monitor,
+    };
+
+    let actions = UserAction::guild_recent(
+        &ctx.data().pool,
+        guild_id,
+        limit_type,
+        LIMIT_ACTION_RETENTION_DAYS as i32,
+    )
+    .await?;
+
+    if actions.is_empty() {
+        ctx.say(format!(
+            "No recorded ``{}`` actions to replay in this server",
+            limit_type.to_cond()
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    let replayed = actions.len();
+    let simulated = super::cache::simulate(&limit, actions);
+
+    let mut description = format!(
+        "Replayed {} recorded ``{}`` action(s) against a limit of {} per {} second(s)\n\n",
+        replayed,
+        limit_type.to_cond(),
+        limit.limit_per,
+        limit.limit_time
+    );
+
+    if simulated.is_empty() {
+        description.push_str("No users would have hit this limit");
+    } else {
+        for hit in simulated.iter().take(MAX_SIMULATED_USERS) {
+            description.push_str(&format!(
+                "- {} ({}) would have hit this limit {} time(s), first <t:{}:R>\n",
+                hit.user_id.mention(),
+                hit.user_id,
+                hit.hits,
+                hit.first_hit.timestamp()
+            ));
+        }
+
+        if simulated.len() > MAX_SIMULATED_USERS {
+            description.push_str(&format!(
+                "...and {} more",
+                simulated.len() - MAX_SIMULATED_USERS
+            ));
+        }
+    }
+
+    ctx.send(
+        CreateReply::new().embed(
+            CreateEmbed::default()
+                .title("Limit Simulation")
+                .color(0x0000ff)
+                .description(description),
+        ),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Manage the users, roles and applications that are exempt from limits
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only,
+    rename = "exempt",
+    subcommands("limits_exempt_add", "limits_exempt_remove", "limits_exempt_list")
+)]
+pub async fn limits_exempt(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Exempt a user, role or bot/integration from limits
+#[poise::command(prefix_command, slash_command, guild_only, rename = "add")]
+pub async fn limits_exempt_add(
+    ctx: Context<'_>,
+    #[description = "The user to exempt"] user: Option<User>,
+    #[description = "The role to exempt"] role: Option<Role>,
+    #[description = "Application ID of a bot/integration to exempt"] application_id: Option<String>,
+    #[description = "The limit type to exempt from. Omit to exempt from all limit types"]
+    limit_type: Option<crate::modules::limits::core::UserLimitTypesChoices>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("Could not get guild id")?;
+
+    let (exemption_type, target, mention) = match (user, role, application_id) {
+        (Some(user), None, None) => (
+            ExemptionType::User,
+            user.id.to_string(),
+            user.mention().to_string(),
+        ),
+        (None, Some(role), None) => {
+            if role.guild_id != guild_id {
+                return Err("The role provided does not exist in this server".into());
+            }
+
+            (
+                ExemptionType::Role,
+                role.id.to_string(),
+                role.mention().to_string(),
+            )
+        }
+        (None, None, Some(application_id)) => {
+            let application_id = application_id
+                .trim()
+                .parse::<ApplicationId>()
+                .map_err(|_| format!("``{}`` is not a valid application ID", application_id))?;
+
+            (
+                ExemptionType::Application,
+                application_id.to_string(),
+                format!("application ``{}``", application_id),
+            )
+        }
+        _ => return Err("Exactly one of user, role or application ID must be provided".into()),
+    };
+
+    let limit_type = limit_type.map(|l| l.resolve());
+
+    let count = sqlx::query!(
+        "
+            SELECT COUNT(*) FROM limits__exemptions
+            WHERE guild_id = $1
+            AND exemption_type = $2
+            AND target = $3
+            AND limit_type IS NOT DISTINCT FROM $4
+        ",
+        guild_id.to_string(),
+        exemption_type.to_string(),
+        target,
+        limit_type.map(|l| l.to_string())
+    )
+    .fetch_one(&ctx.data().pool)
+    .await?;
+
+    if count.count.unwrap_or_default() > 0 {
+        return Err(format!("{} is already exempt", mention).into());
+    }
+
+    let exemption = sqlx::query!(
+        "
+            INSERT INTO limits__exemptions (
+                guild_id,
+                exemption_type,
+                target,
+                limit_type,
+                created_by
+            )
+            VALUES (
+                $1,
+                $2,
+                $3,
+                $4,
+                $5
+            )
+            RETURNING id
+        ",
+        guild_id.to_string(),
+        exemption_type.to_string(),
+        target,
+        limit_type.map(|l| l.to_string()),
+        ctx.author().id.to_string()
+    )
+    .fetch_one(&ctx.data().pool)
+    .await?;
+
+    super::store::store()
+        .await?
+        .invalidate_guild(guild_id)
+        .await?;
+
+    ctx.say(format!(
+        "Exempted {} from {} [{}]",
+        mention,
+        match limit_type {
+            Some(limit_type) => format!("``{}`` limits", limit_type.to_cond()),
+            None => "all limits".to_string(),
+        },
+        exemption.id
+    ))
+    .await?;
+
+    Ok(())
+}
+
+/// Remove an exemption from limits
+#[poise::command(prefix_command, slash_command, guild_only, rename = "remove")]
+pub async fn limits_exempt_remove(
+    ctx: Context<'_>,
+    #[description = "The ID of the exemption to remove"]
+    #[autocomplete = "super::autocompletes::exemptions_autocomplete"]
+    exemption_id: String,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("Could not get guild id")?;
+
+    let res = sqlx::query!(
+        "
+            DELETE FROM limits__exemptions
+            WHERE guild_id = $1
+            AND id = $2
+        ",
+        guild_id.to_string(),
+        exemption_id
+    )
+    .execute(&ctx.data().pool)
+    .await?;
+
+    if res.rows_affected() == 0 {
+        return Err("Could not find exemption".into());
+    }
+
+    super::store::store()
+        .await?
+        .invalidate_guild(guild_id)
+        .await?;
+
+    ctx.say("Removed exemption successfully").await?;
+
+    Ok(())
+}
+
+/// List the users, roles and applications that are exempt from limits
+#[poise::command(prefix_command, slash_command, guild_only, rename = "list")]
+pub async fn limits_exempt_list(ctx: Context<'_>) -> Result<(), Error> {
+    let exemptions = Exemption::from_database(
+        &ctx.data().pool,
+        ctx.guild_id().ok_or("Could not get guild id")?,
+    )
+    .await?;
+
+    if exemptions.is_empty() {
+        ctx.say("No exemptions setup for this server, use ``/limits exempt add`` to add one!")
+            .await?;
+        return Ok(());
+    }
+
+    let mut reply = CreateReply::new();
+
+    for chunk in exemptions.chunks(MAX_EMBED_FIELDS) {
+        let mut embed = CreateEmbed::default()
+            .title("Limit Exemptions")
+            .color(0x00ff00);
+
+        for exemption in chunk {
+            embed = embed.field(
+                exemption.id.clone(),
+                format!(
+                    "{}\nExempt from: {}\nCreated by {} <t:{}:R>",
+                    match exemption.exemption_type {
+                        ExemptionType::User => format!("User <@{}>", exemption.target),
+                        ExemptionType::Role => format!("Role <@&{}>", exemption.target),
+                        ExemptionType::Application => {
+                            format!("Application ``{}``", exemption.target)
+                        }
+                    },
+                    match exemption.limit_type {
+                        Some(limit_type) => format!("``{}``", limit_type.to_cond()),
+                        None => "All limits".to_string(),
+                    },
+                    exemption.created_by.mention(),
+                    exemption.created_at.timestamp()
+                ),
+                false,
+            );
+        }
+
+        reply = reply.embed(embed);
+    }
+
+    ctx.send(reply).await?;
+
+    Ok(())
+}
+
 /// Action management
 #[poise::command(
     prefix_command,
@@ -290,6 +866,67 @@ pub async fn limitactions_view(
     Ok(())
 }
 
+/// Resolve the incidents created when users hit limits
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only,
+    rename = "incident",
+    subcommands("limits_incident_undo", "limits_incident_ban")
+)]
+pub async fn limits_incident(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Resolves an incident, replying with any actions that could not be completed
+async fn resolve_incident(
+    ctx: Context<'_>,
+    incident_id: &str,
+    resolution: Resolution,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("Could not get guild id")?;
+
+    let notes = super::incidents::resolve_incident(
+        &ctx.data().pool,
+        &CacheHttpImpl::from_ctx(ctx.serenity_context()),
+        guild_id,
+        incident_id,
+        resolution,
+        ctx.author().id,
+    )
+    .await?;
+
+    if notes.is_empty() {
+        ctx.say("Resolved incident successfully").await?;
+    } else {
+        ctx.say(format!(
+            "Resolved incident with errors:\n- {}",
+            notes.join("\n- ")
+        ))
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Undo the punishment of a user who hit a limit
+#[poise::command(prefix_command, slash_command, guild_only, rename = "undo")]
+pub async fn limits_incident_undo(
+    ctx: Context<'_>,
+    #[description = "The ID of the incident"] incident_id: String,
+) -> Result<(), Error> {
+    resolve_incident(ctx, &incident_id, Resolution::Undone).await
+}
+
+/// Ban a user who hit a limit
+#[poise::command(prefix_command, slash_command, guild_only, rename = "ban")]
+pub async fn limits_incident_ban(
+    ctx: Context<'_>,
+    #[description = "The ID of the incident"] incident_id: String,
+) -> Result<(), Error> {
+    resolve_incident(ctx, &incident_id, Resolution::Banned).await
+}
+
 /// View hit limits
 #[poise::command(prefix_command, slash_command, guild_only, rename = "hit")]
 pub async fn limits_hit(ctx: Context<'_>) -> Result<(), Error> {
@@ -1,13 +1,13 @@
-use poise::serenity_prelude::{GuildId, UserId};
+use poise::serenity_prelude::{ApplicationId, GuildId, Permissions, RoleId, UserId};
 use serde::{Deserialize, Serialize};
 use sqlx::{
     types::chrono::{DateTime, Utc},
     PgPool,
 };
+use strum::VariantNames as _;
 use strum_macros::{Display, EnumString, VariantNames};
-use surrealdb::engine::remote::ws::Client;
-use surrealdb::Surreal;
 
+use super::store::LimitStore;
 use crate::impls::utils::pg_interval_to_secs;
 use crate::Error;
 
@@ -37,6 +37,20 @@ pub enum UserLimitTypesChoices {
     Ban,
     #[name = "Unban"]
     Unban,
+    #[name = "Webhook Create"]
+    WebhookCreate,
+    #[name = "Emoji Delete"]
+    EmojiDelete,
+    #[name = "Sticker Delete"]
+    StickerDelete,
+    #[name = "Invite Create"]
+    InviteCreate,
+    #[name = "Integration Create"]
+    IntegrationCreate,
+    #[name = "Server Update"]
+    GuildUpdate,
+    #[name = "@everyone/@here Mention"]
+    EveryoneMention,
 }
 
 impl UserLimitTypesChoices {
@@ -54,6 +68,13 @@ impl UserLimitTypesChoices {
             Self::Kick => UserLimitTypes::Kick,
             Self::Ban => UserLimitTypes::Ban,
             Self::Unban => UserLimitTypes::Unban,
+            Self::WebhookCreate => UserLimitTypes::WebhookCreate,
+            Self::EmojiDelete => UserLimitTypes::EmojiDelete,
+            Self::StickerDelete => UserLimitTypes::StickerDelete,
+            Self::InviteCreate => UserLimitTypes::InviteCreate,
+            Self::IntegrationCreate => UserLimitTypes::IntegrationCreate,
+            Self::GuildUpdate => UserLimitTypes::GuildUpdate,
+            Self::EveryoneMention => UserLimitTypes::EveryoneMention,
         }
     }
 }
@@ -85,6 +106,13 @@ pub enum UserLimitTypes {
     Kick,
     Ban,
     Unban,
+    WebhookCreate,     // set
+    EmojiDelete,       // set
+    StickerDelete,     // set
+    InviteCreate,      // set
+    IntegrationCreate, // set
+    GuildUpdate,       // set
+    EveryoneMention,   // set, from message events
 }
 
 impl UserLimitTypes {
@@ -102,13 +130,19 @@ impl UserLimitTypes {
             Self::Kick => "Kicks".to_string(),
             Self::Ban => "Bans".to_string(),
             Self::Unban => "Unbans".to_string(),
+            Self::WebhookCreate => "Webhooks Created".to_string(),
+            Self::EmojiDelete => "Emojis Deleted".to_string(),
+            Self::StickerDelete => "Stickers Deleted".to_string(),
+            Self::InviteCreate => "Invites Created".to_string(),
+            Self::IntegrationCreate => "Integrations Added".to_string(),
+            Self::GuildUpdate => "Server Updates".to_string(),
+            Self::EveryoneMention => "@everyone/@here Mentions".to_string(),
         }
     }
 
     /// Returns the default repeat rate for a user-target pair
     ///
     /// Within this rate, limits will be ignored if the same user-target pair has already been handled
-    #[allow(dead_code)] // May be used in the future
     pub fn default_user_target_repeat_rate(&self) -> i64 {
         match self {
             Self::RoleGivenToMember => 2,     // 2 seconds
@@ -127,6 +161,14 @@ pub enum UserLimitActionsChoices {
     KickUser,
     #[name = "Ban User"]
     BanUser,
+    #[name = "Timeout User"]
+    TimeoutUser,
+    #[name = "Strip Dangerous Roles"]
+    StripDangerousRoles,
+    #[name = "Quarantine User"]
+    QuarantineUser,
+    #[name = "Alert Only"]
+    AlertOnly,
 }
 
 impl UserLimitActionsChoices {
@@ -135,10 +177,26 @@ impl UserLimitActionsChoices {
             Self::RemoveAllRoles => UserLimitActions::RemoveAllRoles,
             Self::KickUser => UserLimitActions::KickUser,
             Self::BanUser => UserLimitActions::BanUser,
+            Self::TimeoutUser => UserLimitActions::TimeoutUser,
+            Self::StripDangerousRoles => UserLimitActions::StripDangerousRoles,
+            Self::QuarantineUser => UserLimitActions::QuarantineUser,
+            Self::AlertOnly => UserLimitActions::AlertOnly,
         }
     }
 }
 
+/// Roles with any of these permissions are removed by [`UserLimitActions::StripDangerousRoles`]
+pub const DANGEROUS_PERMISSIONS: Permissions = Permissions::ADMINISTRATOR
+    .union(Permissions::MANAGE_GUILD)
+    .union(Permissions::MANAGE_ROLES)
+    .union(Permissions::MANAGE_CHANNELS)
+    .union(Permissions::MANAGE_WEBHOOKS)
+    .union(Permissions::MANAGE_GUILD_EXPRESSIONS)
+    .union(Permissions::KICK_MEMBERS)
+    .union(Permissions::BAN_MEMBERS)
+    .union(Permissions::MODERATE_MEMBERS)
+    .union(Permissions::MENTION_EVERYONE);
+
 #[derive(
     EnumString, Display, PartialEq, VariantNames, Clone, Debug, Serialize, Deserialize,
 )]
@@ -147,6 +205,14 @@ pub enum UserLimitActions {
     RemoveAllRoles,
     KickUser,
     BanUser,
+    /// Times out the user for the timeout duration of the limit
+    TimeoutUser,
+    /// Removes only the roles of the user with dangerous permissions
+    StripDangerousRoles,
+    /// Removes all roles of the user and gives them the quarantine role of the limit
+    QuarantineUser,
+    /// Takes no action against the user, the hit is only recorded
+    AlertOnly,
 }
 
 impl UserLimitActions {
@@ -155,11 +221,106 @@ impl UserLimitActions {
             Self::RemoveAllRoles => "Remove All Roles".to_string(),
             Self::KickUser => "Kick User".to_string(),
             Self::BanUser => "Ban User".to_string(),
+            Self::TimeoutUser => "Timeout User".to_string(),
+            Self::StripDangerousRoles => "Strip Dangerous Roles".to_string(),
+            Self::QuarantineUser => "Quarantine User".to_string(),
+            Self::AlertOnly => "Alert Only".to_string(),
+        }
+    }
+
+    /// Returns how severe the action is, when several actions are taken at once they are taken from least to most severe
+    pub fn severity(&self) -> u8 {
+        match self {
+            Self::AlertOnly => 0,
+            Self::TimeoutUser => 1,
+            Self::StripDangerousRoles => 2,
+            Self::QuarantineUser => 3,
+            Self::RemoveAllRoles => 4,
+            Self::KickUser => 5,
+            Self::BanUser => 6,
+        }
+    }
+}
+
+/// Parses escalation tiers in the format ``<actions>;<actions>;...`` where each tier is a comma-separated
+/// list of actions (e.g. ``timeout_user;strip_dangerous_roles,alert_only;ban_user``)
+pub fn parse_limit_tiers(tiers: &str) -> Result<Vec<Vec<UserLimitActions>>, Error> {
+    let mut parsed = Vec::new();
+
+    for tier in tiers.split(';') {
+        let mut actions = Vec::new();
+
+        for action in tier.split(',') {
+            let action = action.trim();
+
+            if action.is_empty() {
+                continue;
+            }
+
+            let action: UserLimitActions = action.parse().map_err(|_| {
+                format!(
+                    "``{}`` is not a valid action. Valid actions are: {}",
+                    action,
+                    UserLimitActions::VARIANTS.join(", ")
+                )
+            })?;
+
+            if !actions.contains(&action) {
+                actions.push(action);
+            }
+        }
+
+        if actions.is_empty() {
+            return Err("Each escalation tier must have at least one action".into());
+        }
+
+        parsed.push(actions);
+    }
+
+    Ok(parsed)
+}
+
+/// Whether the actions of a limit are taken when it is hit
+#[derive(
+    EnumString,
+    Display,
+    PartialEq,
+    VariantNames,
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    Serialize,
+    Deserialize,
+)]
+#[strum(serialize_all = "snake_case")]
+pub enum LimitMode {
+    /// The actions of the limit are taken when it is hit
+    #[default]
+    Enforce,
+    /// Hits of the limit are only recorded, no actions are taken. Useful to find false positives before
+    /// enforcing a limit
+    Monitor,
+}
+
+#[derive(poise::ChoiceParameter)]
+pub enum LimitModeChoices {
+    #[name = "Enforce"]
+    Enforce,
+    #[name = "Monitor"]
+    Monitor,
+}
+
+impl LimitModeChoices {
+    pub fn resolve(self) -> LimitMode {
+        match self {
+            Self::Enforce => LimitMode::Enforce,
+            Self::Monitor => LimitMode::Monitor,
         }
     }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct UserAction {
     /// The ID of the action
     pub action_id: String,
@@ -276,6 +437,46 @@ impl UserAction {
 
         Ok(actions)
     }
+
+    /// Fetch the user actions of a limit type in a guild from the last `days` days
+    pub async fn guild_recent(
+        pool: &PgPool,
+        guild_id: GuildId,
+        limit_type: UserLimitTypes,
+        days: i32,
+    ) -> Result<Vec<Self>, Error> {
+        let rec = sqlx::query!(
+            "
+                SELECT action_id, limit_type, created_at, user_id, action_data, 
+                limits_hit, target FROM limits__user_actions
+                WHERE guild_id = $1
+                AND limit_type = $2
+                AND created_at > NOW() - make_interval(days => $3)
+            ",
+            guild_id.to_string(),
+            limit_type.to_string(),
+            days
+        )
+        .fetch_all(pool)
+        .await?;
+
+        let mut actions = Vec::new();
+
+        for r in rec {
+            actions.push(Self {
+                guild_id,
+                action_id: r.action_id,
+                limit_type: r.limit_type.parse()?,
+                created_at: r.created_at,
+                user_id: r.user_id.parse()?,
+                action_data: r.action_data,
+                limits_hit: r.limits_hit,
+                target: r.target,
+            });
+        }
+
+        Ok(actions)
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -294,14 +495,45 @@ pub struct Limit {
     pub limit_per: i32,
     /// The time frame, in seconds the limit can be hit in
     pub limit_time: i64,
+    /// The actions to take on each hit of the limit within the escalation window, the last tier is repeated
+    /// on further hits. If empty, `limit_action` is taken on every hit
+    #[serde(default)]
+    pub limit_tiers: Vec<Vec<UserLimitActions>>,
+    /// The time frame, in seconds, previous hits are counted in when escalating
+    #[serde(default)]
+    pub escalation_window: i64,
+    /// The duration, in seconds, of timeouts given by this limit
+    #[serde(default)]
+    pub timeout_duration: i64,
+    /// The role given to users quarantined by this limit
+    #[serde(default)]
+    pub quarantine_role: Option<RoleId>,
+    /// Whether to recreate the channels and roles deleted by users who hit the limit
+    #[serde(default)]
+    pub rollback: bool,
+    /// Whether the actions of the limit are taken when it is hit
+    #[serde(default)]
+    pub mode: LimitMode,
 }
 
 impl Limit {
+    /// Returns the actions to take on the given hit (starting at 1) of the limit within the escalation window
+    pub fn tier_actions(&self, hit: usize) -> Vec<UserLimitActions> {
+        if self.limit_tiers.is_empty() {
+            return vec![self.limit_action.clone()];
+        }
+
+        let tier = hit.clamp(1, self.limit_tiers.len()) - 1;
+
+        self.limit_tiers[tier].clone()
+    }
+
     pub async fn from_database(pool: &PgPool, guild_id: GuildId) -> Result<Vec<Self>, Error> {
         let rec = sqlx::query!(
             "
                 SELECT limit_id, limit_name, limit_type, limit_action, limit_per, 
-                limit_time AS limit_time FROM limits__guild_limits
+                limit_time AS limit_time, limit_tiers, escalation_window, timeout_duration,
+                quarantine_role, rollback, mode FROM limits__guild_limits
                 WHERE guild_id = $1
             ",
             guild_id.to_string()
@@ -318,6 +550,16 @@ impl Limit {
         let mut limits = Vec::new();
 
         for r in rec {
+            let mut limit_tiers = Vec::new();
+
+            for tier in serde_json::from_value::<Vec<Vec<String>>>(r.limit_tiers)? {
+                limit_tiers.push(
+                    tier.iter()
+                        .map(|a| a.parse())
+                        .collect::<Result<Vec<UserLimitActions>, _>>()?,
+                );
+            }
+
             limits.push(Self {
                 guild_id,
                 limit_id: r.limit_id,
@@ -326,37 +568,151 @@ impl Limit {
                 limit_action: r.limit_action.parse()?,
                 limit_per: r.limit_per,
                 limit_time: pg_interval_to_secs(r.limit_time),
+                limit_tiers,
+                escalation_window: pg_interval_to_secs(r.escalation_window),
+                timeout_duration: pg_interval_to_secs(r.timeout_duration),
+                quarantine_role: r.quarantine_role.map(|r| r.parse()).transpose()?,
+                rollback: r.rollback,
+                mode: r.mode.parse()?,
             });
         }
         Ok(limits)
     }
-    pub async fn from_cache(
-        cache: &Surreal<Client>,
+
+    /// Fetches the limits of a guild, using the store as a cache
+    pub async fn fetch(
+        store: &impl LimitStore,
+        pool: &PgPool,
         guild_id: GuildId,
     ) -> Result<Vec<Self>, Error> {
-        let mut request = cache
-            .query("select guild_id, limit_id, limit_name, limit_type, limit_action, limit_per, limit_time from guild_limits where guild_id = type::string($guild_id)")
-            .bind(("guild_id", guild_id.to_string()))
-            .await?;
+        if let Some(limits) = store.cached_limits(guild_id).await? {
+            return Ok(limits);
+        }
+
+        let limits = Self::from_database(pool, guild_id).await?;
+        store.cache_limits(guild_id, &limits).await?;
+        Ok(limits)
+    }
+}
+
+/// What an exemption applies to
+#[derive(
+    EnumString, Display, PartialEq, VariantNames, Clone, Copy, Debug, Serialize, Deserialize,
+)]
+#[strum(serialize_all = "snake_case")]
+pub enum ExemptionType {
+    /// A single user
+    User,
+    /// All members with a role
+    Role,
+    /// A bot or integration, matched by its application ID. As the user ID of a bot is its
+    /// application ID, this also covers the bot user itself
+    Application,
+}
+
+/// An exemption from the limits of a guild, actions by exempt users are never recorded
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Exemption {
+    /// The ID of the exemption
+    pub id: String,
+    /// The ID of the guild this exemption is for
+    pub guild_id: GuildId,
+    /// What the exemption applies to
+    pub exemption_type: ExemptionType,
+    /// The ID of the user, role or application that is exempt
+    pub target: String,
+    /// The limit type the exemption is scoped to. If None, the exemption applies to all limit types
+    pub limit_type: Option<UserLimitTypes>,
+    /// When the exemption was created
+    pub created_at: DateTime<Utc>,
+    /// The user who created the exemption
+    pub created_by: UserId,
+}
+
+impl Exemption {
+    /// Returns whether the exemption applies to the given limit type
+    pub fn applies_to(&self, limit_type: UserLimitTypes) -> bool {
+        self.limit_type.map(|l| l == limit_type).unwrap_or(true)
+    }
+
+    /// Returns whether the exemption covers a user with the given roles and application ID
+    pub fn covers(
+        &self,
+        user_id: UserId,
+        roles: &[RoleId],
+        application_id: Option<ApplicationId>,
+    ) -> bool {
+        match self.exemption_type {
+            ExemptionType::User => self.target == user_id.to_string(),
+            ExemptionType::Role => roles.iter().any(|r| self.target == r.to_string()),
+            ExemptionType::Application => {
+                self.target == user_id.to_string()
+                    || application_id
+                        .map(|a| self.target == a.to_string())
+                        .unwrap_or_default()
+            }
+        }
+    }
+
+    /// Fetch all exemptions of a guild from the database
+    pub async fn from_database(pool: &PgPool, guild_id: GuildId) -> Result<Vec<Self>, Error> {
+        let rec = sqlx::query!(
+            "
+                SELECT id, exemption_type, target, limit_type, created_at, created_by
+                FROM limits__exemptions
+                WHERE guild_id = $1
+            ",
+            guild_id.to_string()
+        )
+        .fetch_all(pool)
+        .await?;
+
+        let mut exemptions = Vec::new();
+
+        for r in rec {
+            exemptions.push(Self {
+                id: r.id,
+                guild_id,
+                exemption_type: r.exemption_type.parse()?,
+                target: r.target,
+                limit_type: r.limit_type.map(|l| l.parse()).transpose()?,
+                created_at: r.created_at,
+                created_by: r.created_by.parse()?,
+            });
+        }
 
-        let records: Vec<Limit> = request.take(0)?;
-        Ok(records)
+        Ok(exemptions)
     }
 
+    /// Fetches the exemptions of a guild, using the store as a cache
     pub async fn fetch(
-        cache: &Surreal<Client>,
+        store: &impl LimitStore,
         pool: &PgPool,
         guild_id: GuildId,
     ) -> Result<Vec<Self>, Error> {
-        let cache = Self::from_cache(cache, guild_id).await?;
-        if cache.is_empty() {
-            let db = Self::from_database(pool, guild_id).await?;
-            return Ok(db);
+        if let Some(exemptions) = store.cached_exemptions(guild_id).await? {
+            return Ok(exemptions);
         }
-        Ok(cache)
+
+        let exemptions = Self::from_database(pool, guild_id).await?;
+        store.cache_exemptions(guild_id, &exemptions).await?;
+        Ok(exemptions)
     }
 }
 
+/// Returns whether any of the exemptions covers a user for the given limit type
+pub fn is_exempt(
+    exemptions: &[Exemption],
+    limit_type: UserLimitTypes,
+    user_id: UserId,
+    roles: &[RoleId],
+    application_id: Option<ApplicationId>,
+) -> bool {
+    exemptions
+        .iter()
+        .any(|e| e.applies_to(limit_type) && e.covers(user_id, roles, application_id))
+}
+
 #[derive(Debug, Serialize)]
 pub struct PastHitLimits {
     pub id: String,
@@ -404,3 +760,101 @@ impl PastHitLimits {
         Ok(hits)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_limit_tiers() {
+        assert_eq!(
+            parse_limit_tiers("timeout_user; strip_dangerous_roles, alert_only ;ban_user").unwrap(),
+            vec![
+                vec![UserLimitActions::TimeoutUser],
+                vec![
+                    UserLimitActions::StripDangerousRoles,
+                    UserLimitActions::AlertOnly
+                ],
+                vec![UserLimitActions::BanUser],
+            ]
+        );
+        assert_eq!(
+            parse_limit_tiers("kick_user,kick_user").unwrap(),
+            vec![vec![UserLimitActions::KickUser]]
+        );
+        assert!(parse_limit_tiers("timeout_user;;ban_user").is_err());
+        assert!(parse_limit_tiers("timeout_user;nuke_user").is_err());
+    }
+
+    #[test]
+    fn test_tier_actions() {
+        let mut limit = Limit {
+            guild_id: GuildId::new(1),
+            limit_id: "l".to_string(),
+            limit_name: "l".to_string(),
+            limit_type: UserLimitTypes::ChannelRemove,
+            limit_action: UserLimitActions::KickUser,
+            limit_per: 1,
+            limit_time: 60,
+            limit_tiers: vec![],
+            escalation_window: 86400,
+            timeout_duration: 600,
+            quarantine_role: None,
+            rollback: false,
+            mode: LimitMode::Enforce,
+        };
+
+        assert_eq!(limit.tier_actions(1), vec![UserLimitActions::KickUser]);
+        assert_eq!(limit.tier_actions(5), vec![UserLimitActions::KickUser]);
+
+        limit.limit_tiers = parse_limit_tiers("timeout_user;strip_dangerous_roles;ban_user").unwrap();
+
+        assert_eq!(limit.tier_actions(1), vec![UserLimitActions::TimeoutUser]);
+        assert_eq!(limit.tier_actions(2), vec![UserLimitActions::StripDangerousRoles]);
+        assert_eq!(limit.tier_actions(3), vec![UserLimitActions::BanUser]);
+        assert_eq!(limit.tier_actions(4), vec![UserLimitActions::BanUser]);
+    }
+
+    #[test]
+    fn test_is_exempt() {
+        let exemption = |exemption_type, target: &str, limit_type| Exemption {
+            id: target.to_string(),
+            guild_id: GuildId::new(1),
+            exemption_type,
+            target: target.to_string(),
+            limit_type,
+            created_at: Utc::now(),
+            created_by: UserId::new(1),
+        };
+
+        let exemptions = vec![
+            exemption(ExemptionType::User, "10", None),
+            exemption(
+                ExemptionType::Role,
+                "20",
+                Some(UserLimitTypes::ChannelRemove),
+            ),
+            exemption(ExemptionType::Application, "30", None),
+        ];
+
+        let (ch, rr) = (UserLimitTypes::ChannelRemove, UserLimitTypes::RoleRemove);
+        let (exempt, other) = (UserId::new(10), UserId::new(11));
+        let roles = [RoleId::new(20)];
+
+        // Users are exempt from all limit types
+        assert!(is_exempt(&exemptions, ch, exempt, &[], None));
+        assert!(is_exempt(&exemptions, rr, exempt, &[], None));
+        assert!(!is_exempt(&exemptions, ch, other, &[], None));
+
+        // Roles are scoped to their limit type
+        assert!(is_exempt(&exemptions, ch, other, &roles, None));
+        assert!(!is_exempt(&exemptions, rr, other, &roles, None));
+
+        // Applications match both the bot user and actions done through the application
+        let (app, other_app) = (ApplicationId::new(30), ApplicationId::new(31));
+
+        assert!(is_exempt(&exemptions, rr, UserId::new(30), &[], None));
+        assert!(is_exempt(&exemptions, rr, other, &[], Some(app)));
+        assert!(!is_exempt(&exemptions, rr, other, &[], Some(other_app)));
+    }
+}
@@ -1,18 +1,108 @@
 use log::{error, info};
-use poise::serenity_prelude::{Change, FullEvent, MemberAction, RoleAction};
-use serenity::model::guild::audit_log::{Action, ChannelAction};
+use moka::future::Cache;
+use once_cell::sync::Lazy;
+use poise::serenity_prelude::{
+    Change, FullEvent, GuildId, Interaction, MemberAction, Message, RoleAction, UserId, WebhookId,
+};
+use serenity::model::guild::audit_log::{
+    Action, AuditLogEntry, ChannelAction, EmojiAction, IntegrationAction, InviteAction,
+    StickerAction, WebhookAction,
+};
 
+use super::core::UserLimitTypes;
 use super::handler::handle_mod_action;
+use super::store::Store;
+use crate::impls::cache::CacheHttpImpl;
 use crate::{Data, Error, silverpelt::EventHandlerContext};
 
+/// The creators of recently seen webhooks, None if the webhook has no (known) creator
+///
+/// Webhooks can send many messages in a short time, so their creator is only fetched once in a while
+static WEBHOOK_CREATORS: Lazy<Cache<WebhookId, Option<UserId>>> = Lazy::new(|| {
+    Cache::builder()
+        .time_to_live(std::time::Duration::from_secs(300))
+        .build()
+});
+
+/// Handles an audit log entry as an action of the given limit type on the target of the entry
+///
+/// Entries without a target (such as invites) use the ID of the entry itself as the target
+async fn handle_audit_log_entry(
+    pool: &sqlx::PgPool,
+    store: &Store,
+    cache_http: &CacheHttpImpl,
+    guild_id: GuildId,
+    entry: &AuditLogEntry,
+    limit: UserLimitTypes,
+) -> Result<(), Error> {
+    let target = entry
+        .target_id
+        .map(|t| t.to_string())
+        .unwrap_or_else(|| entry.id.to_string());
+
+    info!("{}: {}", limit.to_cond(), target);
+
+    // Deleted channels and roles are snapshotted by their delete events so they can be rolled back
+    let snapshot = match limit {
+        UserLimitTypes::ChannelRemove | UserLimitTypes::RoleRemove => {
+            super::rollback::take_snapshot(guild_id, &target).await
+        }
+        _ => None,
+    };
+
+    handle_mod_action(
+        pool,
+        store,
+        cache_http,
+        &super::handler::HandleModAction {
+            guild_id,
+            limit,
+            user_id: entry.user_id,
+            target,
+            action_data: serde_json::json!({
+                "snapshot": snapshot,
+                "changes": entry.changes,
+            }),
+        },
+    )
+    .await
+}
+
+/// Returns the user a message is attributed to for limits
+///
+/// Webhook messages are attributed to the creator of the webhook as the webhook itself cannot be punished.
+/// Messages of webhooks without a (known) creator, such as interaction responses, fall back to their author
+async fn message_author(cache_http: &CacheHttpImpl, message: &Message) -> UserId {
+    let Some(webhook_id) = message.webhook_id else {
+        return message.author.id;
+    };
+
+    if let Some(creator) = WEBHOOK_CREATORS.get(&webhook_id).await {
+        return creator.unwrap_or(message.author.id);
+    }
+
+    match cache_http.http.get_webhook(webhook_id).await {
+        Ok(webhook) => {
+            let creator = webhook.user.map(|u| u.id);
+            WEBHOOK_CREATORS.insert(webhook_id, creator).await;
+            creator.unwrap_or(message.author.id)
+        }
+        Err(e) => {
+            error!("Failed to fetch webhook {}: {}", webhook_id, e);
+            message.author.id
+        }
+    }
+}
+
 pub async fn event_listener(
     ctx: &serenity::client::Context,
     event: &FullEvent,
     _: EventHandlerContext,
 ) -> Result<(), Error> {
     let user_data = ctx.data::<Data>();
+    let store = super::store::store().await?;
 
-    let cache_http = crate::impls::cache::CacheHttpImpl {
+    let cache_http = CacheHttpImpl {
         cache: ctx.cache.clone(),
         http: ctx.http.clone(),
     };
@@ -22,120 +112,71 @@ pub async fn event_listener(
             info!("Audit log created: {:?}. Guild: {}", entry, guild_id);
 
             let res = match entry.action {
-                Action::Channel(ch) => {
-                    let ch_id = entry.target_id.ok_or("No channel ID found")?;
-
-                    match ch {
-                        ChannelAction::Create => {
-                            info!("Channel created: {}", ch_id);
-
-                            handle_mod_action(
-                                &user_data.pool,
-                                &user_data.surreal_cache,
-                                &cache_http,
-                                &super::handler::HandleModAction {
-                                    guild_id: *guild_id,
-                                    limit: super::core::UserLimitTypes::ChannelAdd,
-                                    user_id: entry.user_id,
-                                    target: ch_id.to_string(),
-                                    action_data: serde_json::json!({}),
-                                },
-                            )
-                            .await
-                        }
-                        ChannelAction::Delete => {
-                            info!("Channel deleted: {}", ch_id);
-
-                            handle_mod_action(
-                                &user_data.pool,
-                                &user_data.surreal_cache,
-                                &cache_http,
-                                &super::handler::HandleModAction {
-                                    guild_id: *guild_id,
-                                    limit: super::core::UserLimitTypes::ChannelRemove,
-                                    user_id: entry.user_id,
-                                    target: ch_id.to_string(),
-                                    action_data: serde_json::json!({}),
-                                },
-                            )
-                            .await
-                        }
-                        ChannelAction::Update => {
-                            info!("Channel updated: {}", ch_id);
-                            handle_mod_action(
-                                &user_data.pool,
-                                &user_data.surreal_cache,
-                                &cache_http,
-                                &super::handler::HandleModAction {
-                                    guild_id: *guild_id,
-                                    limit: super::core::UserLimitTypes::ChannelUpdate,
-                                    user_id: entry.user_id,
-                                    target: ch_id.to_string(),
-                                    action_data: serde_json::json!({}),
-                                },
-                            )
-                            .await
-                        }
-                        _ => Ok(()),
-                    }
+                Action::Channel(ChannelAction::Create) => {
+                    handle_audit_log_entry(
+                        &user_data.pool,
+                        store,
+                        &cache_http,
+                        *guild_id,
+                        entry,
+                        UserLimitTypes::ChannelAdd,
+                    )
+                    .await
                 }
-                Action::Role(ra) => {
-                    let r_id = entry.target_id.ok_or("No role ID found")?;
-
-                    match ra {
-                        RoleAction::Create => {
-                            info!("Role created: {}", r_id);
-
-                            handle_mod_action(
-                                &user_data.pool,
-                                &user_data.surreal_cache,
-                                &cache_http,
-                                &super::handler::HandleModAction {
-                                    guild_id: *guild_id,
-                                    limit: super::core::UserLimitTypes::RoleAdd,
-                                    user_id: entry.user_id,
-                                    target: r_id.to_string(),
-                                    action_data: serde_json::json!({}),
-                                },
-                            )
-                            .await
-                        }
-                        RoleAction::Update => {
-                            info!("Role updated: {}", r_id);
-
-                            handle_mod_action(
-                                &user_data.pool,
-                                &user_data.surreal_cache,
-                                &cache_http,
-                                &super::handler::HandleModAction {
-                                    guild_id: *guild_id,
-                                    limit: super::core::UserLimitTypes::RoleUpdate,
-                                    user_id: entry.user_id,
-                                    target: r_id.to_string(),
-                                    action_data: serde_json::json!({}),
-                                },
-                            )
-                            .await
-                        }
-                        RoleAction::Delete => {
-                            info!("Role deleted: {}", r_id);
-
-                            handle_mod_action(
-                                &user_data.pool,
-                                &user_data.surreal_cache,
-                                &cache_http,
-                                &super::handler::HandleModAction {
-                                    guild_id: *guild_id,
-                                    limit: super::core::UserLimitTypes::RoleRemove,
-                                    user_id: entry.user_id,
-                                    target: r_id.to_string(),
-                                    action_data: serde_json::json!({}),
-                                },
-                            )
-                            .await
-                        }
-                        _ => Ok(()),
-                    }
+                Action::Channel(ChannelAction::Update) => {
+                    handle_audit_log_entry(
+                        &user_data.pool,
+                        store,
+                        &cache_http,
+                        *guild_id,
+                        entry,
+                        UserLimitTypes::ChannelUpdate,
+                    )
+                    .await
+                }
+                Action::Channel(ChannelAction::Delete) => {
+                    handle_audit_log_entry(
+                        &user_data.pool,
+                        store,
+                        &cache_http,
+                        *guild_id,
+                        entry,
+                        UserLimitTypes::ChannelRemove,
+                    )
+                    .await
+                }
+                Action::Role(RoleAction::Create) => {
+                    handle_audit_log_entry(
+                        &user_data.pool,
+                        store,
+                        &cache_http,
+                        *guild_id,
+                        entry,
+                        UserLimitTypes::RoleAdd,
+                    )
+                    .await
+                }
+                Action::Role(RoleAction::Update) => {
+                    handle_audit_log_entry(
+                        &user_data.pool,
+                        store,
+                        &cache_http,
+                        *guild_id,
+                        entry,
+                        UserLimitTypes::RoleUpdate,
+                    )
+                    .await
+                }
+                Action::Role(RoleAction::Delete) => {
+                    handle_audit_log_entry(
+                        &user_data.pool,
+                        store,
+                        &cache_http,
+                        *guild_id,
+                        entry,
+                        UserLimitTypes::RoleRemove,
+                    )
+                    .await
                 }
                 // DEAL WITH THIS HELL LATER.
                 Action::Member(ma) => {
@@ -210,7 +251,7 @@ pub async fn event_listener(
 
                             handle_mod_action(
                                 &user_data.pool,
-                                &user_data.surreal_cache,
+                                store,
                                 &cache_http,
                                 &super::handler::HandleModAction {
                                     guild_id: *guild_id,
@@ -231,7 +272,7 @@ pub async fn event_listener(
 
                                 handle_mod_action(
                                     &user_data.pool,
-                                    &user_data.surreal_cache,
+                                    store,
                                     &cache_http,
                                     &super::handler::HandleModAction {
                                         guild_id: *guild_id,
@@ -253,7 +294,7 @@ pub async fn event_listener(
 
                                 handle_mod_action(
                                     &user_data.pool,
-                                    &user_data.surreal_cache,
+                                    store,
                                     &cache_http,
                                     &super::handler::HandleModAction {
                                         guild_id: *guild_id,
@@ -275,6 +316,72 @@ pub async fn event_listener(
 
                     Ok(())
                 }
+                Action::Webhook(WebhookAction::Create) => {
+                    handle_audit_log_entry(
+                        &user_data.pool,
+                        store,
+                        &cache_http,
+                        *guild_id,
+                        entry,
+                        UserLimitTypes::WebhookCreate,
+                    )
+                    .await
+                }
+                Action::Emoji(EmojiAction::Delete) => {
+                    handle_audit_log_entry(
+                        &user_data.pool,
+                        store,
+                        &cache_http,
+                        *guild_id,
+                        entry,
+                        UserLimitTypes::EmojiDelete,
+                    )
+                    .await
+                }
+                Action::Sticker(StickerAction::Delete) => {
+                    handle_audit_log_entry(
+                        &user_data.pool,
+                        store,
+                        &cache_http,
+                        *guild_id,
+                        entry,
+                        UserLimitTypes::StickerDelete,
+                    )
+                    .await
+                }
+                Action::Invite(InviteAction::Create) => {
+                    handle_audit_log_entry(
+                        &user_data.pool,
+                        store,
+                        &cache_http,
+                        *guild_id,
+                        entry,
+                        UserLimitTypes::InviteCreate,
+                    )
+                    .await
+                }
+                Action::Integration(IntegrationAction::Create) => {
+                    handle_audit_log_entry(
+                        &user_data.pool,
+                        store,
+                        &cache_http,
+                        *guild_id,
+                        entry,
+                        UserLimitTypes::IntegrationCreate,
+                    )
+                    .await
+                }
+                Action::GuildUpdate => {
+                    handle_audit_log_entry(
+                        &user_data.pool,
+                        store,
+                        &cache_http,
+                        *guild_id,
+                        entry,
+                        UserLimitTypes::GuildUpdate,
+                    )
+                    .await
+                }
                 _ => Ok(()),
             };
 
@@ -285,6 +392,74 @@ pub async fn event_listener(
 
             Ok(())
         }
+        FullEvent::ChannelDelete { channel, .. }
+        | FullEvent::CategoryDelete { category: channel } => {
+            super::rollback::stash_snapshot(
+                channel.guild_id,
+                channel.id.to_string(),
+                serde_json::to_value(channel)?,
+            )
+            .await;
+
+            Ok(())
+        }
+        FullEvent::GuildRoleDelete {
+            guild_id,
+            removed_role_id,
+            removed_role_data_if_available,
+        } => {
+            if let Some(role) = removed_role_data_if_available {
+                super::rollback::stash_snapshot(
+                    *guild_id,
+                    removed_role_id.to_string(),
+                    serde_json::to_value(role)?,
+                )
+                .await;
+            }
+
+            Ok(())
+        }
+        FullEvent::Message { new_message, .. } => {
+            if !new_message.mention_everyone {
+                return Ok(());
+            }
+
+            let Some(guild_id) = new_message.guild_id else {
+                return Ok(());
+            };
+
+            if new_message.author.id == ctx.cache.current_user().id {
+                return Ok(());
+            }
+
+            let user_id = message_author(&cache_http, new_message).await;
+
+            info!(
+                "@everyone/@here mentioned by {} in {}",
+                user_id, new_message.channel_id
+            );
+
+            handle_mod_action(
+                &user_data.pool,
+                store,
+                &cache_http,
+                &super::handler::HandleModAction {
+                    guild_id,
+                    limit: UserLimitTypes::EveryoneMention,
+                    user_id,
+                    target: new_message.id.to_string(),
+                    action_data: serde_json::json!({
+                        "channel_id": new_message.channel_id,
+                        "webhook_id": new_message.webhook_id,
+                        "application_id": new_message.application_id,
+                    }),
+                },
+            )
+            .await
+        }
+        FullEvent::InteractionCreate {
+            interaction: Interaction::Component(ci),
+        } => super::incidents::handle_interaction(ctx, ci).await,
         _ => Ok(()),
     }
 }
@@ -1,57 +1,20 @@
 use log::{error, info, warn};
-use poise::serenity_prelude::{GuildId, UserId};
-use sqlx::PgPool;
-use std::collections::HashMap;
+use poise::serenity_prelude::{
+    ApplicationId, EditMember, GuildId, Member, RoleId, Timestamp, UserId,
+};
 use splashcore_rs::crypto::gen_random;
-use surrealdb::engine::remote::ws::Client;
-use surrealdb::Surreal;
+use sqlx::types::chrono::{Duration, Utc};
+use sqlx::PgPool;
 
+use super::cache::WindowOutcome;
 use super::core;
-use crate::modules::limits::core::{Limit, UserAction};
+use super::incidents::{Incident, TakenAction};
+use super::store::LimitStore;
+use crate::modules::limits::core::{
+    is_exempt, Exemption, ExemptionType, Limit, LimitMode, UserAction,
+};
 use crate::{impls::cache::CacheHttpImpl, Error};
 
-// Returns true if the same user+target combo has appeared in the time interval user_target_repeat_rate
-// TODO: This function needs to be rewritten as limits are handled in-memory
-/**pub async fn ignore_handling(pool: &PgPool, ha: &HandleModAction) -> Result<bool, Error> {
-    let repeat_rate = {
-        let user_target_settings = core::GuildUserTargetSettings::from_guild(pool, ha.guild_id).await?;
-
-        if user_target_settings.is_empty() {
-            ha.limit.default_user_target_repeat_rate()
-        } else {
-            user_target_settings
-                .iter()
-                .find(|a| a.limit_type == ha.limit)
-                .map(|a| a.user_target_repeat_rate)
-                .unwrap_or_else(|| ha.limit.default_user_target_repeat_rate())
-        }
-    };
-
-    // Get the last time the same user-target combo was handled
-    let last_time_rec = sqlx::query!(
-        "
-            SELECT created_at FROM limits__user_actions
-            WHERE guild_id = $1
-            AND user_id = $2
-            AND target = $3
-            ORDER BY created_at DESC
-            LIMIT 1
-        ",
-        ha.guild_id.to_string(),
-        ha.user_id.to_string(),
-        ha.target.to_string()
-    )
-    .fetch_one(pool)
-    .await?;
-
-    // Check if the last time was within the repeat rate
-    if (sqlx::types::chrono::Utc::now() - last_time_rec.created_at).num_seconds() < repeat_rate {
-        return Ok(true); // Ignore
-    }
-
-    Ok(false) // Don't ignore
-}**/
-
 pub struct HandleModAction {
     /// Guild ID
     pub guild_id: GuildId,
@@ -67,171 +30,521 @@ pub struct HandleModAction {
 
 pub async fn handle_mod_action(
     pool: &PgPool,
-    cache: &Surreal<Client>,
+    store: &impl LimitStore,
     cache_http: &CacheHttpImpl,
     ha: &HandleModAction,
 ) -> Result<(), Error> {
     let guild_id = ha.guild_id;
     let limit = ha.limit;
     let user_id = ha.user_id;
-    let target = ha.target.clone();
-    let action_data = &ha.action_data;
-    // Check limits cache
-    let guild_limits: HashMap<String, Limit> = Limit::fetch(cache, pool, guild_id)
+
+    let guild_limits: Vec<Limit> = Limit::fetch(store, pool, guild_id)
         .await?
         .into_iter()
         .filter(|a| a.limit_type == limit)
-        .map(|a| (a.limit_id.clone(), a))
         .collect();
 
+    // Exempt users are checked before anything is recorded, so their actions never count towards a limit
+    let exemptions = Exemption::fetch(store, pool, guild_id)
+        .await?
+        .into_iter()
+        .filter(|e| e.applies_to(limit))
+        .collect::<Vec<_>>();
+
+    if !exemptions.is_empty() {
+        let roles = if exemptions
+            .iter()
+            .any(|e| e.exemption_type == ExemptionType::Role)
+        {
+            member_roles(cache_http, guild_id, user_id).await
+        } else {
+            Vec::new()
+        };
+
+        let application_id = ha
+            .action_data
+            .get("application_id")
+            .and_then(|a| a.as_str())
+            .and_then(|a| a.parse::<ApplicationId>().ok());
+
+        if is_exempt(&exemptions, limit, user_id, &roles, application_id) {
+            info!("User {} is exempt from {:?} limits", user_id, limit);
+            return Ok(());
+        }
+    }
+
+    let action = UserAction {
+        action_id: gen_random(48),
+        guild_id,
+        user_id,
+        target: ha.target.clone(),
+        limit_type: limit,
+        action_data: ha.action_data.clone(),
+        created_at: sqlx::types::chrono::Utc::now(),
+        limits_hit: Vec::new(),
+    };
+
+    // Every action is stored (even without any limits of its type) so that limits can be simulated against them,
+    // actions that do not hit a limit are pruned by the prune_limit_actions task
+    sqlx::query!(
+        "
+        INSERT INTO limits__user_actions
+        (action_id, guild_id, user_id, target, limit_type, action_data, limits_hit, created_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+    ",
+        action.action_id,
+        guild_id.to_string(),
+        user_id.to_string(),
+        action.target,
+        action.limit_type.to_string(),
+        action.action_data,
+        &action.limits_hit,
+        action.created_at
+    )
+    .execute(pool)
+    .await?;
+
     if guild_limits.is_empty() {
         // No limits for this guild
         return Ok(());
     }
-    let _ = cache
-        .create::<Vec<UserAction>>("user_actions")
-        .content(UserAction {
-            action_id: gen_random(48),
-            guild_id,
-            user_id,
-            target: target.clone(),
-            limit_type: limit,
-            action_data: action_data.clone(),
-            created_at: sqlx::types::chrono::Utc::now(),
-            limits_hit: Vec::new(),
-        })
+
+    let outcome = store.record_action(action, &guild_limits).await?;
+
+    let (hits, cause) = match outcome {
+        WindowOutcome::Ignored => {
+            // The same user-target pair was already handled within the repeat rate
+            return Ok(());
+        }
+        WindowOutcome::Recorded { hits, cause } => (hits, cause),
+    };
+
+    let hit_limits = guild_limits
+        .iter()
+        .filter(|l| hits.contains(&l.limit_id))
+        .collect::<Vec<_>>();
+
+    if hit_limits.is_empty() {
+        // No limits hit
+        return Ok(());
+    }
+
+    info!("Hit limits: limit={:?}, limits={:?}", limit, hits);
+
+    // The actions, hits and incident are committed before any actions are taken, so that no transaction is held
+    // open during the (slow) Discord requests. The outcome of the actions is recorded once they are done
+    let mut tx = pool.begin().await?;
+
+    // The actions are normally already stored, but windows may still hold actions from before all actions were stored
+    for action in cause.iter() {
+        sqlx::query!(
+            "
+            INSERT INTO limits__user_actions
+            (action_id, guild_id, user_id, target, limit_type, action_data, limits_hit, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            ON CONFLICT (action_id) DO UPDATE SET limits_hit = ARRAY(SELECT DISTINCT unnest(limits__user_actions.limits_hit || EXCLUDED.limits_hit))
+        ",
+            action.action_id,
+            guild_id.to_string(),
+            user_id.to_string(),
+            action.target,
+            action.limit_type.to_string(),
+            action.action_data,
+            &action.limits_hit,
+            action.created_at
+        )
+        .execute(&mut *tx)
         .await?;
-    for (limit_id, guild_limit) in guild_limits.into_iter() {
-        // Check the limit type and user_id and guild to see if it is in the cache
-        let mut query = cache.query("select * from user_actions where guild_id=type::string($guild_id) and user_id=type::string($user_id) and limit_type=type::string($limit_type)")
-            .bind(("guild_id", guild_id))
-            .bind(("user_id", user_id))
-            .bind(("limit_type", limit))
-            .await?;
-        let response: Vec<UserAction> = query.take(0)?;
-        if response.len() as i32 >= guild_limit.limit_per {
-            // Cut off the latest limit_per(number) of actions from response
-            let mut actions = response.clone();
-            actions.sort_by(|a, b| a.created_at.cmp(&b.created_at));
-            actions.reverse();
-            actions.truncate(guild_limit.limit_per as usize + 1);
-            // Now check if actions are done within the time limit
-            let last_time = actions[0].created_at;
-            let mut action_ids = Vec::new();
-            for action in actions {
-                if (last_time - action.created_at).num_seconds() < guild_limit.limit_time {
-                    action_ids.push(action.action_id.clone());
-                }
+    }
+
+    // Work out the actions to take, escalating each limit based on how often it was already hit within its escalation window.
+    // Enforced limits only escalate on enforced hits, while limits in monitor mode escalate on their monitor hits to show
+    // what they would have done
+    let mut limit_actions: Vec<(core::UserLimitActions, &Limit)> = Vec::new();
+    // Limits in monitor mode, with the actions they would have taken
+    let mut monitored: Vec<(&Limit, Vec<core::UserLimitActions>)> = Vec::new();
+
+    for hit_limit in hit_limits.iter().copied() {
+        let previous_hits = sqlx::query!(
+            "
+            SELECT COUNT(*) FROM limits__past_hit_limits
+            WHERE guild_id = $1
+            AND user_id = $2
+            AND limit_id = $3
+            AND created_at > NOW() - make_interval(secs => $4)
+            AND monitor = $5
+        ",
+            guild_id.to_string(),
+            user_id.to_string(),
+            hit_limit.limit_id,
+            hit_limit.escalation_window as f64,
+            hit_limit.mode == LimitMode::Monitor
+        )
+        .fetch_one(&mut *tx)
+        .await?
+        .count
+        .unwrap_or_default();
+
+        let actions = hit_limit.tier_actions(previous_hits as usize + 1);
+
+        if hit_limit.mode == LimitMode::Monitor {
+            monitored.push((hit_limit, actions));
+            continue;
+        }
+
+        for action in actions {
+            if !limit_actions.iter().any(|(a, _)| a == &action) {
+                limit_actions.push((action, hit_limit));
             }
-            if action_ids.len() as i32 > guild_limit.limit_per {
-                // remove from cache here
-                {
-                    let mut tx: sqlx::Transaction<'_, sqlx::Postgres> = pool.begin().await?;
-
-                    info!("Hit limit: limit={:?}", limit);
-                    // Remove Cache
-                    let _ = cache
-                            .query("delete user_actions where guild_id=type::string($guild_id) and user_id=type::string($user_id) and limit_type=type::string($limit_type) return none")
-                            .bind(("guild_id", guild_id))
-                            .bind(("user_id", user_id))
-                            .bind(("limit_type", limit))
-                        .await?;
-                    // Add UserActions to db Here.
-                    sqlx::query!(
-                        "
-            INSERT INTO limits__user_actions
-            (action_id, guild_id, user_id, target, limit_type, action_data, limits_hit)
+        }
+    }
+
+    // Least severe first, so that a kick or ban does not stop the other actions from being taken
+    limit_actions.sort_by_key(|(a, _)| a.severity());
+
+    let mut notes = Vec::new();
+
+    for (hit_limit, actions) in monitored {
+        notes.push(format!(
+            "Limit ``{}`` is in monitor mode, no actions were taken (would have taken: {})",
+            hit_limit.limit_name,
+            actions
+                .iter()
+                .map(|a| a.to_cond())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+
+    let mut hit_ids = Vec::new();
+
+    for hit_limit in hit_limits.iter() {
+        let hit_cause = cause
+            .iter()
+            .filter(|a| a.limits_hit.contains(&hit_limit.limit_id))
+            .map(|a| a.action_id.clone())
+            .collect::<Vec<String>>();
+
+        let hit_id = gen_random(16);
+
+        sqlx::query!(
+            "
+            INSERT INTO limits__past_hit_limits
+            (id, guild_id, user_id, limit_id, cause, notes, monitor)
             VALUES ($1, $2, $3, $4, $5, $6, $7)
         ",
-                        gen_random(48),
-                        guild_id.to_string(),
-                        user_id.to_string(),
-                        target,
-                        limit.to_string(),
-                        action_data,
-                        &action_ids
-                    )
-                    .execute(&mut *tx)
-                    .await?;
-                    // Immediately handle the limit
-                    let cur_uid = cache_http.cache.current_user().id;
-                    let can_mod = {
-                        let guild = cache_http.cache.guild(guild_id).ok_or("Guild not found")?;
-
-                        guild.greater_member_hierarchy(&cache_http.cache, cur_uid, user_id)
-                    }
-                    .unwrap_or(cur_uid);
-                    if can_mod == cur_uid {
-                        info!("Moderating user");
-                        match guild_limit.limit_action {
-                            core::UserLimitActions::RemoveAllRoles => {
-                                // Get all user roles
-                                if let Ok(member) = guild_id.member(cache_http, user_id).await {
-                                    let roles = member.roles.clone();
-                                    for role in roles.iter() {
-                                        if let Err(e) =
-                                            member.remove_role(&cache_http.http, *role).await
-                                        {
-                                            error!("Failed to remove role: {}", e);
-                                        }
-                                    }
-                                }
-                            }
-                            core::UserLimitActions::KickUser => {
-                                if let Err(e) = guild_id.kick(&cache_http.http, user_id).await {
-                                    error!("Failed to kick user: {}", e);
-                                }
-                            }
-                            core::UserLimitActions::BanUser => {
-                                if let Err(e) = guild_id.ban(&cache_http.http, user_id, 0).await {
-                                    error!("Failed to kick user: {}", e);
-                                }
-                            }
-                        }
-                    } else {
-                        warn!(
-                            "Cannot moderate user, not enough permissions: {}, {}",
-                            can_mod, cur_uid
-                        );
-
-                        sqlx::query!(
-                            "
-                            INSERT INTO limits__past_hit_limits
-                            (id, guild_id, user_id, limit_id, cause, notes)
-                            VALUES ($1, $2, $3, $4, $5, $6)",
-                            gen_random(16),
-                            guild_id.to_string(),
-                            user_id.to_string(),
-                            limit_id,
-                            &action_ids,
-                            &vec!["Not enough permissions to moderate user".to_string()]
-                        )
-                        .execute(&mut *tx)
-                        .await?;
-
-                        return Ok(());
-                    }
-
-                    sqlx::query!(
-                        "
-                        INSERT INTO limits__past_hit_limits
-                        (id, guild_id, user_id, limit_id, cause)
-                        VALUES ($1, $2, $3, $4, $5)",
-                        gen_random(16),
-                        guild_id.to_string(),
-                        user_id.to_string(),
-                        limit_id,
-                        &action_ids
-                    )
-                    .execute(&mut *tx)
-                    .await?;
-                    return Ok(());
+            hit_id,
+            guild_id.to_string(),
+            user_id.to_string(),
+            hit_limit.limit_id,
+            &hit_cause,
+            &notes,
+            hit_limit.mode == LimitMode::Monitor
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        hit_ids.push(hit_id);
+    }
+
+    if limit_actions.is_empty() {
+        // Hits of limits in monitor mode are only recorded, there is nothing to resolve so no incident is created
+        tx.commit().await?;
+        info!("Only limits in monitor mode were hit, not moderating user");
+        return Ok(());
+    }
+
+    let incident_id = gen_random(16);
+
+    sqlx::query!(
+        "
+        INSERT INTO limits__incidents
+        (id, guild_id, user_id, hits, actions_taken, removed_roles, added_roles)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+    ",
+        incident_id,
+        guild_id.to_string(),
+        user_id.to_string(),
+        &hit_ids,
+        &Vec::<String>::new(),
+        &Vec::<String>::new(),
+        &Vec::<String>::new()
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    // Immediately handle the limit
+    let cur_uid = cache_http.cache.current_user().id;
+    let can_mod = {
+        let guild = cache_http.cache.guild(guild_id).ok_or("Guild not found")?;
+
+        guild.greater_member_hierarchy(&cache_http.cache, cur_uid, user_id)
+    }
+    .unwrap_or(cur_uid);
+
+    let mut taken_actions = Vec::new();
+    let mut role_changes = RoleChanges::default();
+
+    if can_mod == cur_uid {
+        info!("Moderating user");
+        for (action, hit_limit) in limit_actions {
+            let error = match take_action(
+                cache_http,
+                guild_id,
+                user_id,
+                &action,
+                hit_limit,
+                &mut role_changes,
+            )
+            .await
+            {
+                Ok(()) => None,
+                Err(e) => {
+                    error!("Failed to take action {}: {}", action, e);
+                    notes.push(format!("Failed to {}: {}", action.to_cond(), e));
+                    Some(e.to_string())
                 }
-            }
-        } else {
-            // No Limits hit.
-            continue;
+            };
+
+            taken_actions.push(TakenAction { action, error });
+        }
+    } else {
+        warn!(
+            "Cannot moderate user, not enough permissions: {}, {}",
+            can_mod, cur_uid
+        );
+
+        notes.push("Not enough permissions to moderate user".to_string());
+
+        taken_actions.extend(limit_actions.into_iter().map(|(action, _)| TakenAction {
+            action,
+            error: Some("Not enough permissions to moderate user".to_string()),
+        }));
+    }
+
+    let alert_channel = match super::incidents::alert_channel(pool, guild_id).await {
+        Ok(alert_channel) => alert_channel,
+        Err(e) => {
+            warn!("Failed to get alert channel for guild {}: {}", guild_id, e);
+            None
+        }
+    };
+
+    // Recreate the channels and roles deleted by the user for the limits that have rollback enabled
+    let rollback_actions = cause
+        .iter()
+        .filter(|a| {
+            hit_limits.iter().any(|l| {
+                l.rollback && l.mode == LimitMode::Enforce && a.limits_hit.contains(&l.limit_id)
+            })
+        })
+        .collect::<Vec<_>>();
+
+    if !rollback_actions.is_empty() {
+        notes.extend(
+            super::rollback::rollback(
+                cache_http,
+                guild_id,
+                user_id,
+                &rollback_actions,
+                alert_channel,
+            )
+            .await,
+        );
+    }
+
+    let mut tx = pool.begin().await?;
+
+    sqlx::query!(
+        "UPDATE limits__past_hit_limits SET notes = $1 WHERE id = ANY($2)",
+        &notes,
+        &hit_ids
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        "UPDATE limits__incidents SET actions_taken = $2, removed_roles = $3, added_roles = $4 WHERE id = $1",
+        incident_id,
+        &taken_actions
+            .iter()
+            .filter(|a| a.error.is_none())
+            .map(|a| a.action.to_string())
+            .collect::<Vec<String>>(),
+        &role_changes
+            .removed
+            .iter()
+            .map(|r| r.to_string())
+            .collect::<Vec<String>>(),
+        &role_changes
+            .added
+            .iter()
+            .map(|r| r.to_string())
+            .collect::<Vec<String>>()
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    let incident = Incident {
+        id: incident_id,
+        user_id,
+        limits: &hit_limits,
+        cause: &cause,
+        actions: &taken_actions,
+        notes: &notes,
+    };
+
+    if let Some(alert_channel) = alert_channel {
+        if let Err(e) = incident.send(pool, cache_http, alert_channel).await {
+            warn!(
+                "Failed to send incident report {} for guild {}: {}",
+                incident.id, guild_id, e
+            );
         }
     }
+
     Ok(())
 }
+
+/// The roles removed from and added to a user while taking actions against them, so the actions can be undone later
+#[derive(Default)]
+struct RoleChanges {
+    removed: Vec<RoleId>,
+    added: Vec<RoleId>,
+}
+
+/// Returns the roles of a member, preferring the cache. Users who are not members (such as webhooks) have no roles
+async fn member_roles(
+    cache_http: &CacheHttpImpl,
+    guild_id: GuildId,
+    user_id: UserId,
+) -> Vec<RoleId> {
+    let cached = cache_http
+        .cache
+        .guild(guild_id)
+        .and_then(|g| g.members.get(&user_id).map(|m| m.roles.to_vec()));
+
+    match cached {
+        Some(roles) => roles,
+        None => guild_id
+            .member(cache_http, user_id)
+            .await
+            .map(|m| m.roles.to_vec())
+            .unwrap_or_default(),
+    }
+}
+
+/// Removes roles from a member, trying every role even if removing one fails
+async fn remove_roles(
+    cache_http: &CacheHttpImpl,
+    member: &Member,
+    roles: &[RoleId],
+    role_changes: &mut RoleChanges,
+) -> Result<(), Error> {
+    let mut errors = Vec::new();
+
+    for role in roles {
+        match member.remove_role(&cache_http.http, *role).await {
+            Ok(()) => role_changes.removed.push(*role),
+            Err(e) => errors.push(format!("{}: {}", role, e)),
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(format!("Failed to remove roles: {}", errors.join(", ")).into());
+    }
+
+    Ok(())
+}
+
+/// Takes an action against a user who hit a limit
+async fn take_action(
+    cache_http: &CacheHttpImpl,
+    guild_id: GuildId,
+    user_id: UserId,
+    action: &core::UserLimitActions,
+    limit: &Limit,
+    role_changes: &mut RoleChanges,
+) -> Result<(), Error> {
+    match action {
+        core::UserLimitActions::AlertOnly => Ok(()),
+        core::UserLimitActions::TimeoutUser => {
+            let until = Timestamp::from_millis(
+                (Utc::now() + Duration::seconds(limit.timeout_duration)).timestamp_millis(),
+            )?;
+
+            guild_id
+                .edit_member(
+                    &cache_http.http,
+                    user_id,
+                    EditMember::new().disable_communication_until(until),
+                )
+                .await?;
+
+            Ok(())
+        }
+        core::UserLimitActions::StripDangerousRoles => {
+            let member = guild_id.member(cache_http, user_id).await?;
+
+            let dangerous_roles = {
+                let guild = cache_http.cache.guild(guild_id).ok_or("Guild not found")?;
+
+                member
+                    .roles
+                    .iter()
+                    .filter(|r| {
+                        guild
+                            .roles
+                            .get(r)
+                            .map(|role| role.permissions.intersects(core::DANGEROUS_PERMISSIONS))
+                            .unwrap_or_default()
+                    })
+                    .copied()
+                    .collect::<Vec<RoleId>>()
+            };
+
+            remove_roles(cache_http, &member, &dangerous_roles, role_changes).await
+        }
+        core::UserLimitActions::QuarantineUser => {
+            let quarantine_role = limit
+                .quarantine_role
+                .ok_or("No quarantine role is set for this limit")?;
+
+            let member = guild_id.member(cache_http, user_id).await?;
+
+            let roles = member
+                .roles
+                .iter()
+                .filter(|r| **r != quarantine_role)
+                .copied()
+                .collect::<Vec<RoleId>>();
+
+            remove_roles(cache_http, &member, &roles, role_changes).await?;
+
+            if !member.roles.contains(&quarantine_role) {
+                member.add_role(&cache_http.http, quarantine_role).await?;
+                role_changes.added.push(quarantine_role);
+            }
+
+            Ok(())
+        }
+        core::UserLimitActions::RemoveAllRoles => {
+            let member = guild_id.member(cache_http, user_id).await?;
+            let roles = member.roles.to_vec();
+
+            remove_roles(cache_http, &member, &roles, role_changes).await
+        }
+        core::UserLimitActions::KickUser => {
+            guild_id.kick(&cache_http.http, user_id).await?;
+            Ok(())
+        }
+        core::UserLimitActions::BanUser => {
+            guild_id.ban(&cache_http.http, user_id, 0).await?;
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,464 @@
+use log::warn;
+use poise::serenity_prelude::{
+    ButtonStyle, ChannelId, ComponentInteraction, CreateActionRow, CreateButton, CreateEmbed,
+    CreateEmbedFooter, CreateMessage, EditInteractionResponse, EditMember, EditMessage, GuildId,
+    MessageId, RoleId, UserId,
+};
+use sqlx::PgPool;
+use strum_macros::{Display, EnumString};
+
+use super::core::{Limit, UserAction, UserLimitActions};
+use crate::impls::{
+    cache::CacheHttpImpl,
+    utils::{get_icon_of_state, MAX_EMBED_FIELD_VALUE_LENGTH},
+};
+use crate::silverpelt::module_config::get_module_option;
+use crate::{Data, Error};
+
+/// The prefix of the custom IDs of the buttons on incident reports, the full custom ID is ``<prefix>:<undo|ban>:<incident id>``
+const INCIDENT_BUTTON_PREFIX: &str = "limits_incident";
+
+/// Returns the channel incident reports are sent to in a guild, if one is configured
+pub async fn alert_channel(pool: &PgPool, guild_id: GuildId) -> Result<Option<ChannelId>, Error> {
+    let value = get_module_option(pool, guild_id, "limits", "alert_channel").await?;
+
+    Ok(value.as_str().map(|c| c.parse()).transpose()?)
+}
+
+/// An action taken against a user who hit a limit
+pub struct TakenAction {
+    /// The action
+    pub action: UserLimitActions,
+    /// The error taking the action failed with, if any
+    pub error: Option<String>,
+}
+
+/// An incident, created whenever a user hits one or more limits at once
+pub struct Incident<'a> {
+    /// The ID of the incident
+    pub id: String,
+    /// The user who hit the limits
+    pub user_id: UserId,
+    /// The limits that were hit
+    pub limits: &'a [&'a Limit],
+    /// The actions that caused the limits to be hit
+    pub cause: &'a [UserAction],
+    /// The actions taken against the user
+    pub actions: &'a [TakenAction],
+    /// Notes on the incident, such as failures to moderate the user
+    pub notes: &'a [String],
+}
+
+/// Joins lines into an embed field value, leaving out the lines that do not fit
+fn field_value(lines: Vec<String>, empty: &str) -> String {
+    if lines.is_empty() {
+        return empty.to_string();
+    }
+
+    let total = lines.len();
+    let mut value = String::new();
+
+    for (i, line) in lines.into_iter().enumerate() {
+        let more = format!("\n...and {} more", total - i);
+
+        if value.len() + line.len() + 1 + more.len() > MAX_EMBED_FIELD_VALUE_LENGTH {
+            value.push_str(&more);
+            break;
+        }
+
+        if !value.is_empty() {
+            value.push('\n');
+        }
+
+        value.push_str(&line);
+    }
+
+    value
+}
+
+impl Incident<'_> {
+    /// Creates the incident report sent to the alert channel
+    pub fn embed<'a>(&self) -> CreateEmbed<'a> {
+        let mut embed = CreateEmbed::default()
+            .title("Limit Hit")
+            .color(0xff0000)
+            .description(format!(
+                "<@{user_id}> ({user_id}) hit {count} limit(s)",
+                user_id = self.user_id,
+                count = self.limits.len()
+            ))
+            .field(
+                "Limits",
+                field_value(
+                    self.limits
+                        .iter()
+                        .map(|l| {
+                            format!(
+                                "**{}**: over {} ``{}`` [{}]",
+                                l.limit_name,
+                                l.limit_per,
+                                l.limit_type.to_cond(),
+                                l.limit_id
+                            )
+                        })
+                        .collect(),
+                    "None",
+                ),
+                false,
+            )
+            .field(
+                "Caused By",
+                field_value(
+                    self.cause
+                        .iter()
+                        .map(|a| {
+                            format!(
+                                "``{}`` on ``{}`` <t:{}:R>",
+                                a.limit_type.to_cond(),
+                                a.target,
+                                a.created_at.timestamp()
+                            )
+                        })
+                        .collect(),
+                    "None",
+                ),
+                false,
+            )
+            .field(
+                "Actions Taken",
+                field_value(
+                    self.actions
+                        .iter()
+                        .map(|a| {
+                            format!(
+                                "{} | {}",
+                                get_icon_of_state(if a.error.is_some() {
+                                    "failed"
+                                } else {
+                                    "completed"
+                                }),
+                                a.action.to_cond()
+                            )
+                        })
+                        .collect(),
+                    "None",
+                ),
+                false,
+            );
+
+        if !self.notes.is_empty() {
+            embed = embed.field(
+                "Notes",
+                field_value(
+                    self.notes.iter().map(|n| format!("- {}", n)).collect(),
+                    "None",
+                ),
+                false,
+            );
+        }
+
+        embed.footer(CreateEmbedFooter::new(format!("Incident ID: {}", self.id)))
+    }
+
+    /// Sends the incident report to a channel, remembering the message so it can be updated once the incident is resolved
+    pub async fn send(
+        &self,
+        pool: &PgPool,
+        cache_http: &CacheHttpImpl,
+        channel_id: ChannelId,
+    ) -> Result<(), Error> {
+        let msg = channel_id
+            .send_message(
+                &cache_http.http,
+                CreateMessage::new()
+                    .embed(self.embed())
+                    .components(incident_buttons(&self.id, false)),
+            )
+            .await?;
+
+        sqlx::query!(
+            "UPDATE limits__incidents SET alert_channel_id = $2, alert_message_id = $3 WHERE id = $1",
+            self.id,
+            channel_id.to_string(),
+            msg.id.to_string()
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(())
+    }
+}
+
+/// Returns the undo and ban buttons of an incident report
+fn incident_buttons<'a>(incident_id: &str, disabled: bool) -> Vec<CreateActionRow<'a>> {
+    vec![CreateActionRow::Buttons(vec![
+        CreateButton::new(format!("{}:undo:{}", INCIDENT_BUTTON_PREFIX, incident_id))
+            .label("Undo Punishment")
+            .style(ButtonStyle::Secondary)
+            .disabled(disabled),
+        CreateButton::new(format!("{}:ban:{}", INCIDENT_BUTTON_PREFIX, incident_id))
+            .label("Ban User")
+            .style(ButtonStyle::Danger)
+            .disabled(disabled),
+    ])]
+}
+
+/// How an incident was resolved
+#[derive(EnumString, Display, PartialEq, Clone, Copy, Debug)]
+#[strum(serialize_all = "snake_case")]
+pub enum Resolution {
+    /// The punishment of the user was undone
+    Undone,
+    /// The user was banned
+    Banned,
+}
+
+impl Resolution {
+    /// Returns the qualified name of the command whose permissions are needed to resolve an incident this way
+    pub fn command(&self) -> &'static str {
+        match self {
+            Self::Undone => "limits incident undo",
+            Self::Banned => "limits incident ban",
+        }
+    }
+}
+
+/// Resolves an incident by undoing the punishment of the user or banning them, returning notes on what
+/// could not be done
+///
+/// Kicks cannot be undone, the user has to be invited back
+pub async fn resolve_incident(
+    pool: &PgPool,
+    cache_http: &CacheHttpImpl,
+    guild_id: GuildId,
+    incident_id: &str,
+    resolution: Resolution,
+    resolved_by: UserId,
+) -> Result<Vec<String>, Error> {
+    // The incident is claimed before anything is done so that concurrent resolutions cannot both go through
+    let Some(rec) = sqlx::query!(
+        "
+            UPDATE limits__incidents SET resolution = $3, resolved_by = $4
+            WHERE guild_id = $1
+            AND id = $2
+            AND resolution IS NULL
+            RETURNING user_id, actions_taken, removed_roles, added_roles,
+            alert_channel_id, alert_message_id
+        ",
+        guild_id.to_string(),
+        incident_id,
+        resolution.to_string(),
+        resolved_by.to_string()
+    )
+    .fetch_optional(pool)
+    .await?
+    else {
+        let existing = sqlx::query!(
+            "SELECT resolution FROM limits__incidents WHERE guild_id = $1 AND id = $2",
+            guild_id.to_string(),
+            incident_id
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        return match existing.and_then(|e| e.resolution) {
+            Some(resolution) => Err(format!(
+                "This incident has already been resolved (``{}``)",
+                resolution
+            )
+            .into()),
+            None => Err("Could not find incident".into()),
+        };
+    };
+
+    let user_id: UserId = rec.user_id.parse()?;
+
+    let mut notes = Vec::new();
+
+    match resolution {
+        Resolution::Undone => {
+            for action in rec.actions_taken.iter() {
+                let action: UserLimitActions = action.parse()?;
+
+                let res = match action {
+                    UserLimitActions::TimeoutUser => guild_id
+                        .edit_member(
+                            &cache_http.http,
+                            user_id,
+                            EditMember::new().enable_communication(),
+                        )
+                        .await
+                        .map(|_| ())
+                        .map_err(Error::from),
+                    UserLimitActions::BanUser => guild_id
+                        .unban(&cache_http.http, user_id)
+                        .await
+                        .map_err(Error::from),
+                    UserLimitActions::KickUser => {
+                        Err("Kicked users must be invited back to the server".into())
+                    }
+                    // Roles are restored below
+                    _ => Ok(()),
+                };
+
+                if let Err(e) = res {
+                    notes.push(format!("Failed to undo {}: {}", action.to_cond(), e));
+                }
+            }
+
+            if !rec.removed_roles.is_empty() || !rec.added_roles.is_empty() {
+                match guild_id.member(cache_http, user_id).await {
+                    Ok(member) => {
+                        for role in rec.removed_roles.iter() {
+                            let role: RoleId = role.parse()?;
+
+                            if let Err(e) = member.add_role(&cache_http.http, role).await {
+                                notes.push(format!("Failed to give back role {}: {}", role, e));
+                            }
+                        }
+
+                        for role in rec.added_roles.iter() {
+                            let role: RoleId = role.parse()?;
+
+                            if let Err(e) = member.remove_role(&cache_http.http, role).await {
+                                notes.push(format!("Failed to remove role {}: {}", role, e));
+                            }
+                        }
+                    }
+                    Err(e) => notes.push(format!("Failed to restore roles: {}", e)),
+                }
+            }
+        }
+        Resolution::Banned => {
+            if let Err(e) = guild_id.ban(&cache_http.http, user_id, 0).await {
+                // Nothing was done, so release the claim on the incident
+                sqlx::query!(
+                    "UPDATE limits__incidents SET resolution = NULL, resolved_by = NULL WHERE id = $1",
+                    incident_id
+                )
+                .execute(pool)
+                .await?;
+
+                return Err(e.into());
+            }
+        }
+    }
+
+    // Disable the buttons of the incident report
+    if let (Some(channel_id), Some(message_id)) = (rec.alert_channel_id, rec.alert_message_id) {
+        let (channel_id, message_id) = (
+            channel_id.parse::<ChannelId>()?,
+            message_id.parse::<MessageId>()?,
+        );
+
+        if let Err(e) = channel_id
+            .edit_message(
+                &cache_http.http,
+                message_id,
+                EditMessage::new()
+                    .content(format!(
+                        "{} by <@{}>",
+                        match resolution {
+                            Resolution::Undone => "Punishment undone",
+                            Resolution::Banned => "User banned",
+                        },
+                        resolved_by
+                    ))
+                    .components(incident_buttons(incident_id, true)),
+            )
+            .await
+        {
+            warn!("Failed to update incident report {}: {}", incident_id, e);
+        }
+    }
+
+    Ok(notes)
+}
+
+/// Handles presses of the buttons on incident reports, checking that the user pressing them can run the matching command
+pub async fn handle_interaction(
+    ctx: &serenity::client::Context,
+    ci: &ComponentInteraction,
+) -> Result<(), Error> {
+    let Some((kind, incident_id)) = ci
+        .data
+        .custom_id
+        .strip_prefix(INCIDENT_BUTTON_PREFIX)
+        .and_then(|r| r.strip_prefix(':'))
+        .and_then(|r| r.split_once(':'))
+    else {
+        return Ok(());
+    };
+
+    let resolution = match kind {
+        "undo" => Resolution::Undone,
+        "ban" => Resolution::Banned,
+        _ => return Ok(()),
+    };
+
+    let guild_id = ci
+        .guild_id
+        .ok_or("Incident reports can only be used in servers")?;
+
+    ci.defer_ephemeral(&ctx.http).await?;
+
+    let data = ctx.data::<Data>();
+    let cache_http = CacheHttpImpl::from_ctx(ctx);
+
+    let perm_res = crate::silverpelt::cmd::check_command(
+        "limits",
+        resolution.command(),
+        guild_id,
+        ci.user.id,
+        Some(ci.channel_id),
+        &data.pool,
+        &cache_http,
+        &None,
+        None,
+    )
+    .await;
+
+    let content = if !perm_res.is_ok() {
+        perm_res.to_markdown()
+    } else {
+        match resolve_incident(
+            &data.pool,
+            &cache_http,
+            guild_id,
+            incident_id,
+            resolution,
+            ci.user.id,
+        )
+        .await
+        {
+            Ok(notes) if notes.is_empty() => "Incident resolved successfully".to_string(),
+            Ok(notes) => format!("Incident resolved with errors:\n- {}", notes.join("\n- ")),
+            Err(e) => e.to_string(),
+        }
+    };
+
+    ci.edit_response(&ctx.http, EditInteractionResponse::new().content(content))
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_field_value() {
+        assert_eq!(field_value(vec![], "None"), "None");
+        assert_eq!(
+            field_value(vec!["a".to_string(), "b".to_string()], "None"),
+            "a\nb"
+        );
+
+        let lines = (0..100).map(|i| format!("{:020}", i)).collect::<Vec<_>>();
+        let value = field_value(lines, "None");
+
+        assert!(value.len() <= MAX_EMBED_FIELD_VALUE_LENGTH);
+        assert!(value.ends_with("more"));
+    }
+}
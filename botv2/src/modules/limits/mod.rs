@@ -1,10 +1,15 @@
 mod autocompletes;
+mod cache;
 mod cmds;
 mod core;
 mod events;
 mod handler;
+mod incidents;
+mod rollback;
+mod store;
 
 use indexmap::indexmap;
+use poise::serenity_prelude::ChannelId;
 
 pub fn module() -> crate::silverpelt::Module {
     crate::silverpelt::Module {
@@ -17,6 +22,8 @@ pub fn module() -> crate::silverpelt::Module {
         virtual_module: false,
         web_hidden: false,
         is_default_enabled: false,
+        depends_on: vec![],
+        conflicts_with: vec![],
         commands: vec![
             (
                 cmds::limits(),
@@ -24,7 +31,15 @@ pub fn module() -> crate::silverpelt::Module {
                     "add" => crate::silverpelt::CommandExtendedData::kittycat_or_admin("limits", "add"),
                     "view" => crate::silverpelt::CommandExtendedData::kittycat_or_admin("limits", "view"),
                     "remove" => crate::silverpelt::CommandExtendedData::kittycat_or_admin("limits", "remove"),
+                    "escalation" => crate::silverpelt::CommandExtendedData::kittycat_or_admin("limits", "escalation"),
+                    "exempt add" => crate::silverpelt::CommandExtendedData::kittycat_or_admin("limits", "exempt_add"),
+                    "exempt remove" => crate::silverpelt::CommandExtendedData::kittycat_or_admin("limits", "exempt_remove"),
+                    "exempt list" => crate::silverpelt::CommandExtendedData::kittycat_or_admin("limits", "exempt_list"),
                     "hit" => crate::silverpelt::CommandExtendedData::kittycat_or_admin("limits", "hit"),
+                    "incident undo" => crate::silverpelt::CommandExtendedData::kittycat_or_admin("limits", "incident_undo"),
+                    "incident ban" => crate::silverpelt::CommandExtendedData::kittycat_or_admin("limits", "incident_ban"),
+                    "mode" => crate::silverpelt::CommandExtendedData::kittycat_or_admin("limits", "mode"),
+                    "simulate" => crate::silverpelt::CommandExtendedData::kittycat_or_admin("limits", "simulate"),
                 },
             ),
             (
@@ -34,6 +49,22 @@ pub fn module() -> crate::silverpelt::Module {
                 }
             ),
         ],
+        config_options: vec![crate::silverpelt::config_opt::ConfigOption {
+            id: "alert_channel".to_string(),
+            name: "Alert Channel".to_string(),
+            description: "The channel incident reports are sent to when a user hits a limit".to_string(),
+            field_type: crate::silverpelt::config_opt::WebFieldType::Optional(Box::new(
+                crate::silverpelt::config_opt::WebFieldType::Channel(ChannelId::new(1)),
+            )),
+        }],
+        event_filter: vec![
+            "GUILD_AUDIT_LOG_ENTRY_CREATE",
+            "MESSAGE",
+            "CHANNEL_DELETE",
+            "CATEGORY_DELETE",
+            "GUILD_ROLE_DELETE",
+            "INTERACTION_CREATE",
+        ],
         event_handlers: vec![Box::new(move |ctx, fe, ectx| {
             Box::pin(async move { events::event_listener(ctx, fe, ectx).await })
         })],
@@ -0,0 +1,405 @@
+use log::{error, warn};
+use moka::future::Cache;
+use once_cell::sync::Lazy;
+use poise::serenity_prelude::{
+    ChannelId, ChannelType, CreateChannel, CreateEmbed, CreateMessage, EditMessage, EditRole,
+    GuildId, PermissionOverwrite, PermissionOverwriteType, Permissions, RoleId, UserId,
+};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use super::core::{UserAction, UserLimitTypes};
+use crate::impls::{cache::CacheHttpImpl, utils::get_icon_of_state};
+use crate::Error;
+
+/// Snapshots of recently deleted channels and roles, keyed by guild and channel/role ID
+///
+/// Delete events carry the full deleted object while audit log entries only carry some of its fields,
+/// so the snapshot is kept here until the audit log entry of the deletion arrives
+static SNAPSHOTS: Lazy<Cache<(GuildId, String), serde_json::Value>> = Lazy::new(|| {
+    Cache::builder()
+        .time_to_live(std::time::Duration::from_secs(60))
+        .build()
+});
+
+/// Stores the snapshot of a deleted channel or role until the audit log entry of the deletion arrives
+pub async fn stash_snapshot(guild_id: GuildId, target: String, snapshot: serde_json::Value) {
+    SNAPSHOTS.insert((guild_id, target), snapshot).await;
+}
+
+/// Takes the stashed snapshot of a deleted channel or role, if any
+pub async fn take_snapshot(guild_id: GuildId, target: &str) -> Option<serde_json::Value> {
+    SNAPSHOTS.remove(&(guild_id, target.to_string())).await
+}
+
+/// Builds a partial snapshot of a deleted object from the old values of the changes of its audit log entry
+///
+/// `changes` is the serialized form of the changes, a list of ``{"key": ..., "old_value": ...}`` objects
+pub fn snapshot_from_changes(changes: &serde_json::Value) -> serde_json::Value {
+    let mut snapshot = serde_json::Map::new();
+
+    for change in changes.as_array().into_iter().flatten() {
+        let (Some(key), Some(old)) = (
+            change.get("key").and_then(|k| k.as_str()),
+            change.get("old_value"),
+        ) else {
+            continue;
+        };
+
+        snapshot.insert(key.to_string(), old.clone());
+    }
+
+    serde_json::Value::Object(snapshot)
+}
+
+/// The parts of a deleted channel needed to recreate it, in the format used by discord
+#[derive(Deserialize, Debug)]
+pub struct ChannelSnapshot {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: ChannelType,
+    #[serde(default)]
+    pub parent_id: Option<ChannelId>,
+    #[serde(default)]
+    pub position: Option<u16>,
+    #[serde(default)]
+    pub topic: Option<String>,
+    #[serde(default)]
+    pub nsfw: bool,
+    #[serde(default)]
+    pub rate_limit_per_user: Option<u16>,
+    #[serde(default)]
+    pub bitrate: Option<u32>,
+    #[serde(default)]
+    pub user_limit: Option<u32>,
+    #[serde(default)]
+    pub permission_overwrites: Vec<PermissionOverwrite>,
+}
+
+/// The parts of a deleted role needed to recreate it, in the format used by discord
+#[derive(Deserialize, Debug)]
+pub struct RoleSnapshot {
+    pub name: String,
+    #[serde(default)]
+    pub permissions: Permissions,
+    #[serde(default)]
+    pub color: u32,
+    #[serde(default)]
+    pub hoist: bool,
+    #[serde(default)]
+    pub mentionable: bool,
+    #[serde(default)]
+    pub position: Option<u16>,
+}
+
+/// Returns the snapshot of the object deleted by an action, preferring the full snapshot taken from the
+/// delete event over the one built from the audit log entry
+fn snapshot_of<T: serde::de::DeserializeOwned>(action: &UserAction) -> Result<T, Error> {
+    let snapshot = match action.action_data.get("snapshot") {
+        Some(snapshot) if !snapshot.is_null() => snapshot.clone(),
+        _ => snapshot_from_changes(
+            action
+                .action_data
+                .get("changes")
+                .unwrap_or(&serde_json::Value::Null),
+        ),
+    };
+
+    serde_json::from_value(snapshot).map_err(|e| format!("No usable snapshot: {}", e).into())
+}
+
+/// An item being rolled back
+struct RollbackItem<'a> {
+    action: &'a UserAction,
+    label: String,
+    state: &'static str,
+    error: Option<String>,
+}
+
+fn progress_embed<'a>(user_id: UserId, items: &[RollbackItem], done: bool) -> CreateEmbed<'a> {
+    let mut description = format!(
+        "{} | Rolling back the damage done by <@{}>\n",
+        get_icon_of_state(if done { "completed" } else { "running" }),
+        user_id
+    );
+
+    for item in items {
+        description.push_str(&format!(
+            "\n{} | {}",
+            get_icon_of_state(item.state),
+            item.label
+        ));
+
+        if let Some(ref error) = item.error {
+            description.push_str(&format!(": {}", error));
+        }
+    }
+
+    CreateEmbed::default()
+        .title(if done {
+            "Rolled Back Damage"
+        } else {
+            "Rolling Back Damage..."
+        })
+        .description(description)
+}
+
+/// Recreates the channels and roles deleted by a user who hit a limit, returning notes on what could not be restored
+///
+/// Roles are restored before channels and categories before other channels so that permission overwrites
+/// and parents can point at the recreated objects. Progress is reported in `progress_channel`, falling back to
+/// the system channel of the guild
+pub async fn rollback(
+    cache_http: &CacheHttpImpl,
+    guild_id: GuildId,
+    user_id: UserId,
+    actions: &[&UserAction],
+    progress_channel: Option<ChannelId>,
+) -> Vec<String> {
+    let mut items = actions
+        .iter()
+        .filter(|a| {
+            matches!(
+                a.limit_type,
+                UserLimitTypes::ChannelRemove | UserLimitTypes::RoleRemove
+            )
+        })
+        .map(|a| RollbackItem {
+            action: a,
+            label: match a.limit_type {
+                UserLimitTypes::RoleRemove => format!("Role ``{}``", a.target),
+                _ => format!("Channel ``{}``", a.target),
+            },
+            state: "pending",
+            error: None,
+        })
+        .collect::<Vec<_>>();
+
+    items.sort_by_key(|i| match i.action.limit_type {
+        UserLimitTypes::RoleRemove => 0,
+        _ => match snapshot_of::<ChannelSnapshot>(i.action) {
+            Ok(ChannelSnapshot {
+                kind: ChannelType::Category,
+                ..
+            }) => 1,
+            _ => 2,
+        },
+    });
+
+    if items.is_empty() {
+        return Vec::new();
+    }
+
+    let progress_channel = progress_channel.or_else(|| {
+        cache_http
+            .cache
+            .guild(guild_id)
+            .and_then(|g| g.system_channel_id)
+    });
+
+    let mut progress_msg = match progress_channel {
+        Some(channel_id) => match channel_id
+            .send_message(
+                &cache_http.http,
+                CreateMessage::new().embed(progress_embed(user_id, &items, false)),
+            )
+            .await
+        {
+            Ok(msg) => Some(msg),
+            Err(e) => {
+                warn!(
+                    "Failed to send rollback progress for guild {}: {}",
+                    guild_id, e
+                );
+                None
+            }
+        },
+        None => None,
+    };
+
+    // Old ID to the ID of the recreated object
+    let mut id_map: HashMap<String, String> = HashMap::new();
+
+    for i in 0..items.len() {
+        items[i].state = "running";
+
+        let res = match items[i].action.limit_type {
+            UserLimitTypes::RoleRemove => {
+                rollback_role(cache_http, guild_id, items[i].action).await
+            }
+            _ => rollback_channel(cache_http, guild_id, items[i].action, &id_map).await,
+        };
+
+        match res {
+            Ok((label, new_id)) => {
+                items[i].label = label;
+                items[i].state = "completed";
+                id_map.insert(items[i].action.target.clone(), new_id);
+            }
+            Err(e) => {
+                error!("Failed to roll back {}: {}", items[i].action.target, e);
+                items[i].state = "failed";
+                items[i].error = Some(e.to_string());
+            }
+        }
+
+        if let Some(ref mut msg) = progress_msg {
+            if let Err(e) = msg
+                .edit(
+                    &cache_http.http,
+                    EditMessage::new().embed(progress_embed(user_id, &items, i + 1 == items.len())),
+                )
+                .await
+            {
+                warn!(
+                    "Failed to update rollback progress for guild {}: {}",
+                    guild_id, e
+                );
+            }
+        }
+    }
+
+    items
+        .iter()
+        .filter_map(|i| {
+            i.error
+                .as_ref()
+                .map(|e| format!("Failed to roll back {}: {}", i.label, e))
+        })
+        .collect()
+}
+
+/// Recreates a deleted role, returning a label for the role and the ID of the recreated role
+async fn rollback_role(
+    cache_http: &CacheHttpImpl,
+    guild_id: GuildId,
+    action: &UserAction,
+) -> Result<(String, String), Error> {
+    let snapshot: RoleSnapshot = snapshot_of(action)?;
+
+    let mut builder = EditRole::new()
+        .name(snapshot.name.clone())
+        .permissions(snapshot.permissions)
+        .colour(snapshot.color)
+        .hoist(snapshot.hoist)
+        .mentionable(snapshot.mentionable)
+        .audit_log_reason("Limits rollback");
+
+    if let Some(position) = snapshot.position {
+        builder = builder.position(position);
+    }
+
+    let role = guild_id.create_role(&cache_http.http, builder).await?;
+
+    Ok((format!("Role ``{}``", snapshot.name), role.id.to_string()))
+}
+
+/// Recreates a deleted channel, returning a label for the channel and the ID of the recreated channel
+///
+/// Overwrites and parents pointing at objects recreated earlier in the rollback are pointed at the new objects,
+/// and overwrites of roles that no longer exist are dropped
+async fn rollback_channel(
+    cache_http: &CacheHttpImpl,
+    guild_id: GuildId,
+    action: &UserAction,
+    id_map: &HashMap<String, String>,
+) -> Result<(String, String), Error> {
+    let snapshot: ChannelSnapshot = snapshot_of(action)?;
+
+    let mut overwrites = snapshot.permission_overwrites;
+
+    for overwrite in overwrites.iter_mut() {
+        if let PermissionOverwriteType::Role(role_id) = overwrite.kind {
+            if let Some(new_id) = id_map.get(&role_id.to_string()) {
+                overwrite.kind = PermissionOverwriteType::Role(new_id.parse::<RoleId>()?);
+            }
+        }
+    }
+
+    {
+        let guild = cache_http.cache.guild(guild_id).ok_or("Guild not found")?;
+
+        overwrites.retain(|o| match o.kind {
+            PermissionOverwriteType::Role(role_id) => guild.roles.get(&role_id).is_some(),
+            _ => true,
+        });
+    }
+
+    let mut builder = CreateChannel::new(snapshot.name.clone())
+        .kind(snapshot.kind)
+        .nsfw(snapshot.nsfw)
+        .permissions(overwrites)
+        .audit_log_reason("Limits rollback");
+
+    if let Some(parent_id) = snapshot.parent_id {
+        let parent_id = match id_map.get(&parent_id.to_string()) {
+            Some(new_id) => new_id.parse::<ChannelId>()?,
+            None => parent_id,
+        };
+
+        builder = builder.category(parent_id);
+    }
+
+    if let Some(position) = snapshot.position {
+        builder = builder.position(position);
+    }
+
+    if let Some(topic) = snapshot.topic {
+        builder = builder.topic(topic);
+    }
+
+    if let Some(rate_limit_per_user) = snapshot.rate_limit_per_user {
+        builder = builder.rate_limit_per_user(rate_limit_per_user);
+    }
+
+    if let Some(bitrate) = snapshot.bitrate {
+        builder = builder.bitrate(bitrate);
+    }
+
+    if let Some(user_limit) = snapshot.user_limit {
+        builder = builder.user_limit(user_limit);
+    }
+
+    let channel = guild_id.create_channel(&cache_http.http, builder).await?;
+
+    Ok((
+        format!("Channel ``#{}``", snapshot.name),
+        channel.id.to_string(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_from_changes() {
+        let changes = serde_json::json!([
+            {"key": "name", "old_value": "general"},
+            {"key": "type", "old_value": 0},
+            {"key": "nsfw", "old_value": true},
+            {"key": "permission_overwrites", "old_value": [
+                {"id": "2", "type": 0, "allow": "1024", "deny": "0"}
+            ]},
+            {"key": "position", "new_value": 3},
+        ]);
+
+        let snapshot = snapshot_from_changes(&changes);
+        assert!(snapshot.get("position").is_none());
+
+        let channel: ChannelSnapshot = serde_json::from_value(snapshot).unwrap();
+        assert_eq!(channel.name, "general");
+        assert_eq!(channel.kind, ChannelType::Text);
+        assert!(channel.nsfw);
+        assert_eq!(channel.permission_overwrites.len(), 1);
+        assert_eq!(
+            channel.permission_overwrites[0].kind,
+            PermissionOverwriteType::Role(RoleId::new(2))
+        );
+
+        assert!(snapshot_from_changes(&serde_json::Value::Null)
+            .as_object()
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -8,19 +8,30 @@ CREATE TABLE limits__guild_limits (
     limit_type TEXT NOT NULL,
     limit_action TEXT NOT NULL,
     limit_per INTEGER NOT NULL,
-    limit_time INTERVAL NOT NULL
+    limit_time INTERVAL NOT NULL,
+    -- The actions to take on each hit within the escalation window, as a JSON list of tiers (each a list of actions)
+    limit_tiers JSONB NOT NULL DEFAULT '[]',
+    escalation_window INTERVAL NOT NULL DEFAULT '1 day',
+    timeout_duration INTERVAL NOT NULL DEFAULT '10 minutes',
+    quarantine_role TEXT,
+    -- Whether to recreate the channels and roles deleted by users who hit the limit
+    rollback BOOLEAN NOT NULL DEFAULT false,
+    -- One of enforce or monitor, limits in monitor mode only record hits without taking any actions
+    mode TEXT NOT NULL DEFAULT 'enforce'
 );
 
 
 -- Stores a list of user actions and which limits they have hit
--- A user action contributes to a limit
+-- A user action contributes to a limit. All actions not exempt from limits are stored so that limits can be
+-- simulated against them, actions that did not hit any limit are pruned after 30 days
 CREATE TABLE limits__user_actions (
     action_id TEXT PRIMARY KEY,
     limit_type TEXT NOT NULL,
     created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
     user_id TEXT NOT NULL,
     guild_id TEXT NOT NULL REFERENCES guilds(id) ON DELETE CASCADE ON UPDATE CASCADE,
-    action_target TEXT NOT NULL,
+    target TEXT NOT NULL,
+    action_data JSONB NOT NULL DEFAULT '{}',
     limits_hit TEXT[] NOT NULL DEFAULT '{}'
 );
 
@@ -32,5 +43,41 @@ CREATE TABLE limits__past_hit_limits (
     limit_id TEXT NOT NULL REFERENCES limits(limit_id) ON DELETE CASCADE ON UPDATE CASCADE,
     cause TEXT[] NOT NULL DEFAULT '{}',
     notes TEXT[] NOT NULL DEFAULT '{}',
+    -- Whether the limit was in monitor mode when it was hit, monitor hits do not escalate enforced limits
+    monitor BOOLEAN NOT NULL DEFAULT false,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Stores the users, roles and applications that are exempt from the limits of a guild
+CREATE TABLE limits__exemptions (
+    id TEXT PRIMARY KEY DEFAULT uuid_generate_v4(),
+    guild_id TEXT NOT NULL REFERENCES guilds(id) ON DELETE CASCADE ON UPDATE CASCADE,
+    -- One of user, role or application
+    exemption_type TEXT NOT NULL,
+    -- The ID of the user, role or application
+    target TEXT NOT NULL,
+    -- The limit type the exemption is scoped to, if NULL the exemption applies to all limit types
+    limit_type TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    created_by TEXT NOT NULL
+);
+
+-- Stores the incidents of a guild, an incident is created whenever a user hits one or more limits at once
+CREATE TABLE limits__incidents (
+    id TEXT PRIMARY KEY,
+    guild_id TEXT NOT NULL REFERENCES guilds(id) ON DELETE CASCADE ON UPDATE CASCADE,
+    user_id TEXT NOT NULL,
+    -- The IDs of the past hit limits of the incident
+    hits TEXT[] NOT NULL DEFAULT '{}',
+    -- The actions that were successfully taken against the user
+    actions_taken TEXT[] NOT NULL DEFAULT '{}',
+    -- The roles removed from and added to the user, used to undo the punishment
+    removed_roles TEXT[] NOT NULL DEFAULT '{}',
+    added_roles TEXT[] NOT NULL DEFAULT '{}',
+    -- One of undone or banned, NULL if the incident has not been resolved yet
+    resolution TEXT,
+    resolved_by TEXT,
+    alert_channel_id TEXT,
+    alert_message_id TEXT,
     created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
 );
@@ -0,0 +1,517 @@
+use super::cache::{ActionWindow, WindowOutcome};
+use super::core::{Exemption, Limit, UserAction, UserLimitTypes};
+use crate::tasks::prune_limit_actions::LIMIT_ACTION_RETENTION_DAYS;
+use crate::Error;
+use moka::future::Cache;
+use poise::serenity_prelude::{GuildId, UserId};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use tokio::sync::OnceCell;
+
+/// The store of the limits module, created from the config on first use
+static STORE: OnceCell<Store> = OnceCell::const_new();
+
+/// Returns the store of the limits module
+pub async fn store() -> Result<&'static Store, Error> {
+    STORE
+        .get_or_try_init(|| Store::from_config(crate::config::CONFIG.surreal.as_ref()))
+        .await
+}
+
+/// A store for the state of the limits module, namely the cached limits and exemptions of a guild
+/// and the sliding windows of recent user actions
+///
+/// The limits and exemptions themselves are always persisted in postgres, the store only caches them
+pub trait LimitStore {
+    /// Returns the cached limits of a guild, or None if the limits of the guild are not cached
+    fn cached_limits(
+        &self,
+        guild_id: GuildId,
+    ) -> impl Future<Output = Result<Option<Vec<Limit>>, Error>> + Send;
+
+    /// Caches the limits of a guild
+    fn cache_limits(
+        &self,
+        guild_id: GuildId,
+        limits: &[Limit],
+    ) -> impl Future<Output = Result<(), Error>> + Send;
+
+    /// Returns the cached exemptions of a guild, or None if the exemptions of the guild are not cached
+    fn cached_exemptions(
+        &self,
+        guild_id: GuildId,
+    ) -> impl Future<Output = Result<Option<Vec<Exemption>>, Error>> + Send;
+
+    /// Caches the exemptions of a guild
+    fn cache_exemptions(
+        &self,
+        guild_id: GuildId,
+        exemptions: &[Exemption],
+    ) -> impl Future<Output = Result<(), Error>> + Send;
+
+    /// Invalidates the cached limits and exemptions and the sliding windows of a guild, this must be called when the
+    /// limits or exemptions of a guild change
+    fn invalidate_guild(&self, guild_id: GuildId)
+        -> impl Future<Output = Result<(), Error>> + Send;
+
+    /// Records an action in the sliding window of its guild, user and limit type, see [`ActionWindow::record`]
+    ///
+    /// `limits` must be the limits of the guild with the same type as the action
+    fn record_action(
+        &self,
+        action: UserAction,
+        limits: &[Limit],
+    ) -> impl Future<Output = Result<WindowOutcome, Error>> + Send;
+}
+
+/// The store used by the bot, selected from the config
+pub enum Store {
+    Memory(MemoryStore),
+    #[cfg(feature = "surreal")]
+    Surreal(surreal::SurrealStore),
+}
+
+impl Store {
+    /// Creates a SurrealDB backed store if SurrealDB is configured (and the surreal feature is enabled),
+    /// otherwise an in-process store
+    pub async fn from_config(
+        surreal_config: Option<&crate::config::Surreal>,
+    ) -> Result<Self, Error> {
+        #[cfg(feature = "surreal")]
+        if let Some(surreal_config) = surreal_config {
+            return Ok(Self::Surreal(
+                surreal::SurrealStore::connect(surreal_config).await?,
+            ));
+        }
+
+        #[cfg(not(feature = "surreal"))]
+        if surreal_config.is_some() {
+            log::warn!("SurrealDB is configured but the bot was built without the surreal feature, using the in-process limits store");
+        }
+
+        Ok(Self::Memory(MemoryStore::default()))
+    }
+}
+
+impl LimitStore for Store {
+    async fn cached_limits(&self, guild_id: GuildId) -> Result<Option<Vec<Limit>>, Error> {
+        match self {
+            Self::Memory(s) => s.cached_limits(guild_id).await,
+            #[cfg(feature = "surreal")]
+            Self::Surreal(s) => s.cached_limits(guild_id).await,
+        }
+    }
+
+    async fn cache_limits(&self, guild_id: GuildId, limits: &[Limit]) -> Result<(), Error> {
+        match self {
+            Self::Memory(s) => s.cache_limits(guild_id, limits).await,
+            #[cfg(feature = "surreal")]
+            Self::Surreal(s) => s.cache_limits(guild_id, limits).await,
+        }
+    }
+
+    async fn cached_exemptions(&self, guild_id: GuildId) -> Result<Option<Vec<Exemption>>, Error> {
+        match self {
+            Self::Memory(s) => s.cached_exemptions(guild_id).await,
+            #[cfg(feature = "surreal")]
+            Self::Surreal(s) => s.cached_exemptions(guild_id).await,
+        }
+    }
+
+    async fn cache_exemptions(
+        &self,
+        guild_id: GuildId,
+        exemptions: &[Exemption],
+    ) -> Result<(), Error> {
+        match self {
+            Self::Memory(s) => s.cache_exemptions(guild_id, exemptions).await,
+            #[cfg(feature = "surreal")]
+            Self::Surreal(s) => s.cache_exemptions(guild_id, exemptions).await,
+        }
+    }
+
+    async fn invalidate_guild(&self, guild_id: GuildId) -> Result<(), Error> {
+        match self {
+            Self::Memory(s) => s.invalidate_guild(guild_id).await,
+            #[cfg(feature = "surreal")]
+            Self::Surreal(s) => s.invalidate_guild(guild_id).await,
+        }
+    }
+
+    async fn record_action(
+        &self,
+        action: UserAction,
+        limits: &[Limit],
+    ) -> Result<WindowOutcome, Error> {
+        match self {
+            Self::Memory(s) => s.record_action(action, limits).await,
+            #[cfg(feature = "surreal")]
+            Self::Surreal(s) => s.record_action(action, limits).await,
+        }
+    }
+}
+
+/// An in-process store, this is the default and needs no external services
+///
+/// As guilds are only handled by a single cluster, keeping this state in-process is enough
+pub struct MemoryStore {
+    /// Guild ID to the limits of the guild
+    limits: Cache<GuildId, Arc<Vec<Limit>>>,
+    /// Guild ID to the exemptions of the guild
+    exemptions: Cache<GuildId, Arc<Vec<Exemption>>>,
+    /// The sliding windows of all (guild, user, limit type) combinations that have recently performed an action
+    ///
+    /// Windows expire once they have been idle for longer than the longest possible limit interval
+    windows: Cache<(GuildId, UserId, UserLimitTypes), Arc<Mutex<ActionWindow>>>,
+}
+
+impl Default for MemoryStore {
+    fn default() -> Self {
+        Self {
+            limits: Cache::builder()
+                .time_to_live(std::time::Duration::from_secs(300))
+                .build(),
+            exemptions: Cache::builder()
+                .time_to_live(std::time::Duration::from_secs(300))
+                .build(),
+            windows: Cache::builder()
+                .time_to_idle(std::time::Duration::from_secs(
+                    (LIMIT_ACTION_RETENTION_DAYS * 86400) as u64,
+                ))
+                .support_invalidation_closures()
+                .build(),
+        }
+    }
+}
+
+impl LimitStore for MemoryStore {
+    async fn cached_limits(&self, guild_id: GuildId) -> Result<Option<Vec<Limit>>, Error> {
+        Ok(self.limits.get(&guild_id).await.map(|l| l.to_vec()))
+    }
+
+    async fn cache_limits(&self, guild_id: GuildId, limits: &[Limit]) -> Result<(), Error> {
+        self.limits
+            .insert(guild_id, Arc::new(limits.to_vec()))
+            .await;
+        Ok(())
+    }
+
+    async fn cached_exemptions(&self, guild_id: GuildId) -> Result<Option<Vec<Exemption>>, Error> {
+        Ok(self.exemptions.get(&guild_id).await.map(|e| e.to_vec()))
+    }
+
+    async fn cache_exemptions(
+        &self,
+        guild_id: GuildId,
+        exemptions: &[Exemption],
+    ) -> Result<(), Error> {
+        self.exemptions
+            .insert(guild_id, Arc::new(exemptions.to_vec()))
+            .await;
+        Ok(())
+    }
+
+    async fn invalidate_guild(&self, guild_id: GuildId) -> Result<(), Error> {
+        self.limits.invalidate(&guild_id).await;
+        self.exemptions.invalidate(&guild_id).await;
+        self.windows
+            .invalidate_entries_if(move |(g, _, _), _| *g == guild_id)?;
+        Ok(())
+    }
+
+    async fn record_action(
+        &self,
+        action: UserAction,
+        limits: &[Limit],
+    ) -> Result<WindowOutcome, Error> {
+        let key = (action.guild_id, action.user_id, action.limit_type);
+        let repeat_rate = action.limit_type.default_user_target_repeat_rate();
+
+        let window = self.windows.get_with(key, async { Arc::default() }).await;
+
+        let outcome = window
+            .lock()
+            .map_err(|_| "Action window lock poisoned")?
+            .record(action, limits, repeat_rate);
+
+        Ok(outcome)
+    }
+}
+
+#[cfg(feature = "surreal")]
+pub mod surreal {
+    use super::*;
+    use dashmap::DashMap;
+    use serde::{Deserialize, Serialize};
+    use surrealdb::engine::remote::ws::{Client, Ws};
+    use surrealdb::opt::auth::Root;
+    use surrealdb::Surreal;
+
+    /// A sliding window as stored in SurrealDB
+    #[derive(Serialize, Deserialize)]
+    struct StoredWindow {
+        guild_id: String,
+        window: ActionWindow,
+    }
+
+    /// The limits of a guild as stored in SurrealDB
+    #[derive(Serialize, Deserialize)]
+    struct StoredLimits {
+        guild_id: String,
+        limits: Vec<Limit>,
+    }
+
+    /// The exemptions of a guild as stored in SurrealDB
+    #[derive(Serialize, Deserialize)]
+    struct StoredExemptions {
+        guild_id: String,
+        exemptions: Vec<Exemption>,
+    }
+
+    /// A store backed by a (remote) SurrealDB instance
+    pub struct SurrealStore {
+        db: Surreal<Client>,
+        /// Window ID to the lock serializing the read-modify-write of the window in `record_action`
+        ///
+        /// As guilds are only handled by a single cluster, in-process locks are enough here
+        window_locks: DashMap<String, Arc<tokio::sync::Mutex<()>>>,
+    }
+
+    impl SurrealStore {
+        pub async fn connect(config: &crate::config::Surreal) -> Result<Self, Error> {
+            let db = Surreal::new::<Ws>(config.url.as_str()).await?;
+
+            db.signin(Root {
+                username: config.username.as_str(),
+                password: config.password.as_str(),
+            })
+            .await?;
+
+            db.use_ns("antiraid").use_db("splashtail").await?;
+
+            Ok(Self {
+                db,
+                window_locks: DashMap::new(),
+            })
+        }
+
+        fn window_id(action: &UserAction) -> String {
+            format!(
+                "{}_{}_{}",
+                action.guild_id, action.user_id, action.limit_type
+            )
+        }
+
+        /// Records an action in a stored window, the caller must hold the lock of the window
+        async fn update_window(
+            &self,
+            id: &str,
+            guild_id: String,
+            action: UserAction,
+            limits: &[Limit],
+            repeat_rate: i64,
+        ) -> Result<WindowOutcome, Error> {
+            let stored: Option<StoredWindow> = self.db.select(("action_windows", id)).await?;
+
+            let mut window = stored.map(|s| s.window).unwrap_or_default();
+            let outcome = window.record(action, limits, repeat_rate);
+
+            let _: Option<StoredWindow> = self
+                .db
+                .update(("action_windows", id))
+                .content(StoredWindow { guild_id, window })
+                .await?;
+
+            Ok(outcome)
+        }
+    }
+
+    impl LimitStore for SurrealStore {
+        async fn cached_limits(&self, guild_id: GuildId) -> Result<Option<Vec<Limit>>, Error> {
+            let stored: Option<StoredLimits> = self
+                .db
+                .select(("guild_limits", guild_id.to_string()))
+                .await?;
+
+            Ok(stored.map(|s| s.limits))
+        }
+
+        async fn cache_limits(&self, guild_id: GuildId, limits: &[Limit]) -> Result<(), Error> {
+            // Stored as a single record so that a guild without limits can be told apart from an uncached one
+            let _: Option<StoredLimits> = self
+                .db
+                .update(("guild_limits", guild_id.to_string()))
+                .content(StoredLimits {
+                    guild_id: guild_id.to_string(),
+                    limits: limits.to_vec(),
+                })
+                .await?;
+
+            Ok(())
+        }
+
+        async fn cached_exemptions(
+            &self,
+            guild_id: GuildId,
+        ) -> Result<Option<Vec<Exemption>>, Error> {
+            let stored: Option<StoredExemptions> = self
+                .db
+                .select(("guild_exemptions", guild_id.to_string()))
+                .await?;
+
+            Ok(stored.map(|s| s.exemptions))
+        }
+
+        async fn cache_exemptions(
+            &self,
+            guild_id: GuildId,
+            exemptions: &[Exemption],
+        ) -> Result<(), Error> {
+            // Stored as a single record so that a guild without exemptions can be told apart from an uncached one
+            let _: Option<StoredExemptions> = self
+                .db
+                .update(("guild_exemptions", guild_id.to_string()))
+                .content(StoredExemptions {
+                    guild_id: guild_id.to_string(),
+                    exemptions: exemptions.to_vec(),
+                })
+                .await?;
+
+            Ok(())
+        }
+
+        async fn invalidate_guild(&self, guild_id: GuildId) -> Result<(), Error> {
+            self.db
+                .query("delete guild_exemptions where guild_id=type::string($guild_id) return none")
+                .query("delete guild_limits where guild_id=type::string($guild_id) return none")
+                .query("delete action_windows where guild_id=type::string($guild_id) return none")
+                .bind(("guild_id", guild_id.to_string()))
+                .await?;
+
+            Ok(())
+        }
+
+        async fn record_action(
+            &self,
+            action: UserAction,
+            limits: &[Limit],
+        ) -> Result<WindowOutcome, Error> {
+            let id = Self::window_id(&action);
+            let guild_id = action.guild_id.to_string();
+            let repeat_rate = action.limit_type.default_user_target_repeat_rate();
+
+            let lock = self.window_locks.entry(id.clone()).or_default().clone();
+            let guard = lock.lock_owned().await;
+
+            let outcome = self
+                .update_window(&id, guild_id, action, limits, repeat_rate)
+                .await;
+
+            // Locks are only kept for as long as someone is waiting on them
+            drop(guard);
+            self.window_locks
+                .remove_if(&id, |_, l| Arc::strong_count(l) == 1);
+
+            outcome
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::limits::core::{LimitMode, UserLimitActions};
+    use sqlx::types::chrono::Utc;
+
+    fn action(id: &str, user_id: u64) -> UserAction {
+        UserAction {
+            action_id: id.to_string(),
+            limit_type: UserLimitTypes::ChannelRemove,
+            created_at: Utc::now(),
+            user_id: UserId::new(user_id),
+            guild_id: GuildId::new(1),
+            action_data: serde_json::json!({}),
+            limits_hit: Vec::new(),
+            target: id.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_memory_store() {
+        let store = MemoryStore::default();
+        let guild_id = GuildId::new(1);
+
+        let limits = vec![Limit {
+            guild_id,
+            limit_id: "l".to_string(),
+            limit_name: "l".to_string(),
+            limit_type: UserLimitTypes::ChannelRemove,
+            limit_action: UserLimitActions::BanUser,
+            limit_per: 1,
+            limit_time: 60,
+            limit_tiers: vec![],
+            escalation_window: 86400,
+            timeout_duration: 600,
+            quarantine_role: None,
+            rollback: false,
+            mode: LimitMode::Enforce,
+        }];
+
+        assert!(store.cached_limits(guild_id).await.unwrap().is_none());
+        store.cache_limits(guild_id, &limits).await.unwrap();
+        assert_eq!(
+            store.cached_limits(guild_id).await.unwrap().unwrap().len(),
+            1
+        );
+
+        // An empty list of limits is still cached
+        store.cache_limits(GuildId::new(2), &[]).await.unwrap();
+        assert!(store
+            .cached_limits(GuildId::new(2))
+            .await
+            .unwrap()
+            .is_some());
+
+        // An empty list of exemptions is still cached
+        assert!(store.cached_exemptions(guild_id).await.unwrap().is_none());
+        store.cache_exemptions(guild_id, &[]).await.unwrap();
+        assert!(store.cached_exemptions(guild_id).await.unwrap().is_some());
+
+        // Windows are per user
+        for (id, user_id) in [("a", 2), ("b", 3)] {
+            assert_eq!(
+                store
+                    .record_action(action(id, user_id), &limits)
+                    .await
+                    .unwrap(),
+                WindowOutcome::Recorded {
+                    hits: vec![],
+                    cause: vec![]
+                }
+            );
+        }
+
+        let WindowOutcome::Recorded { hits, .. } =
+            store.record_action(action("c", 2), &limits).await.unwrap()
+        else {
+            panic!("action was ignored")
+        };
+        assert_eq!(hits, vec!["l"]);
+
+        // Invalidating a guild clears its limits, exemptions and windows
+        store.invalidate_guild(guild_id).await.unwrap();
+        assert!(store.cached_limits(guild_id).await.unwrap().is_none());
+        assert!(store.cached_exemptions(guild_id).await.unwrap().is_none());
+        for user_id in [2, 3] {
+            assert!(store
+                .windows
+                .get(&(
+                    guild_id,
+                    UserId::new(user_id),
+                    UserLimitTypes::ChannelRemove
+                ))
+                .await
+                .is_none());
+        }
+    }
+}
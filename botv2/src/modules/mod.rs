@@ -1,4 +1,5 @@
 // Auto-generated by build.rs
+mod auditlogs;
 mod core;
 mod gitlogs;
 mod limits;
@@ -11,6 +12,7 @@ mod root;
 /// List of modules available. Not all may be enabled
 pub fn modules() -> Vec<crate::silverpelt::Module> {
     vec![
+        auditlogs::module(),
         core::module(),
         gitlogs::module(),
         limits::module(),
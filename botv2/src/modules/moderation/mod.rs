@@ -11,6 +11,8 @@ pub fn module() -> crate::silverpelt::Module {
         virtual_module: false,
         web_hidden: false,
         is_default_enabled: true,
+        depends_on: vec![],
+        conflicts_with: vec![],
         commands: vec![
             (
                 cmd::kick(),
@@ -25,6 +27,8 @@ pub fn module() -> crate::silverpelt::Module {
                 },
             )
         ],
+        config_options: vec![],
+        event_filter: vec![],
         event_handlers: vec![],
     }
 }
@@ -10,11 +10,15 @@ pub fn module() -> crate::silverpelt::Module {
         virtual_module: false,
         web_hidden: true,
         is_default_enabled: true,
+        depends_on: vec![],
+        conflicts_with: vec![],
         // These commands do not follow the typical permission system anyways
         commands: vec![(
             cmds::register(),
             crate::silverpelt::CommandExtendedData::none(),
         )],
+        config_options: vec![],
+        event_filter: vec![],
         event_handlers: vec![], // Root has no event listeners
     }
 }
@@ -11,6 +11,8 @@ pub fn module() -> crate::silverpelt::Module {
         virtual_module: false,
         web_hidden: false,
         is_default_enabled: true,
+        depends_on: vec![],
+        conflicts_with: vec![],
         commands: vec![(
             cmds::backups(),
             indexmap! {
@@ -20,6 +22,8 @@ pub fn module() -> crate::silverpelt::Module {
                 "restore" => crate::silverpelt::CommandExtendedData::kittycat_or_admin("server_backups", "restore"),
             },
         )],
+        config_options: vec![],
+        event_filter: vec![],
         event_handlers: vec![], // Root has no event listeners
     }
 }
@@ -8,7 +8,11 @@ pub fn module() -> crate::silverpelt::Module {
         virtual_module: false,
         web_hidden: false,
         is_default_enabled: false,
+        depends_on: vec!["server_backups"],
+        conflicts_with: vec![],
         commands: vec![],
+        config_options: vec![],
+        event_filter: vec![],
         event_handlers: vec![],
     }
 }
@@ -0,0 +1,373 @@
+use crate::silverpelt::perm_expr::PermissionExpr;
+use crate::silverpelt::silverpelt_cache::{invalidate_guild_cache, SILVERPELT_CACHE};
+use crate::silverpelt::{CommandOverrideScope, CooldownConfiguration};
+use poise::CreateReply;
+use serenity::all::{ChannelType, GuildChannel, GuildId, Role};
+
+type Error = crate::Error;
+type Context<'a> = crate::Context<'a>;
+
+#[poise::command(
+    prefix_command,
+    slash_command,
+    user_cooldown = 1,
+    guild_cooldown = 1,
+    subcommands(
+        "commands_setoverride",
+        "commands_deloverride",
+        "commands_overrides",
+        "commands_setcooldown",
+        "commands_resetcooldown",
+    )
+)]
+pub async fn commands(
+    _ctx: Context<'_>,
+) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Normalizes a command name and checks that its commands can be configured
+fn validate_command(command: &str) -> Result<String, Error> {
+    let command = command.split_whitespace().collect::<Vec<&str>>().join(" ");
+
+    let Some(base_command) = command.split(' ').next() else {
+        return Err("No command was provided".into());
+    };
+
+    let registry = SILVERPELT_CACHE.registry();
+
+
+    let Some(module) = registry.module_of_command(base_command) else {
+        return Err(format!("The command ``{}`` does not exist", command).into());
+    };
+
+    if !registry.command_exists(&command) {
+        return Err(format!("The command ``{}`` does not exist", command).into());
+    }
+
+    let Some(module_data) = registry.module(module) else {
+        return Err(format!("The module ``{}`` does not exist", module).into());
+    };
+
+    if !module_data.commands_configurable || module_data.id == "root" {
+        return Err(format!("Commands in the module ``{}`` cannot be configured", module_data.id).into());
+    }
+
+    Ok(command)
+}
+
+/// Returns the override scope from a channel/role pair, exactly one of which must be set and be in the given guild
+fn get_scope(guild_id: GuildId, channel: Option<GuildChannel>, role: Option<Role>) -> Result<CommandOverrideScope, Error> {
+    match (channel, role) {
+        (Some(channel), None) => {
+            if channel.guild_id != guild_id {
+                return Err("The channel provided does not exist in this server".into());
+            }
+
+            if channel.kind == ChannelType::Category {
+                Ok(CommandOverrideScope::Category(channel.id))
+            } else {
+                Ok(CommandOverrideScope::Channel(channel.id))
+            }
+        }
+        (None, Some(role)) => {
+            if role.guild_id != guild_id {
+                return Err("The role provided does not exist in this server".into());
+            }
+
+            Ok(CommandOverrideScope::Role(role.id))
+        }
+        _ => Err("You must provide exactly one of a channel/category or a role".into()),
+    }
+}
+
+/// Sets (or resets if None) the cooldown of a command in the guild command configuration
+async fn update_command_cooldown(
+    data: &crate::Data,
+    guild_id: GuildId,
+    command: &str,
+    cooldown: Option<CooldownConfiguration>,
+) -> Result<(), Error> {
+    let cooldown = cooldown.map(serde_json::to_value).transpose()?;
+
+    let mut tx = data.pool.begin().await?;
+
+    let existing = sqlx::query!(
+        "SELECT id FROM guild_command_configurations WHERE guild_id = $1 AND command = $2 FOR UPDATE",
+        guild_id.to_string(),
+        command
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    if existing.is_some() {
+        sqlx::query!(
+            "UPDATE guild_command_configurations SET cooldown = $1 WHERE guild_id = $2 AND command = $3",
+            cooldown,
+            guild_id.to_string(),
+            command
+        )
+        .execute(&mut *tx)
+        .await?;
+    } else if cooldown.is_some() {
+        sqlx::query!(
+            "INSERT INTO guild_command_configurations (guild_id, command, cooldown) VALUES ($1, $2, $3)",
+            guild_id.to_string(),
+            command,
+            cooldown
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    invalidate_guild_cache(&data.animus_magic_ipc, guild_id).await;
+
+    Ok(())
+}
+
+/// Overrides whether a command is enabled and/or its permissions in a channel, category or for a role
+#[poise::command(
+    prefix_command,
+    slash_command,
+    user_cooldown = 1,
+    guild_cooldown = 1,
+    rename = "setoverride",
+)]
+pub async fn commands_setoverride(
+    ctx: Context<'_>,
+    #[description = "The command to override, e.g. 'backups'"]
+    command: String,
+    #[description = "The channel or category to override the command in"]
+    channel: Option<GuildChannel>,
+    #[description = "The role to override the command for"]
+    role: Option<Role>,
+    #[description = "Whether or not the command should be disabled. Omit to use the server configuration"]
+    disabled: Option<bool>,
+    #[description = "The permission expression to use. Omit to use the server configuration"]
+    expression: Option<String>,
+) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Err("This command must be run in a guild".into());
+    };
+
+    let command = validate_command(&command)?;
+    let scope = get_scope(guild_id, channel, role)?;
+
+    if disabled.is_none() && expression.is_none() {
+        return Err("You must set at least one of disabled or expression. Use `/commands deloverride` to remove an override".into());
+    }
+
+    let perms = match expression {
+        Some(expression) => Some(serde_json::to_value(expression.parse::<PermissionExpr>()?.to_checks()?)?),
+        None => None,
+    };
+
+    let data = ctx.data();
+
+    sqlx::query!(
+        "INSERT INTO guild_command_overrides (guild_id, command, scope_type, scope_id, perms, disabled) VALUES ($1, $2, $3, $4, $5, $6) ON CONFLICT (guild_id, command, scope_type, scope_id) DO UPDATE SET perms = EXCLUDED.perms, disabled = EXCLUDED.disabled",
+        guild_id.to_string(),
+        command,
+        scope.scope_type(),
+        scope.scope_id(),
+        perms,
+        disabled,
+    )
+    .execute(&data.pool)
+    .await?;
+
+    invalidate_guild_cache(&data.animus_magic_ipc, guild_id).await;
+
+    ctx.say(format!("Override for ``{}`` in {} set successfully", command, scope)).await?;
+
+    Ok(())
+}
+
+/// Removes a command override from a channel, category or role
+#[poise::command(
+    prefix_command,
+    slash_command,
+    user_cooldown = 1,
+    guild_cooldown = 1,
+    rename = "deloverride",
+)]
+pub async fn commands_deloverride(
+    ctx: Context<'_>,
+    #[description = "The command to remove the override of"]
+    command: String,
+    #[description = "The channel or category the override is in"]
+    channel: Option<GuildChannel>,
+    #[description = "The role the override is for"]
+    role: Option<Role>,
+) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Err("This command must be run in a guild".into());
+    };
+
+    let command = validate_command(&command)?;
+    let scope = get_scope(guild_id, channel, role)?;
+
+    let data = ctx.data();
+
+    let res = sqlx::query!(
+        "DELETE FROM guild_command_overrides WHERE guild_id = $1 AND command = $2 AND scope_type = $3 AND scope_id = $4",
+        guild_id.to_string(),
+        command,
+        scope.scope_type(),
+        scope.scope_id(),
+    )
+    .execute(&data.pool)
+    .await?;
+
+    if res.rows_affected() == 0 {
+        return Err(format!("No override for ``{}`` exists in {}", command, scope).into());
+    }
+
+    invalidate_guild_cache(&data.animus_magic_ipc, guild_id).await;
+
+    ctx.say(format!("Override for ``{}`` in {} removed successfully", command, scope)).await?;
+
+    Ok(())
+}
+
+/// Lists all command overrides on this server
+#[poise::command(
+    prefix_command,
+    slash_command,
+    user_cooldown = 1,
+    guild_cooldown = 1,
+    rename = "overrides",
+)]
+pub async fn commands_overrides(
+    ctx: Context<'_>,
+) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Err("This command must be run in a guild".into());
+    };
+
+    let data = ctx.data();
+
+    let overrides = sqlx::query!(
+        "SELECT command, scope_type, scope_id, perms, disabled FROM guild_command_overrides WHERE guild_id = $1 ORDER BY command, scope_type",
+        guild_id.to_string()
+    )
+    .fetch_all(&data.pool)
+    .await?;
+
+    if overrides.is_empty() {
+        ctx.say("No command overrides have been configured").await?;
+        return Ok(());
+    }
+
+    let mut reply = CreateReply::default();
+
+    for chunk in overrides.chunks(crate::impls::utils::MAX_EMBED_FIELDS) {
+        let mut embed = serenity::all::CreateEmbed::default()
+            .title("Command Overrides")
+            .description("Channel, category and role overrides of command configurations");
+
+        for rec in chunk {
+            let scope = match CommandOverrideScope::from_db(&rec.scope_type, &rec.scope_id) {
+                Ok(scope) => scope.to_string(),
+                Err(_) => format!("{} {}", rec.scope_type, rec.scope_id),
+            };
+
+            let perms = match rec.perms.clone().map(crate::silverpelt::perm_expr::checks_from_value) {
+                Some(Ok(Some(checks))) => match PermissionExpr::from_checks(&checks) {
+                    Ok(Some(expr)) => format!("``{}``", expr),
+                    Ok(None) => "No permissions needed".to_string(),
+                    Err(_) => checks.to_string(),
+                },
+                Some(Err(e)) => format!("Invalid: {}", e),
+                _ => "Server configuration".to_string(),
+            };
+
+            embed = embed.field(
+                rec.command.clone(),
+                format!(
+                    "Scope: {}\nDisabled: {}\nPermissions: {}",
+                    scope,
+                    rec.disabled.map(|d| d.to_string()).unwrap_or("Server configuration".to_string()),
+                    perms
+                ),
+                false,
+            );
+        }
+
+        reply = reply.embed(embed);
+    }
+
+    ctx.send(reply).await?;
+
+    Ok(())
+}
+
+/// Sets the cooldowns (in seconds) of a command. Unset cooldowns use the module/default cooldowns, 0 removes a cooldown
+#[poise::command(
+    prefix_command,
+    slash_command,
+    user_cooldown = 1,
+    guild_cooldown = 1,
+    rename = "setcooldown",
+)]
+pub async fn commands_setcooldown(
+    ctx: Context<'_>,
+    #[description = "The command to set the cooldown of, e.g. 'backups create'"]
+    command: String,
+    #[description = "Cooldown per user in seconds"]
+    user: Option<u64>,
+    #[description = "Cooldown per server in seconds"]
+    guild: Option<u64>,
+    #[description = "Cooldown per channel in seconds"]
+    channel: Option<u64>,
+) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Err("This command must be run in a guild".into());
+    };
+
+    let command = validate_command(&command)?;
+
+    if user.is_none() && guild.is_none() && channel.is_none() {
+        return Err("You must set at least one cooldown. Use `/commands resetcooldown` to reset the cooldowns of a command".into());
+    }
+
+    let cooldown = CooldownConfiguration {
+        user,
+        guild,
+        channel,
+    };
+
+    update_command_cooldown(&ctx.data(), guild_id, &command, Some(cooldown)).await?;
+
+    ctx.say(format!("Cooldowns of ``{}`` set to {}", command, cooldown)).await?;
+
+    Ok(())
+}
+
+/// Resets the cooldowns of a command to the module/default cooldowns
+#[poise::command(
+    prefix_command,
+    slash_command,
+    user_cooldown = 1,
+    guild_cooldown = 1,
+    rename = "resetcooldown",
+)]
+pub async fn commands_resetcooldown(
+    ctx: Context<'_>,
+    #[description = "The command to reset the cooldown of"]
+    command: String,
+) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Err("This command must be run in a guild".into());
+    };
+
+    let command = validate_command(&command)?;
+
+    update_command_cooldown(&ctx.data(), guild_id, &command, None).await?;
+
+    ctx.say(format!("Cooldowns of ``{}`` reset", command)).await?;
+
+    Ok(())
+}
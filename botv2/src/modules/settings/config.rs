@@ -0,0 +1,218 @@
+use crate::silverpelt::config_opt::WebFieldType;
+use crate::silverpelt::module_config::{get_config_option, get_module_option, reset_module_option, set_module_option};
+
+type Error = crate::Error;
+type Context<'a> = crate::Context<'a>;
+
+#[poise::command(
+    prefix_command,
+    slash_command,
+    user_cooldown = 1,
+    guild_cooldown = 1,
+    subcommands(
+        "settings_config",
+    )
+)]
+pub async fn settings(
+    _ctx: Context<'_>,
+) -> Result<(), Error> {
+    Ok(())
+}
+
+#[poise::command(
+    prefix_command,
+    slash_command,
+    user_cooldown = 1,
+    guild_cooldown = 1,
+    rename = "config",
+    subcommands(
+        "settings_config_get",
+        "settings_config_set",
+        "settings_config_reset",
+    )
+)]
+pub async fn settings_config(
+    _ctx: Context<'_>,
+) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Formats a configuration value for display in Discord
+fn format_value(field_type: &WebFieldType, value: &serde_json::Value) -> String {
+    match (field_type, value) {
+        (WebFieldType::Optional(_), serde_json::Value::Null) => return "Not set".to_string(),
+        (WebFieldType::Optional(inner), _) => return format_value(inner, value),
+        (WebFieldType::List(inner), serde_json::Value::Array(values)) => {
+            if values.is_empty() {
+                return "None".to_string();
+            }
+
+            return values.iter().map(|v| format_value(inner, v)).collect::<Vec<String>>().join(", ");
+        }
+        (WebFieldType::Duration(_), _) => {
+            if let Some(secs) = value.as_u64() {
+                return format!("``{:?}``", std::time::Duration::from_secs(secs));
+            }
+        }
+        _ => {}
+    }
+
+    let Some(s) = value.as_str() else {
+        return format!("``{}``", value);
+    };
+
+    match field_type {
+        WebFieldType::User(_) => format!("<@{}>", s),
+        WebFieldType::Channel(_) => format!("<#{}>", s),
+        WebFieldType::Role(_) => format!("<@&{}>", s),
+        _ => format!("``{}``", s),
+    }
+}
+
+/// Ensures that any channels and roles in a configuration value belong to the guild
+fn check_guild_ids(guild: &serenity::all::Guild, field_type: &WebFieldType, value: &serde_json::Value) -> Result<(), Error> {
+    match (field_type, value) {
+        (WebFieldType::Channel(_), _) => {
+            let channel_id = value.as_str().unwrap_or_default().parse::<serenity::all::ChannelId>()?;
+
+            if !guild.channels.contains_key(&channel_id) {
+                return Err("The channel provided does not exist in this server".into());
+            }
+        }
+        (WebFieldType::Role(_), _) => {
+            let role_id = value.as_str().unwrap_or_default().parse::<serenity::all::RoleId>()?;
+
+            if !guild.roles.contains_key(&role_id) {
+                return Err("The role provided does not exist in this server".into());
+            }
+        }
+        (WebFieldType::List(inner), serde_json::Value::Array(values)) => {
+            for value in values {
+                check_guild_ids(guild, inner, value)?;
+            }
+        }
+        (WebFieldType::Optional(inner), _) if !value.is_null() => check_guild_ids(guild, inner, value)?,
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// Gets the value of a module configuration option
+#[poise::command(
+    prefix_command,
+    slash_command,
+    user_cooldown = 1,
+    guild_cooldown = 1,
+    rename = "get",
+)]
+pub async fn settings_config_get(
+    ctx: Context<'_>,
+    #[description = "The module the option belongs to"]
+    #[autocomplete = "crate::silverpelt::poise_ext::module_list::autocomplete"]
+    module: String,
+    #[description = "The option to get"]
+    #[autocomplete = "crate::silverpelt::poise_ext::config_option_list::autocomplete"]
+    option: String,
+) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Err("This command must be run in a guild".into());
+    };
+
+    let config_option = get_config_option(&module, &option)?;
+
+    let value = get_module_option(&ctx.data().pool, guild_id, &module, &option).await?;
+
+    ctx.say(format!(
+        "**{}** ({}): {}",
+        config_option.name,
+        config_option.description,
+        format_value(&config_option.field_type, &value)
+    ))
+    .await?;
+
+    Ok(())
+}
+
+/// Sets the value of a module configuration option
+#[poise::command(
+    prefix_command,
+    slash_command,
+    user_cooldown = 1,
+    guild_cooldown = 1,
+    rename = "set",
+)]
+pub async fn settings_config_set(
+    ctx: Context<'_>,
+    #[description = "The module the option belongs to"]
+    #[autocomplete = "crate::silverpelt::poise_ext::module_list::autocomplete"]
+    module: String,
+    #[description = "The option to set"]
+    #[autocomplete = "crate::silverpelt::poise_ext::config_option_list::autocomplete"]
+    option: String,
+    #[description = "The new value of the option"]
+    value: String,
+) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Err("This command must be run in a guild".into());
+    };
+
+    let config_option = get_config_option(&module, &option)?;
+
+    let value = config_option.field_type.parse_value(&value)?;
+
+    // Ensure channels and roles belong to this server
+    {
+        let Some(guild) = ctx.guild() else {
+            return Err("This command must be run in a guild".into());
+        };
+
+        check_guild_ids(&guild, &config_option.field_type, &value)?;
+    }
+
+    set_module_option(&ctx.data().pool, guild_id, &module, &option, value.clone()).await?;
+
+    ctx.say(format!(
+        "**{}** set to {}",
+        config_option.name,
+        format_value(&config_option.field_type, &value)
+    ))
+    .await?;
+
+    Ok(())
+}
+
+/// Resets a module configuration option to its default value
+#[poise::command(
+    prefix_command,
+    slash_command,
+    user_cooldown = 1,
+    guild_cooldown = 1,
+    rename = "reset",
+)]
+pub async fn settings_config_reset(
+    ctx: Context<'_>,
+    #[description = "The module the option belongs to"]
+    #[autocomplete = "crate::silverpelt::poise_ext::module_list::autocomplete"]
+    module: String,
+    #[description = "The option to reset"]
+    #[autocomplete = "crate::silverpelt::poise_ext::config_option_list::autocomplete"]
+    option: String,
+) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Err("This command must be run in a guild".into());
+    };
+
+    let config_option = get_config_option(&module, &option)?;
+
+    reset_module_option(&ctx.data().pool, guild_id, &module, &option).await?;
+
+    ctx.say(format!(
+        "**{}** reset to its default value ({})",
+        config_option.name,
+        format_value(&config_option.field_type, &config_option.field_type.default_value())
+    ))
+    .await?;
+
+    Ok(())
+}
@@ -1,5 +1,7 @@
 use indexmap::indexmap;
 
+mod commands;
+mod config;
 mod modules;
 mod perms;
 
@@ -13,12 +15,15 @@ pub fn module() -> crate::silverpelt::Module {
         virtual_module: false,
         web_hidden: false,
         is_default_enabled: true,
+        depends_on: vec![],
+        conflicts_with: vec![],
         commands: vec![
             (
                 modules::modules(),
                 indexmap! {
                     "enable" => crate::silverpelt::CommandExtendedData::kittycat_or_admin("modules", "enable"),
                     "disable" => crate::silverpelt::CommandExtendedData::kittycat_or_admin("modules", "disable"),
+                    "setcooldown" => crate::silverpelt::CommandExtendedData::kittycat_or_admin("modules", "setcooldown"),
                 },
             ),
             (
@@ -44,9 +49,35 @@ pub fn module() -> crate::silverpelt::Module {
                         },
                         ..Default::default()
                     },
+                    "user list" => crate::silverpelt::CommandExtendedData::kittycat_or_admin("perms", "user_list"),
+                    "user add" => crate::silverpelt::CommandExtendedData::kittycat_or_admin("perms", "user_edit"),
+                    "user remove" => crate::silverpelt::CommandExtendedData::kittycat_or_admin("perms", "user_edit"),
+                    "explain" => crate::silverpelt::CommandExtendedData::kittycat_or_admin("perms", "explain"),
+                    "setcommand" => crate::silverpelt::CommandExtendedData::kittycat_or_admin("perms", "setcommand"),
+                    "viewcommand" => crate::silverpelt::CommandExtendedData::kittycat_or_admin("perms", "viewcommand"),
+                },
+            ),
+            (
+                commands::commands(),
+                indexmap! {
+                    "setoverride" => crate::silverpelt::CommandExtendedData::kittycat_or_admin("commands", "setoverride"),
+                    "deloverride" => crate::silverpelt::CommandExtendedData::kittycat_or_admin("commands", "deloverride"),
+                    "overrides" => crate::silverpelt::CommandExtendedData::kittycat_or_admin("commands", "overrides"),
+                    "setcooldown" => crate::silverpelt::CommandExtendedData::kittycat_or_admin("commands", "setcooldown"),
+                    "resetcooldown" => crate::silverpelt::CommandExtendedData::kittycat_or_admin("commands", "resetcooldown"),
+                },
+            ),
+            (
+                config::settings(),
+                indexmap! {
+                    "config get" => crate::silverpelt::CommandExtendedData::kittycat_or_admin("config", "get"),
+                    "config set" => crate::silverpelt::CommandExtendedData::kittycat_or_admin("config", "set"),
+                    "config reset" => crate::silverpelt::CommandExtendedData::kittycat_or_admin("config", "reset"),
                 },
             ),
         ],
+        config_options: vec![],
+        event_filter: vec![],
         event_handlers: vec![], // Settings has no event listeners
     }
 }
@@ -1,4 +1,6 @@
-use crate::silverpelt::silverpelt_cache::SILVERPELT_CACHE;
+use crate::silverpelt::module_config::get_module_states;
+use crate::silverpelt::silverpelt_cache::{invalidate_guild_cache, SILVERPELT_CACHE};
+use crate::silverpelt::utils::check_module_toggle;
 
 type Error = crate::Error;
 type Context<'a> = crate::Context<'a>;
@@ -11,6 +13,7 @@ type Context<'a> = crate::Context<'a>;
     subcommands(
         "modules_enable",
         "modules_disable",
+        "modules_setcooldown",
     )
 )]
 pub async fn modules(
@@ -38,7 +41,8 @@ pub async fn modules_enable(
     };
 
    // Check that the module exists
-   let Some(module_data) = SILVERPELT_CACHE.module_id_cache.get(&module) else {
+    let registry = SILVERPELT_CACHE.registry();
+    let Some(module_data) = registry.module(&module) else {
         return Err(
             format!(
                 "The module you are trying to disable ({}) does not exist",
@@ -51,8 +55,6 @@ pub async fn modules_enable(
         return Err("This module cannot be enabled/disabled".into());
     }
 
-    drop(module_data);
-
     // Check for a module_configuration in db
     // If it doesn't exist, create it
     let data = ctx.data();
@@ -66,12 +68,26 @@ pub async fn modules_enable(
     .fetch_optional(&mut *tx)
     .await?;
 
-    if let Some(disabled) = disabled {
+    if let Some(disabled) = &disabled {
         // We have a module, now check
         if disabled.disabled.is_some() && !disabled.disabled.unwrap_or_default() {
             return Err("Module is already enabled".into());
         }
+    }
+
+    // Check that enabling the module does not break any dependencies or conflicts
+    {
+        let states = get_module_states(&mut *tx, guild_id).await?;
+
+        check_module_toggle(
+            &registry.modules().collect::<Vec<_>>(),
+            &states,
+            &module,
+            true,
+        )?;
+    }
 
+    if disabled.is_some() {
         sqlx::query!(
             "UPDATE guild_module_configurations SET disabled = false WHERE guild_id = $1 AND module = $2",
             guild_id.to_string(),
@@ -92,17 +108,7 @@ pub async fn modules_enable(
 
     tx.commit().await?;
 
-    SILVERPELT_CACHE.module_enabled_cache.remove(&(guild_id, module)).await;
-
-    tokio::spawn(async move {
-        if let Err(err) = SILVERPELT_CACHE.command_permission_cache.invalidate_entries_if(move |k, _| {
-            k.0 == guild_id
-        }) {
-            log::error!("Failed to invalidate command permission cache for guild {}: {}", guild_id, err);
-        } else {
-            log::info!("Invalidated cache for guild {}", guild_id);
-        }
-    });
+    invalidate_guild_cache(&data.animus_magic_ipc, guild_id).await;
 
     ctx.say("Module enabled successfully!").await?;
 
@@ -128,7 +134,8 @@ pub async fn modules_disable(
     };
 
     // Check that the module exists
-    let Some(module_data) = SILVERPELT_CACHE.module_id_cache.get(&module) else {
+    let registry = SILVERPELT_CACHE.registry();
+    let Some(module_data) = registry.module(&module) else {
         return Err(
             format!(
                 "The module you are trying to disable ({}) does not exist",
@@ -141,8 +148,6 @@ pub async fn modules_disable(
         return Err("This module cannot be enabled/disabled".into());
     }
 
-    drop(module_data);
-
     // Check for a module_configuration in db
     // If it doesn't exist, create it
     let data = ctx.data();
@@ -156,12 +161,26 @@ pub async fn modules_disable(
     .fetch_optional(&mut *tx)
     .await?;
 
-    if let Some(disabled) = disabled {
+    if let Some(disabled) = &disabled {
         // We have a module, now check
         if disabled.disabled.is_some() && disabled.disabled.unwrap_or_default() {
             return Err("Module is already disabled".into());
         }
+    }
 
+    // Check that disabling the module does not break any modules depending on it
+    {
+        let states = get_module_states(&mut *tx, guild_id).await?;
+
+        check_module_toggle(
+            &registry.modules().collect::<Vec<_>>(),
+            &states,
+            &module,
+            false,
+        )?;
+    }
+
+    if disabled.is_some() {
         sqlx::query!(
             "UPDATE guild_module_configurations SET disabled = true WHERE guild_id = $1 AND module = $2",
             guild_id.to_string(),
@@ -182,19 +201,103 @@ pub async fn modules_disable(
 
     tx.commit().await?;
 
-    SILVERPELT_CACHE.module_enabled_cache.remove(&(guild_id, module)).await;
-
-    tokio::spawn(async move {
-        if let Err(err) = SILVERPELT_CACHE.command_permission_cache.invalidate_entries_if(move |k, _| {
-            k.0 == guild_id
-        }) {
-            log::error!("Failed to invalidate command permission cache for guild {}: {}", guild_id, err);
-        } else {
-            log::info!("Invalidated cache for guild {}", guild_id);
-        }
-    });
+    invalidate_guild_cache(&data.animus_magic_ipc, guild_id).await;
 
     ctx.say("Module disabled successfully!").await?;
 
     Ok(())
 }
+
+/// Sets the cooldowns (in seconds) of all commands in a module. Omit all cooldowns to reset them
+#[poise::command(
+    prefix_command, 
+    slash_command, 
+    user_cooldown = 1,
+    guild_cooldown = 1,
+    rename = "setcooldown",
+)]
+pub async fn modules_setcooldown(
+    ctx: Context<'_>,
+    #[description = "The module to set the cooldowns of"] 
+    #[autocomplete = "crate::silverpelt::poise_ext::module_list::autocomplete"]
+    module: String,
+    #[description = "Cooldown per user in seconds"]
+    user: Option<u64>,
+    #[description = "Cooldown per server in seconds"]
+    guild: Option<u64>,
+    #[description = "Cooldown per channel in seconds"]
+    channel: Option<u64>,
+) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Err("This command must be run in a guild".into());
+    };
+
+    // Check that the module exists
+    let registry = SILVERPELT_CACHE.registry();
+    let Some(module_data) = registry.module(&module) else {
+        return Err(
+            format!(
+                "The module you are trying to configure ({}) does not exist",
+                module
+            ).into()
+        );
+    };
+
+    if !module_data.commands_configurable {
+        return Err("The commands of this module cannot be configured".into());
+    }
+
+    let cooldown = if user.is_none() && guild.is_none() && channel.is_none() {
+        None
+    } else {
+        Some(crate::silverpelt::CooldownConfiguration {
+            user,
+            guild,
+            channel,
+        })
+    };
+
+    let data = ctx.data();
+    let mut tx = data.pool.begin().await?;
+
+    let existing = sqlx::query!(
+        "SELECT id FROM guild_module_configurations WHERE guild_id = $1 AND module = $2 FOR UPDATE",
+        guild_id.to_string(),
+        module
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let cooldown_value = cooldown.map(serde_json::to_value).transpose()?;
+
+    if existing.is_some() {
+        sqlx::query!(
+            "UPDATE guild_module_configurations SET cooldown = $1 WHERE guild_id = $2 AND module = $3",
+            cooldown_value,
+            guild_id.to_string(),
+            module
+        )
+        .execute(&mut *tx)
+        .await?;
+    } else if cooldown_value.is_some() {
+        sqlx::query!(
+            "INSERT INTO guild_module_configurations (guild_id, module, cooldown) VALUES ($1, $2, $3)",
+            guild_id.to_string(),
+            module,
+            cooldown_value
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    invalidate_guild_cache(&data.animus_magic_ipc, guild_id).await;
+
+    match cooldown {
+        Some(cooldown) => ctx.say(format!("Cooldowns of module ``{}`` set to {}", module, cooldown)).await?,
+        None => ctx.say(format!("Cooldowns of module ``{}`` reset", module)).await?,
+    };
+
+    Ok(())
+}
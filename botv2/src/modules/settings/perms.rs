@@ -1,6 +1,12 @@
-use serenity::all::{Role, RoleId};
+use serenity::all::{Member, Mentionable, Role, RoleId, User, UserId};
 use poise::CreateReply;
-use crate::silverpelt::member_permission_calc::get_kittycat_perms;
+use crate::silverpelt::member_permission_calc::{get_kittycat_perms, rederive_perms};
+use crate::silverpelt::perm_expr::PermissionExpr;
+use crate::silverpelt::perm_presets::{parse_kittycat_perms, PermissionPreset};
+use crate::silverpelt::silverpelt_cache::{invalidate_guild_cache, invalidate_member_cache, SILVERPELT_CACHE};
+use crate::silverpelt::TemporaryPermsScope;
+use crate::impls::utils::{parse_duration_string, MAX_EMBED_FIELDS};
+use chrono::{DateTime, Utc};
 
 #[poise::command(
     prefix_command, 
@@ -10,7 +16,11 @@ use crate::silverpelt::member_permission_calc::get_kittycat_perms;
     subcommands(
         "perms_modrole",
         "perms_list",
-        "perms_deleterole"
+        "perms_deleterole",
+        "perms_user",
+        "perms_explain",
+        "perms_setcommand",
+        "perms_viewcommand"
     )
 )]
 pub async fn perms(
@@ -79,19 +89,51 @@ pub async fn perms_modrole(
     #[description = "The role to edit"]
     role: Role,
     #[description = "The permissions to set, separated by commas"]
-    perms: String,
+    #[autocomplete = "crate::silverpelt::poise_ext::kittycat_perm_list::autocomplete"]
+    perms: Option<String>,
+    #[description = "A preset of permissions to set, combined with any permissions given above"]
+    #[autocomplete = "crate::silverpelt::poise_ext::perm_preset_list::autocomplete"]
+    preset: Option<String>,
     #[description = "The index of the role"]
     index: Option<i32>,
+    #[description = "How long the newly added permissions last, e.g. 4h. Omit to add them permanently"]
+    expires_in: Option<String>,
 ) -> Result<(), crate::Error> {
-    let mut perms_vec = Vec::new();
+    let expires_at = expires_in.as_deref().map(|e| parse_expiry(e, Utc::now())).transpose()?;
+
+    let Some(guild_id) = ctx.guild_id() else {
+        return Err("You must be in a server to run this command".into());
+    };
+
+    let known_perms = guild_known_perms(&ctx.data().pool, guild_id).await?;
+
+    let perms_vec = {
+        let registry = SILVERPELT_CACHE.registry();
 
-    for perm in perms.split(',') {
-        if !perm.contains('.') {
-            return Err("Invalid permission format. Permission must be in format `<namespace>.<permission>`".into());
+        let mut perms_vec = Vec::new();
+
+        if let Some(ref preset) = preset {
+            let Some(preset) = PermissionPreset::find(preset) else {
+                return Err(format!("Unknown preset ``{}``", preset).into());
+            };
+
+            perms_vec.extend(preset.perms(&registry));
         }
 
-        perms_vec.push(perm.to_string());
-    }
+        if let Some(ref perms) = perms {
+            for perm in parse_kittycat_perms(perms, &known_perms.iter().map(|p| p.as_str()).collect::<Vec<&str>>())? {
+                if !perms_vec.contains(&perm) {
+                    perms_vec.push(perm);
+                }
+            }
+        }
+
+        if perms.is_none() && preset.is_none() {
+            return Err("You must provide either a list of permissions or a preset".into());
+        }
+
+        perms_vec
+    };
 
     let data = ctx.data();
     
@@ -131,10 +173,6 @@ pub async fn perms_modrole(
         }
     }
 
-    let Some(guild_id) = ctx.guild_id() else {
-        return Err("You must be in a server to run this command".into());
-    };
-
     let author_kittycat_perms = get_kittycat_perms(&data.pool, guild_id, member.user.id, &member.roles).await?;
 
     let mut tx = data.pool.begin().await?;
@@ -147,6 +185,8 @@ pub async fn perms_modrole(
     .fetch_optional(&mut *tx)
     .await?;
 
+    let current_perms = current.as_ref().map(|c| c.perms.clone()).unwrap_or_default();
+
     if let Some(current) = current {
         kittycat::perms::check_patch_changes(&author_kittycat_perms, &current.perms, &perms_vec)
         .map_err(|e| format!("You do not have permission to edit this role's permissions: {}", e))?;
@@ -242,9 +282,35 @@ pub async fn perms_modrole(
         .await?;
     }
 
+    if let Some(expires_at) = expires_at {
+        add_temporary_perms(
+            &mut tx,
+            guild_id,
+            TemporaryPermsScope::Role(role.id),
+            &current_perms,
+            &perms_vec,
+            expires_at,
+            member.user.id,
+        )
+        .await?;
+    }
+
+    sqlx::query!(
+        "UPDATE guild_members SET needs_perm_rederive = true WHERE guild_id = $1 AND $2 = ANY(roles)",
+        guild_id.to_string(),
+        role.id.to_string()
+    )
+    .execute(&mut *tx)
+    .await?;
+
     tx.commit().await?;
 
-    ctx.say("Permissions updated successfully for role").await?;
+    invalidate_guild_cache(&data.animus_magic_ipc, guild_id).await;
+
+    match expires_at {
+        Some(expires_at) => ctx.say(format!("Permissions updated successfully for role: {}\n\nThe newly added permissions expire <t:{}:R>", perms_vec.join(", "), expires_at.timestamp())).await?,
+        None => ctx.say(format!("Permissions updated successfully for role: {}", perms_vec.join(", "))).await?,
+    };
 
     Ok(())
 }
@@ -336,9 +402,734 @@ pub async fn perms_deleterole(
     .execute(&mut *tx)
     .await?;
 
+    sqlx::query!(
+        "UPDATE guild_members SET needs_perm_rederive = true WHERE guild_id = $1 AND $2 = ANY(roles)",
+        guild_id.to_string(),
+        role.id.to_string()
+    )
+    .execute(&mut *tx)
+    .await?;
+
     tx.commit().await?;
 
+    invalidate_guild_cache(&data.animus_magic_ipc, guild_id).await;
+
     ctx.say("Role configuration deleted successfully").await?;
 
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Returns the kittycat permissions that may be given to roles and members of a guild
+///
+/// Besides the permissions declared by modules, guilds may require custom permissions in their command
+/// configurations and overrides. These, along with the permissions roles already have, are accepted as well
+async fn guild_known_perms(pool: &sqlx::PgPool, guild_id: serenity::all::GuildId) -> Result<Vec<String>, crate::Error> {
+    let mut known_perms = SILVERPELT_CACHE
+        .registry()
+        .kittycat_perms()
+        .into_iter()
+        .map(|p| p.to_string())
+        .collect::<Vec<String>>();
+
+    let configurations = sqlx::query!(
+        "SELECT perms FROM guild_command_configurations WHERE guild_id = $1 AND perms IS NOT NULL",
+        guild_id.to_string()
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let overrides = sqlx::query!(
+        "SELECT perms FROM guild_command_overrides WHERE guild_id = $1 AND perms IS NOT NULL",
+        guild_id.to_string()
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let roles = sqlx::query!(
+        "SELECT perms FROM guild_roles WHERE guild_id = $1",
+        guild_id.to_string()
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let custom_perms = configurations
+        .into_iter()
+        .filter_map(|r| r.perms)
+        .chain(overrides.into_iter().filter_map(|r| r.perms))
+        .filter_map(|perms| crate::silverpelt::perm_expr::checks_from_value(perms).ok().flatten())
+        .flat_map(|checks| checks.checks.into_iter().flat_map(|c| c.kittycat_perms))
+        .chain(roles.into_iter().flat_map(|r| r.perms))
+        // Negated permissions are validated by their base permission
+        .map(|p| p.trim_start_matches('~').to_string())
+        .collect::<Vec<String>>();
+
+    for perm in custom_perms {
+        if !known_perms.contains(&perm) {
+            known_perms.push(perm);
+        }
+    }
+
+    Ok(known_perms)
+}
+
+/// The maximum number of days temporary permissions may last for
+const MAX_TEMPORARY_PERMS_DAYS: i64 = 365;
+
+/// Parses how long temporary permissions last (e.g. 4h) into the time they expire at, relative to `now`
+fn parse_expiry(expires_in: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>, crate::Error> {
+    let (number, unit) = parse_duration_string(&expires_in.replace(' ', ""))
+        .map_err(|_| format!("``{}`` is not a valid duration", expires_in))?;
+
+    if number == 0 {
+        return Err("The expiry of temporary permissions must be greater than zero".into());
+    }
+
+    let duration = number
+        .checked_mul(unit.to_seconds())
+        .and_then(|secs| i64::try_from(secs).ok())
+        .filter(|secs| *secs <= MAX_TEMPORARY_PERMS_DAYS * 86400)
+        .and_then(chrono::TimeDelta::try_seconds)
+        .ok_or_else(|| format!("Temporary permissions can last for at most {} days", MAX_TEMPORARY_PERMS_DAYS))?;
+
+    now.checked_add_signed(duration)
+        .ok_or_else(|| format!("``{}`` is not a valid duration", expires_in).into())
+}
+
+/// Returns the permissions in `new` that are not in `current`, these are the permissions that are recorded as temporary
+fn new_temporary_perms(current: &[String], new: &[String]) -> Result<Vec<String>, crate::Error> {
+    let added = new
+        .iter()
+        .filter(|p| !current.contains(p))
+        .cloned()
+        .collect::<Vec<String>>();
+
+    if added.is_empty() {
+        return Err("None of the given permissions are new, only newly added permissions can expire".into());
+    }
+
+    Ok(added)
+}
+
+/// Records the permissions added by an edit as temporary
+///
+/// Only permissions that are in `new` but not in `current` are recorded, these are removed again by the
+/// expire_temporary_perms task once they expire
+async fn add_temporary_perms(
+    conn: &mut sqlx::PgConnection,
+    guild_id: serenity::all::GuildId,
+    scope: TemporaryPermsScope,
+    current: &[String],
+    new: &[String],
+    expires_at: DateTime<Utc>,
+    created_by: serenity::all::UserId,
+) -> Result<(), crate::Error> {
+    let added = new_temporary_perms(current, new)?;
+
+    sqlx::query!(
+        "INSERT INTO guild_temporary_perms (guild_id, scope_type, scope_id, perms, expires_at, created_by) VALUES ($1, $2, $3, $4, $5, $6)",
+        guild_id.to_string(),
+        scope.scope_type(),
+        scope.scope_id(),
+        &added,
+        expires_at,
+        created_by.to_string()
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Returns the highest role of a member, if any
+fn highest_role<'a>(guild: &'a serenity::all::Guild, member: &Member) -> Option<&'a Role> {
+    member
+        .roles
+        .iter()
+        .filter_map(|r| guild.roles.get(r))
+        .max_by_key(|r| r.position)
+}
+
+/// Checks that the author can edit the permission overrides of a member
+///
+/// `author_above` is whether the highest role of the author is above the highest role of the target. The
+/// server owner can edit anyone, everyone else can only edit members below their highest role
+fn check_member_hierarchy(owner_id: UserId, author_id: UserId, target_id: UserId, author_above: bool) -> Result<(), crate::Error> {
+    if owner_id == author_id {
+        return Ok(());
+    }
+
+    if owner_id == target_id {
+        return Err("You cannot edit the permission overrides of the server owner".into());
+    }
+
+    if author_id == target_id {
+        return Err("You cannot edit your own permission overrides".into());
+    }
+
+    if !author_above {
+        return Err("You do not have permission to edit this member's permission overrides as they are not below you".into());
+    }
+
+    Ok(())
+}
+
+/// Applies new permission overrides to a members current overrides
+///
+/// Adding a permission replaces its negation (and vice versa)
+fn add_perm_overrides(current: &[String], perms: &[String]) -> Vec<String> {
+    let mut overrides = current.to_vec();
+
+    for perm in perms {
+        let opposite = match perm.strip_prefix('~') {
+            Some(perm) => perm.to_string(),
+            None => format!("~{}", perm),
+        };
+
+        overrides.retain(|p| p != &opposite);
+
+        if !overrides.contains(perm) {
+            overrides.push(perm.clone());
+        }
+    }
+
+    overrides
+}
+
+/// Updates the permission overrides of a member, rederiving their permissions
+///
+/// If `expires_at` is set, the newly added overrides are removed again once they expire
+async fn set_perm_overrides(
+    ctx: &crate::Context<'_>,
+    target: &Member,
+    expires_at: Option<DateTime<Utc>>,
+    update: impl FnOnce(&[String]) -> Result<Vec<String>, crate::Error>,
+) -> Result<Vec<String>, crate::Error> {
+    let data = ctx.data();
+
+    let Some(guild_id) = ctx.guild_id() else {
+        return Err("You must be in a server to run this command".into());
+    };
+
+    let Some(author) = ctx.author_member().await else {
+        return Err("You must be in a server to run this command".into());
+    };
+
+    {
+        let Some(guild) = ctx.guild() else {
+            return Err("You must be in a server to run this command".into());
+        };
+
+        // Members without roles are below everyone else
+        let author_above = highest_role(&guild, &author).map(|r| r.position) > highest_role(&guild, target).map(|r| r.position);
+
+        check_member_hierarchy(guild.owner_id, author.user.id, target.user.id, author_above)?;
+    }
+
+    let author_kittycat_perms = get_kittycat_perms(&data.pool, guild_id, author.user.id, &author.roles).await?;
+
+    let mut tx = data.pool.begin().await?;
+
+    let current = sqlx::query!(
+        "SELECT perm_overrides FROM guild_members WHERE guild_id = $1 AND user_id = $2 FOR UPDATE",
+        guild_id.to_string(),
+        target.user.id.to_string()
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let current_overrides = current.as_ref().map(|c| c.perm_overrides.clone()).unwrap_or_default();
+
+    let new_overrides = update(&current_overrides)?;
+
+    kittycat::perms::check_patch_changes(&author_kittycat_perms, &current_overrides, &new_overrides)
+    .map_err(|e| format!("You do not have permission to edit this member's permission overrides: {}", e))?;
+
+    if current.is_some() {
+        sqlx::query!(
+            "UPDATE guild_members SET perm_overrides = $1 WHERE guild_id = $2 AND user_id = $3",
+            &new_overrides,
+            guild_id.to_string(),
+            target.user.id.to_string()
+        )
+        .execute(&mut *tx)
+        .await?;
+    } else {
+        sqlx::query!(
+            "INSERT INTO guild_members (guild_id, user_id, roles, perm_overrides, resolved_perms_cache) VALUES ($1, $2, $3, $4, $5)",
+            guild_id.to_string(),
+            target.user.id.to_string(),
+            &target.roles.iter().map(|r| r.to_string()).collect::<Vec<String>>(),
+            &new_overrides,
+            &Vec::<String>::new()
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    if let Some(expires_at) = expires_at {
+        add_temporary_perms(
+            &mut tx,
+            guild_id,
+            TemporaryPermsScope::Member(target.user.id),
+            &current_overrides,
+            &new_overrides,
+            expires_at,
+            author.user.id,
+        )
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    rederive_perms(&data.pool, guild_id, target.user.id, &target.roles).await?;
+
+    invalidate_member_cache(&data.animus_magic_ipc, guild_id, target.user.id).await;
+
+    Ok(new_overrides)
+}
+
+/// Manages the permission overrides of individual members
+#[poise::command(
+    prefix_command,
+    slash_command,
+    user_cooldown = 1,
+    guild_cooldown = 1,
+    rename = "user",
+    subcommands(
+        "perms_user_list",
+        "perms_user_add",
+        "perms_user_remove"
+    )
+)]
+pub async fn perms_user(
+    _ctx: crate::Context<'_>,
+) -> Result<(), crate::Error> {
+    Ok(())
+}
+
+/// Lists the permission overrides of members
+#[poise::command(
+    prefix_command,
+    slash_command,
+    user_cooldown = 1,
+    guild_cooldown = 1,
+    rename = "list",
+)]
+pub async fn perms_user_list(
+    ctx: crate::Context<'_>,
+    #[description = "The member to list the overrides of, defaults to all members with overrides"]
+    member: Option<Member>,
+) -> Result<(), crate::Error> {
+    let data = ctx.data();
+
+    let Some(guild_id) = ctx.guild_id() else {
+        return Err("You must be in a server to run this command".into());
+    };
+
+    let recs = sqlx::query!(
+        "SELECT user_id, perm_overrides FROM guild_members WHERE guild_id = $1 AND ($2::text IS NULL OR user_id = $2) AND cardinality(perm_overrides) > 0",
+        guild_id.to_string(),
+        member.as_ref().map(|m| m.user.id.to_string())
+    )
+    .fetch_all(&data.pool)
+    .await?;
+
+    let mut embed = serenity::all::CreateEmbed::default()
+        .title("Member Permission Overrides")
+        .description("Permission overrides are applied on top of the permissions of a members roles");
+
+    if recs.is_empty() {
+        return Err("No permission overrides have been set".into());
+    }
+
+    if recs.len() > MAX_EMBED_FIELDS {
+        embed = embed.footer(serenity::all::CreateEmbedFooter::new(format!(
+            "Showing {} of {} members, use /perms user list <member> to view a specific member",
+            MAX_EMBED_FIELDS,
+            recs.len()
+        )));
+    }
+
+    for rec in recs.into_iter().take(MAX_EMBED_FIELDS) {
+        embed = embed.field(
+            rec.user_id.clone(),
+            format!("<@{}>\n{}", rec.user_id, rec.perm_overrides.iter().map(|p| format!("``{}``", p)).collect::<Vec<String>>().join(", ")),
+            false
+        );
+    }
+
+    ctx.send(CreateReply::default().embed(embed)).await?;
+
+    Ok(())
+}
+
+/// Adds permission overrides to a member, prefix a permission with ~ to negate it
+#[poise::command(
+    prefix_command,
+    slash_command,
+    user_cooldown = 1,
+    guild_cooldown = 1,
+    rename = "add",
+)]
+pub async fn perms_user_add(
+    ctx: crate::Context<'_>,
+    #[description = "The member to add the overrides to"]
+    member: Member,
+    #[description = "The permissions to add, separated by commas. Prefix a permission with ~ to negate it"]
+    #[autocomplete = "crate::silverpelt::poise_ext::kittycat_perm_list::autocomplete"]
+    perms: String,
+    #[description = "How long the overrides last, e.g. 4h. Omit to add them permanently"]
+    expires_in: Option<String>,
+) -> Result<(), crate::Error> {
+    let expires_at = expires_in.as_deref().map(|e| parse_expiry(e, Utc::now())).transpose()?;
+
+    let known_perms = guild_known_perms(&ctx.data().pool, member.guild_id).await?;
+
+    let perms = parse_kittycat_perms(&perms, &known_perms.iter().map(|p| p.as_str()).collect::<Vec<&str>>())?;
+
+    if perms.is_empty() {
+        return Err("No permissions were provided".into());
+    }
+
+    let overrides = set_perm_overrides(&ctx, &member, expires_at, |current| Ok(add_perm_overrides(current, &perms))).await?;
+
+    match expires_at {
+        Some(expires_at) => ctx.say(format!("Permission overrides of {} are now: {}\n\nThe newly added overrides expire <t:{}:R>", member.user.id.mention(), overrides.join(", "), expires_at.timestamp())).await?,
+        None => ctx.say(format!("Permission overrides of {} are now: {}", member.user.id.mention(), overrides.join(", "))).await?,
+    };
+
+    Ok(())
+}
+
+/// Removes permission overrides from a member
+#[poise::command(
+    prefix_command,
+    slash_command,
+    user_cooldown = 1,
+    guild_cooldown = 1,
+    rename = "remove",
+)]
+pub async fn perms_user_remove(
+    ctx: crate::Context<'_>,
+    #[description = "The member to remove the overrides from"]
+    member: Member,
+    #[description = "The overrides to remove, separated by commas. Omit to remove all overrides"]
+    #[autocomplete = "crate::silverpelt::poise_ext::kittycat_perm_list::autocomplete"]
+    perms: Option<String>,
+) -> Result<(), crate::Error> {
+    let perms = perms.map(|perms| {
+        perms
+            .split(',')
+            .map(|p| p.trim().to_string())
+            .filter(|p| !p.is_empty())
+            .collect::<Vec<String>>()
+    });
+
+    let overrides = set_perm_overrides(&ctx, &member, None, |current| {
+        let Some(ref perms) = perms else {
+            return Ok(Vec::new());
+        };
+
+        if let Some(perm) = perms.iter().find(|p| !current.contains(p)) {
+            return Err(format!("The member does not have the override ``{}``", perm).into());
+        }
+
+        Ok(current.iter().filter(|p| !perms.contains(p)).cloned().collect())
+    })
+    .await?;
+
+    if overrides.is_empty() {
+        ctx.say(format!("Removed all permission overrides of {}", member.user.id.mention())).await?;
+    } else {
+        ctx.say(format!("Permission overrides of {} are now: {}", member.user.id.mention(), overrides.join(", "))).await?;
+    }
+
+    Ok(())
+}
+
+/// Explains why a user can or cannot run a command
+#[poise::command(
+    prefix_command, 
+    slash_command, 
+    user_cooldown = 1,
+    guild_cooldown = 1,
+    rename = "explain",
+)]
+pub async fn perms_explain(
+    ctx: crate::Context<'_>,
+    #[description = "The user to explain permissions for"]
+    user: User,
+    #[description = "The command to check, e.g. 'limits add'"]
+    command: String,
+    #[description = "The channel to check in, defaults to the current channel"]
+    channel: Option<serenity::all::GuildChannel>,
+) -> Result<(), crate::Error> {
+    let data = ctx.data();
+
+    let Some(guild_id) = ctx.guild_id() else {
+        return Err("You must be in a server to run this command".into());
+    };
+
+    let command = command.split_whitespace().collect::<Vec<&str>>().join(" ");
+
+    let Some(base_command) = command.split(' ').next() else {
+        return Err("No command was provided".into());
+    };
+
+    let cache_http = crate::impls::cache::CacheHttpImpl {
+        cache: ctx.serenity_context().cache.clone(),
+        http: ctx.serenity_context().http.clone(),
+    };
+
+    let trace = crate::silverpelt::cmd::explain_command(
+        base_command,
+        &command,
+        guild_id,
+        user.id,
+        Some(channel.map(|c| c.id).unwrap_or(ctx.channel_id())),
+        &data.pool,
+        &cache_http,
+        None,
+    )
+    .await;
+
+    let mut description = trace.to_markdown();
+
+    if description.len() > 4096 {
+        let mut end = 4093;
+        while !description.is_char_boundary(end) {
+            end -= 1;
+        }
+        description.truncate(end);
+        description.push_str("...");
+    }
+
+    let embed = serenity::all::CreateEmbed::default()
+        .title(format!("Permission trace for {}", user.name))
+        .description(description)
+        .color(if trace.result.is_ok() { 0x00ff00 } else { 0xff0000 });
+
+    ctx.send(CreateReply::default().embed(embed)).await?;
+
+    Ok(())
+}
+
+/// Sets the permissions needed to run a command using a permission expression
+#[poise::command(
+    prefix_command, 
+    slash_command, 
+    user_cooldown = 1,
+    guild_cooldown = 1,
+    rename = "setcommand",
+)]
+pub async fn perms_setcommand(
+    ctx: crate::Context<'_>,
+    #[description = "The command to configure, e.g. 'limits add'"]
+    command: String,
+    #[description = "The expression, e.g. 'native:BAN_MEMBERS & kc:moderation.ban | native:ADMINISTRATOR'. Omit to reset"]
+    expression: Option<String>,
+) -> Result<(), crate::Error> {
+    let data = ctx.data();
+
+    let Some(guild_id) = ctx.guild_id() else {
+        return Err("You must be in a server to run this command".into());
+    };
+
+    let command = command.split_whitespace().collect::<Vec<&str>>().join(" ");
+
+    let Some(base_command) = command.split(' ').next() else {
+        return Err("No command was provided".into());
+    };
+
+    {
+        let registry = SILVERPELT_CACHE.registry();
+
+        let Some(module) = registry.module_of_command(base_command) else {
+            return Err(format!("The command ``{}`` does not exist", command).into());
+        };
+
+        if !registry.command_exists(&command) {
+            return Err(format!("The command ``{}`` does not exist", command).into());
+        }
+
+        let Some(module_data) = registry.module(module) else {
+            return Err(format!("The module ``{}`` does not exist", module).into());
+        };
+
+        if !module_data.commands_configurable || module_data.id == "root" {
+            return Err(format!("Commands in the module ``{}`` cannot be configured", module_data.id).into());
+        }
+    }
+
+    let (expr_str, perms) = match expression {
+        Some(expression) => {
+            let expr = expression.parse::<PermissionExpr>()?;
+            let checks = expr.to_checks()?;
+            (Some(expr.to_string()), Some(serde_json::to_value(checks)?))
+        }
+        None => (None, None),
+    };
+
+    let mut tx = data.pool.begin().await?;
+
+    let existing = sqlx::query!(
+        "SELECT id FROM guild_command_configurations WHERE guild_id = $1 AND command = $2 FOR UPDATE",
+        guild_id.to_string(),
+        command
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    if existing.is_some() {
+        sqlx::query!(
+            "UPDATE guild_command_configurations SET perms = $1 WHERE guild_id = $2 AND command = $3",
+            perms,
+            guild_id.to_string(),
+            command
+        )
+        .execute(&mut *tx)
+        .await?;
+    } else if perms.is_some() {
+        sqlx::query!(
+            "INSERT INTO guild_command_configurations (guild_id, command, perms) VALUES ($1, $2, $3)",
+            guild_id.to_string(),
+            command,
+            perms
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    invalidate_guild_cache(&data.animus_magic_ipc, guild_id).await;
+
+    match expr_str {
+        Some(expr_str) => ctx.say(format!("Permissions for ``{}`` set to ``{}``", command, expr_str)).await?,
+        None => ctx.say(format!("Permissions for ``{}`` reset to the default", command)).await?,
+    };
+
+    Ok(())
+}
+
+/// Shows the permissions needed to run a command as a permission expression
+#[poise::command(
+    prefix_command, 
+    slash_command, 
+    user_cooldown = 1,
+    guild_cooldown = 1,
+    rename = "viewcommand",
+)]
+pub async fn perms_viewcommand(
+    ctx: crate::Context<'_>,
+    #[description = "The command to view, e.g. 'limits add'"]
+    command: String,
+) -> Result<(), crate::Error> {
+    let data = ctx.data();
+
+    let Some(guild_id) = ctx.guild_id() else {
+        return Err("You must be in a server to run this command".into());
+    };
+
+    let command = command.split_whitespace().collect::<Vec<&str>>().join(" ");
+
+    let (cmd_data, command_config, _) = crate::silverpelt::module_config::get_command_configuration(
+        &data.pool,
+        guild_id.to_string().as_str(),
+        &command,
+    )
+    .await?;
+
+    let (perms, source) = match command_config.and_then(|c| c.perms) {
+        Some(perms) => (perms, "server-configured"),
+        None => (cmd_data.default_perms, "default"),
+    };
+
+    let expr = match PermissionExpr::from_checks(&perms) {
+        Ok(Some(expr)) => expr.to_string(),
+        Ok(None) => "No permissions needed".to_string(),
+        Err(e) => format!("Cannot be shown as an expression: {}", e),
+    };
+
+    let embed = serenity::all::CreateEmbed::default()
+        .title(format!("Permissions for {}", command))
+        .field("Source", source, true)
+        .field("Expression", format!("``{}``", expr), false)
+        .field("Checks", perms.to_string(), false);
+
+    ctx.send(CreateReply::default().embed(embed)).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn perms(perms: &[&str]) -> Vec<String> {
+        perms.iter().map(|p| p.to_string()).collect()
+    }
+
+    #[test]
+    fn test_add_perm_overrides() {
+        assert_eq!(
+            add_perm_overrides(&perms(&["a.b", "~c.d"]), &perms(&["c.d", "e.f", "a.b"])),
+            perms(&["a.b", "c.d", "e.f"])
+        );
+
+        // Negating a permission replaces it
+        assert_eq!(
+            add_perm_overrides(&perms(&["a.b", "c.d"]), &perms(&["~a.b"])),
+            perms(&["c.d", "~a.b"])
+        );
+
+        assert_eq!(
+            add_perm_overrides(&[], &perms(&["a.b", "a.b"])),
+            perms(&["a.b"])
+        );
+    }
+
+    #[test]
+    fn test_parse_expiry() {
+        let now = Utc::now();
+
+        assert_eq!(parse_expiry("4h", now).unwrap(), now + chrono::TimeDelta::hours(4));
+        assert_eq!(parse_expiry("2 days", now).unwrap(), now + chrono::TimeDelta::days(2));
+        assert_eq!(parse_expiry("52w", now).unwrap(), now + chrono::TimeDelta::weeks(52));
+
+        assert!(parse_expiry("0h", now).is_err());
+        assert!(parse_expiry("abc", now).is_err());
+
+        // Longer than a year
+        assert!(parse_expiry("53w", now).is_err());
+
+        // Overflows when converted to seconds
+        assert!(parse_expiry(&format!("{}w", u64::MAX / 2), now).is_err());
+        assert!(parse_expiry(&format!("{}s", i64::MAX as u64 + 1), now).is_err());
+    }
+
+    #[test]
+    fn test_new_temporary_perms() {
+        assert_eq!(
+            new_temporary_perms(&perms(&["a.b"]), &perms(&["a.b", "c.d", "~e.f"])).unwrap(),
+            perms(&["c.d", "~e.f"])
+        );
+
+        assert!(new_temporary_perms(&perms(&["a.b", "c.d"]), &perms(&["c.d"])).is_err());
+        assert!(new_temporary_perms(&[], &[]).is_err());
+    }
+
+    #[test]
+    fn test_check_member_hierarchy() {
+        let (owner, author, target) = (UserId::new(1), UserId::new(2), UserId::new(3));
+
+        // The owner can edit anyone, including themselves
+        assert!(check_member_hierarchy(owner, owner, target, false).is_ok());
+        assert!(check_member_hierarchy(owner, owner, owner, false).is_ok());
+
+        assert!(check_member_hierarchy(owner, author, owner, true).is_err());
+        assert!(check_member_hierarchy(owner, author, author, true).is_err());
+        assert!(check_member_hierarchy(owner, author, target, false).is_err());
+        assert!(check_member_hierarchy(owner, author, target, true).is_ok());
+    }
+}
@@ -27,8 +27,17 @@ pub struct CanonicalModule {
     /// Whether the module is enabled or disabled by default
     pub is_default_enabled: bool,
 
+    /// The IDs of the modules that must be enabled for this module to be enabled
+    pub depends_on: Vec<String>,
+
+    /// The IDs of the modules that cannot be enabled at the same time as this module
+    pub conflicts_with: Vec<String>,
+
     /// The commands in the module
     pub commands: Vec<CanonicalCommand>,
+
+    /// The configuration options of the module
+    pub config_options: Vec<crate::silverpelt::config_opt::ConfigOption>,
 }
 
 /// Canonical representation of a command (data section) for external use
@@ -128,8 +137,8 @@ impl From<&crate::silverpelt::Command> for CanonicalCommandData {
 }
 
 /// Given a module, return its canonical representation
-impl From<crate::silverpelt::Module> for CanonicalModule {
-    fn from(module: crate::silverpelt::Module) -> Self {
+impl From<&crate::silverpelt::Module> for CanonicalModule {
+    fn from(module: &crate::silverpelt::Module) -> Self {
         CanonicalModule {
             id: module.id.to_string(),
             name: module.name.to_string(),
@@ -139,11 +148,14 @@ impl From<crate::silverpelt::Module> for CanonicalModule {
             virtual_module: module.virtual_module,
             web_hidden: module.web_hidden,
             is_default_enabled: module.is_default_enabled,
+            depends_on: module.depends_on.iter().map(|m| m.to_string()).collect(),
+            conflicts_with: module.conflicts_with.iter().map(|m| m.to_string()).collect(),
             commands: module
                 .commands
-                .into_iter()
-                .map(|(cmd, perms)| CanonicalCommand::from_repr(&cmd, perms))
+                .iter()
+                .map(|(cmd, perms)| CanonicalCommand::from_repr(cmd, perms.clone()))
                 .collect(),
+            config_options: module.config_options.clone(),
         }
     }
 }
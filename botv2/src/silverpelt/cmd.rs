@@ -1,9 +1,11 @@
 use super::silverpelt_cache::SILVERPELT_CACHE;
 use crate::impls::cache::CacheHttpImpl;
 use crate::silverpelt;
-use serenity::all::{GuildId, UserId};
+use serenity::all::{ChannelId, GuildId, UserId};
 use sqlx::PgPool;
-use super::permissions::PermissionResult;
+use super::permissions::{PermissionResult, PermissionTrace, PermissionTraceLayer};
+use super::permission_source::{DatabaseSource, PermissionSource};
+use super::CooldownConfiguration;
 use log::info;
 
 pub async fn get_perm_info(
@@ -61,6 +63,66 @@ pub async fn get_perm_info(
 }
 
 
+/// Resolves the channel and category a command override can apply to
+///
+/// Threads resolve to their parent channel. This only uses the cache, if the channel is not
+/// cached, only the channel itself is returned
+pub fn resolve_channel_scope(
+    guild_id: GuildId,
+    channel_id: ChannelId,
+    cache_http: &CacheHttpImpl,
+) -> (ChannelId, Option<ChannelId>) {
+    let Some(cached_guild) = guild_id.to_guild_cached(&cache_http.cache) else {
+        return (channel_id, None);
+    };
+
+    if let Some(channel) = cached_guild.channels.get(&channel_id) {
+        return (channel_id, channel.parent_id);
+    }
+
+    if let Some(thread) = cached_guild.threads.iter().find(|t| t.id == channel_id) {
+        if let Some(parent_id) = thread.parent_id {
+            let category = cached_guild.channels.get(&parent_id).and_then(|c| c.parent_id);
+            return (parent_id, category);
+        }
+    }
+
+    (channel_id, None)
+}
+
+/// Handles commands that do not follow the typical permission system (unknown commands, root commands etc.)
+///
+/// Returns None if the command should go through the usual permission checks
+fn check_special_command(base_command: &str, user_id: UserId) -> Option<PermissionResult> {
+    let registry = SILVERPELT_CACHE.registry();
+    let Some(module) = registry
+        .module_of_command(base_command) else {
+            return Some("This command is not registered in the database, please contact support".into());
+        };
+
+    if module == "root" {
+        if !crate::config::CONFIG
+            .discord_auth
+            .root_users
+            .contains(&user_id)
+        {
+            return Some("Root commands are off-limits unless you are a bot owner or otherwise have been granted authorization!".into());
+        }
+
+        return Some(PermissionResult::OkWithMessage {
+            message: "root_cmd".to_string(),
+        });
+    }
+
+    if ["register"].contains(&base_command) {
+        return Some(PermissionResult::OkWithMessage {
+            message: "register_cmd".to_string(),
+        });
+    }
+
+    None
+}
+
 /// Check command checks whether or not a user has permission to run a command
 #[allow(clippy::too_many_arguments)]
 pub async fn check_command(
@@ -68,6 +130,7 @@ pub async fn check_command(
     command: &str,
     guild_id: GuildId,
     user_id: UserId,
+    channel_id: Option<ChannelId>,
     pool: &PgPool,
     cache_http: &CacheHttpImpl,
     // If a poise::Context is available and originates from a Application Command, we can fetch the guild+member from cache itself
@@ -75,40 +138,131 @@ pub async fn check_command(
     // API needs this for limiting the permissions of a user, allows setting custom resolved perms
     custom_resolved_kittycat_perms: Option<Vec<String>>,
 ) -> PermissionResult {
-    if !SILVERPELT_CACHE
-        .command_id_module_map
-        .contains_key(base_command) {
-            return "This command is not registered in the database, please contact support".into();
-        }
+    // Special commands skip the usual permission checks, but not cooldowns
+    let perm_res = match check_special_command(base_command, user_id) {
+        Some(res) => res,
+        None => {
+            let source = DatabaseSource {
+                pool,
+                cache_http,
+                poise_ctx,
+            };
 
-    let module = SILVERPELT_CACHE
-    .command_id_module_map
-    .get(base_command)
-    .unwrap();
+            check_command_cached(
+                base_command,
+                command,
+                guild_id,
+                user_id,
+                channel_id,
+                &source,
+                custom_resolved_kittycat_perms,
+            )
+            .await
+        }
+    };
 
-    if module == "root" {
-        if !crate::config::CONFIG
-            .discord_auth
-            .root_users
-            .contains(&user_id)
-        {
-            return "Root commands are off-limits unless you are a bot owner or otherwise have been granted authorization!".into();
+    // Cooldowns can only be tracked for commands that are actually being invoked
+    if perm_res.is_ok() {
+        if let Some(ctx) = poise_ctx {
+            if let Some(res) = check_cooldown(ctx, guild_id, pool).await {
+                return res;
+            }
         }
+    }
 
-        return PermissionResult::OkWithMessage {
-            message: "root_cmd".to_string(),
-        };
+    perm_res
+}
+
+/// Returns the guild-configured cooldowns of a command, layering the command configuration on top of the module configuration
+pub async fn get_cooldown_configuration(
+    pool: &PgPool,
+    guild_id: GuildId,
+    command: &str,
+) -> Result<CooldownConfiguration, crate::Error> {
+    if let Some(cooldown) = SILVERPELT_CACHE
+        .command_cooldown_cache
+        .get(&(guild_id, command.to_string()))
+        .await
+    {
+        return Ok(cooldown);
     }
 
-    if ["register"].contains(&base_command) {
-        return PermissionResult::OkWithMessage {
-            message: "register_cmd".to_string(),
-        };
+    let (_, command_config, module_config) = silverpelt::module_config::get_command_configuration(
+        pool,
+        guild_id.to_string().as_str(),
+        command,
+    )
+    .await?;
+
+    let mut cooldown = CooldownConfiguration::default();
+
+    if let Some(module_cooldown) = module_config.and_then(|m| m.cooldown) {
+        cooldown = cooldown.merge(&module_cooldown);
     }
 
+    if let Some(command_cooldown) = command_config.and_then(|c| c.cooldown) {
+        cooldown = cooldown.merge(&command_cooldown);
+    }
+
+    SILVERPELT_CACHE
+        .command_cooldown_cache
+        .insert((guild_id, command.to_string()), cooldown)
+        .await;
+
+    Ok(cooldown)
+}
+
+/// Checks the cooldown of a command being invoked, starting the cooldown if the command is not on cooldown
+///
+/// As the framework uses manual_cooldowns, this replaces poise's own cooldown handling. The defaults
+/// come from the cooldowns set on the command itself and are overriden by any guild-configured cooldowns
+async fn check_cooldown(
+    ctx: &crate::Context<'_>,
+    guild_id: GuildId,
+    pool: &PgPool,
+) -> Option<PermissionResult> {
+    let command = ctx.command();
+
+    let cooldown = match get_cooldown_configuration(pool, guild_id, &command.qualified_name).await {
+        Ok(cooldown) => cooldown,
+        Err(e) => return Some(e.into()),
+    };
+
+    let mut cooldown_config = command.cooldown_config.read().unwrap().clone();
+    cooldown.apply(&mut cooldown_config);
+
+    let cooldown_ctx = || poise::CooldownContext {
+        user_id: ctx.author().id,
+        guild_id: ctx.guild_id(),
+        channel_id: ctx.channel_id(),
+    };
+
+    let mut cooldown_tracker = command.cooldowns.lock().unwrap();
+
+    if let Some(remaining) = cooldown_tracker.remaining_cooldown(cooldown_ctx(), &cooldown_config) {
+        return Some(PermissionResult::Cooldown {
+            remaining: remaining.as_millis() as u64,
+        });
+    }
+
+    cooldown_tracker.start_cooldown(cooldown_ctx());
+
+    None
+}
+
+/// Checks the permissions of a command, using the command permission cache where possible
+async fn check_command_cached<S: PermissionSource + Sync>(
+    base_command: &str,
+    command: &str,
+    guild_id: GuildId,
+    user_id: UserId,
+    channel_id: Option<ChannelId>,
+    source: &S,
+    custom_resolved_kittycat_perms: Option<Vec<String>>,
+) -> PermissionResult {
     let key = SILVERPELT_CACHE
         .command_permission_cache
-        .get(&(guild_id, user_id))
+        .get(&(guild_id, user_id, channel_id))
         .await;
 
     if let Some(ref map) = key {
@@ -119,10 +273,113 @@ pub async fn check_command(
         }
     }
 
+    let perm_res = run_command_checks(
+        base_command,
+        command,
+        guild_id,
+        user_id,
+        channel_id,
+        source,
+        custom_resolved_kittycat_perms,
+        None,
+    )
+    .await;
+
+    let mut key = SILVERPELT_CACHE
+    .command_permission_cache
+    .get(&(guild_id, user_id, channel_id))
+    .await;
+            
+    if let Some(ref mut map) = key {
+        map.insert(
+            command.to_string(),
+            perm_res.clone(),
+        );
+    } else {
+        let mut map = indexmap::IndexMap::new();
+        map.insert(
+            command.to_string(),
+            perm_res.clone(),
+        );
+        SILVERPELT_CACHE
+            .command_permission_cache
+            .insert((guild_id, user_id, channel_id), map)
+            .await;
+    }
+
+    perm_res
+}
+
+/// Explain command is a variant of check_command that returns a trace explaining why a user can or cannot run a command
+///
+/// Unlike check_command, this function never uses or populates the command permission cache
+#[allow(clippy::too_many_arguments)]
+pub async fn explain_command(
+    base_command: &str,
+    command: &str,
+    guild_id: GuildId,
+    user_id: UserId,
+    channel_id: Option<ChannelId>,
+    pool: &PgPool,
+    cache_http: &CacheHttpImpl,
+    custom_resolved_kittycat_perms: Option<Vec<String>>,
+) -> PermissionTrace {
+    let mut trace = PermissionTrace::new(command);
+
+    if let Some(res) = check_special_command(base_command, user_id) {
+        trace.layers.push(PermissionTraceLayer::ShortCircuit {
+            reason: res.to_markdown(),
+        });
+        trace.result = res;
+        return trace;
+    }
+
+    // Poise context is not passed as the member being explained is not necessarily the author
+    let source = DatabaseSource {
+        pool,
+        cache_http,
+        poise_ctx: &None,
+    };
+
+    let res = run_command_checks(
+        base_command,
+        command,
+        guild_id,
+        user_id,
+        channel_id,
+        &source,
+        custom_resolved_kittycat_perms,
+        Some(&mut trace),
+    )
+    .await;
+
+    trace.result = res;
+
+    trace
+}
+
+/// Runs the permission checks for a command, optionally recording them in a trace
+///
+/// All configuration and member data is fetched from the given permission source
+#[allow(clippy::too_many_arguments)]
+async fn run_command_checks<S: PermissionSource + Sync>(
+    base_command: &str,
+    command: &str,
+    guild_id: GuildId,
+    user_id: UserId,
+    channel_id: Option<ChannelId>,
+    source: &S,
+    custom_resolved_kittycat_perms: Option<Vec<String>>,
+    mut trace: Option<&mut PermissionTrace>,
+) -> PermissionResult {
+    let Some(module) = source
+        .module_of_command(base_command) else {
+            return "This command is not registered in the database, please contact support".into();
+        };
+
     let (cmd_data, command_config, module_config) =
-        match silverpelt::module_config::get_command_configuration(
-            pool,
-            guild_id.to_string().as_str(),
+        match source.command_configuration(
+            guild_id,
             command,
         )
         .await {
@@ -138,24 +395,55 @@ pub async fn check_command(
         command: command.to_string(),
         perms: None,
         disabled: None,
+        cooldown: None,
     });
 
     let module_config = module_config.unwrap_or(silverpelt::GuildModuleConfiguration {
         id: "".to_string(),
         guild_id: guild_id.to_string(),
-        module: module.clone(),
+        module,
         disabled: None,
+        cooldown: None,
     });
 
     // Try getting guild+member from cache to speed up response times first
-    let (is_owner, member_perms, roles) = match get_perm_info(guild_id, user_id, cache_http, poise_ctx).await {
+    let (is_owner, member_perms, roles) = match source.member_perm_info(guild_id, user_id).await {
         Ok(v) => v,
         Err(e) => {
             return e;
         }
     };
 
+    let overrides = match source.command_overrides(
+        guild_id,
+        command,
+        channel_id,
+        &roles,
+    )
+    .await {
+        Ok(v) => v,
+        Err(e) => {
+            return e.into();
+        }
+    };
+
+    if let Some(trace) = trace.as_deref_mut() {
+        for command_override in &overrides {
+            trace.layers.push(PermissionTraceLayer::CommandOverride {
+                command_override: command_override.clone(),
+            });
+        }
+    }
+
+    let command_config = silverpelt::permissions::apply_command_overrides(&command_config, &overrides);
+
     if is_owner {
+        if let Some(trace) = trace {
+            trace.layers.push(PermissionTraceLayer::ShortCircuit {
+                reason: "owner".to_string(),
+            });
+        }
+
         return PermissionResult::OkWithMessage {
             message: "owner".to_string(),
         };
@@ -165,7 +453,7 @@ pub async fn check_command(
         if let Some(custom_resolved_kittycat_perms) = custom_resolved_kittycat_perms {
             custom_resolved_kittycat_perms
         } else {
-            match silverpelt::member_permission_calc::get_kittycat_perms(pool, guild_id, user_id, &roles).await {
+            match source.kittycat_perms(guild_id, user_id, &roles).await {
                 Ok(v) => v,
                 Err(e) => {
                     return e.into();
@@ -181,36 +469,350 @@ pub async fn check_command(
         member_perms
     );
 
-    let perm_res = silverpelt::permissions::can_run_command(
-        &cmd_data,
-        &command_config,
-        &module_config,
-        command,
-        member_perms,
-        &kittycat_perms,
-    );
-
-    let mut key = SILVERPELT_CACHE
-    .command_permission_cache
-    .get(&(guild_id, user_id))
-    .await;
-            
-    if let Some(ref mut map) = key {
-        map.insert(
-            command.to_string(),
-            perm_res.clone(),
-        );
+    if let Some(trace) = trace {
+        silverpelt::permissions::can_run_command_traced(
+            &cmd_data,
+            &command_config,
+            &module_config,
+            command,
+            member_perms,
+            &kittycat_perms,
+            trace,
+        )
     } else {
-        let mut map = indexmap::IndexMap::new();
-        map.insert(
-            command.to_string(),
-            perm_res.clone(),
-        );
-        SILVERPELT_CACHE
-            .command_permission_cache
-            .insert((guild_id, user_id), map)
+        silverpelt::permissions::can_run_command(
+            &cmd_data,
+            &command_config,
+            &module_config,
+            command,
+            member_perms,
+            &kittycat_perms,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::silverpelt::permission_source::fake::{MemoryMember, MemorySource};
+    use crate::silverpelt::{
+        CommandExtendedData, CommandOverrideScope, GuildCommandConfiguration, GuildCommandOverride,
+        GuildModuleConfiguration, PermissionCheck, PermissionChecks,
+    };
+    use indexmap::indexmap;
+    use serenity::all::{Permissions, RoleId};
+    use std::collections::HashMap;
+
+    const OWNER: UserId = UserId::new(1);
+    const ADMIN: UserId = UserId::new(2);
+    const MODERATOR: UserId = UserId::new(3);
+    const BANNER: UserId = UserId::new(4);
+    const KICKER: UserId = UserId::new(5);
+    const MEMBER: UserId = UserId::new(6);
+    const UNKNOWN: UserId = UserId::new(7);
+
+    const MUTED_CHANNEL: ChannelId = ChannelId::new(100);
+    const STAFF_ROLE: RoleId = RoleId::new(10);
+
+    fn check(kittycat_perms: &[&str], native_perms: &[Permissions], outer_and: bool, inner_and: bool) -> PermissionCheck {
+        PermissionCheck {
+            kittycat_perms: kittycat_perms.iter().map(|p| p.to_string()).collect(),
+            native_perms: native_perms.to_vec(),
+            outer_and,
+            inner_and,
+        }
+    }
+
+    fn command(module: &str, checks: Vec<PermissionCheck>, checks_needed: usize, is_default_enabled: bool) -> (String, crate::silverpelt::CommandExtendedDataMap) {
+        (
+            module.to_string(),
+            indexmap! {
+                "" => CommandExtendedData {
+                    default_perms: PermissionChecks { checks, checks_needed },
+                    is_default_enabled,
+                },
+            },
+        )
+    }
+
+    fn member(native_perms: Permissions, roles: Vec<RoleId>, kittycat_perms: &[&str]) -> MemoryMember {
+        MemoryMember {
+            native_perms,
+            roles,
+            kittycat_perms: kittycat_perms.iter().map(|p| p.to_string()).collect(),
+        }
+    }
+
+    fn test_source() -> MemorySource {
+        MemorySource {
+            owner_id: OWNER,
+            members: HashMap::from([
+                (ADMIN, member(Permissions::ADMINISTRATOR, vec![], &[])),
+                (MODERATOR, member(Permissions::BAN_MEMBERS, vec![STAFF_ROLE], &["moderation.kick"])),
+                (BANNER, member(Permissions::BAN_MEMBERS, vec![], &[])),
+                (KICKER, member(Permissions::empty(), vec![], &["moderation.kick"])),
+                (MEMBER, member(Permissions::empty(), vec![], &[])),
+            ]),
+            commands: HashMap::from([
+                ("open".to_string(), command("core", vec![], 0, true)),
+                ("off".to_string(), command("core", vec![], 0, true)),
+                ("hidden".to_string(), command("core", vec![], 0, false)),
+                (
+                    "ban".to_string(),
+                    command("moderation", vec![check(&["moderation.ban"], &[Permissions::BAN_MEMBERS], false, false)], 1, true),
+                ),
+                (
+                    "strict".to_string(),
+                    command("moderation", vec![check(&["moderation.kick"], &[Permissions::BAN_MEMBERS], false, true)], 1, true),
+                ),
+                (
+                    "either".to_string(),
+                    command(
+                        "moderation",
+                        vec![
+                            check(&[], &[Permissions::BAN_MEMBERS], false, false),
+                            check(&["moderation.kick"], &[], false, false),
+                        ],
+                        1,
+                        true,
+                    ),
+                ),
+                (
+                    "both".to_string(),
+                    command(
+                        "moderation",
+                        vec![
+                            check(&[], &[Permissions::BAN_MEMBERS], true, false),
+                            check(&["moderation.kick"], &[], false, false),
+                        ],
+                        2,
+                        true,
+                    ),
+                ),
+                (
+                    "twoofthree".to_string(),
+                    command(
+                        "moderation",
+                        vec![
+                            check(&[], &[Permissions::BAN_MEMBERS], false, false),
+                            check(&[], &[Permissions::KICK_MEMBERS], false, false),
+                            check(&["moderation.kick"], &[], false, false),
+                        ],
+                        2,
+                        true,
+                    ),
+                ),
+                ("limitcmd".to_string(), command("limits", vec![], 0, true)),
+                ("gitcmd".to_string(), command("gitlogs", vec![], 0, true)),
+            ]),
+            command_configurations: vec![
+                GuildCommandConfiguration {
+                    id: "".to_string(),
+                    guild_id: "1".to_string(),
+                    command: "off".to_string(),
+                    perms: None,
+                    disabled: Some(true),
+                    cooldown: None,
+                },
+                GuildCommandConfiguration {
+                    id: "".to_string(),
+                    guild_id: "1".to_string(),
+                    command: "strict".to_string(),
+                    perms: Some(PermissionChecks {
+                        checks: vec![check(&["moderation.kick"], &[], false, false)],
+                        checks_needed: 1,
+                    }),
+                    disabled: None,
+                    cooldown: None,
+                },
+            ],
+            module_configurations: vec![GuildModuleConfiguration {
+                id: "".to_string(),
+                guild_id: "1".to_string(),
+                module: "gitlogs".to_string(),
+                disabled: Some(false),
+                cooldown: None,
+            }],
+            command_overrides: vec![
+                GuildCommandOverride {
+                    id: "".to_string(),
+                    guild_id: "1".to_string(),
+                    command: "open".to_string(),
+                    scope: CommandOverrideScope::Channel(MUTED_CHANNEL),
+                    perms: None,
+                    disabled: Some(true),
+                },
+                GuildCommandOverride {
+                    id: "".to_string(),
+                    guild_id: "1".to_string(),
+                    command: "ban".to_string(),
+                    scope: CommandOverrideScope::Role(STAFF_ROLE),
+                    perms: Some(PermissionChecks {
+                        checks: vec![],
+                        checks_needed: 0,
+                    }),
+                    disabled: None,
+                },
+            ],
+            categories: HashMap::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_run_command_checks() {
+        let source = test_source();
+
+        // (description, user, command, channel, expected code)
+        let tests = [
+            ("owner shortcut", OWNER, "off", None, "ok_with_message"),
+            ("owner shortcut skips disabled modules", OWNER, "limitcmd", None, "ok_with_message"),
+            ("no checks", MEMBER, "open", None, "ok"),
+            ("unknown command", MEMBER, "missing", None, "generic_error"),
+            ("unknown member", UNKNOWN, "open", None, "discord_error"),
+            ("administrator has all native perms", ADMIN, "ban", None, "ok"),
+            ("native perm", BANNER, "ban", None, "ok"),
+            ("missing perms", MEMBER, "ban", None, "no_checks_succeeded"),
+            ("kittycat perm alone does not satisfy a different kittycat perm", KICKER, "ban", None, "no_checks_succeeded"),
+            ("role override removes checks", MODERATOR, "ban", None, "ok"),
+            ("guild command configuration replaces default perms", KICKER, "strict", None, "ok"),
+            ("guild command configuration replaces default perms", BANNER, "strict", None, "no_checks_succeeded"),
+            ("inner or, native", BANNER, "either", None, "ok"),
+            ("inner or, kittycat", KICKER, "either", None, "ok"),
+            ("inner or, neither", MEMBER, "either", None, "no_checks_succeeded"),
+            ("outer and, both", MODERATOR, "both", None, "ok"),
+            ("outer and, only first", BANNER, "both", None, "missing_any_perms"),
+            ("outer and, neither", MEMBER, "both", None, "missing_any_perms"),
+            ("checks needed, met", MODERATOR, "twoofthree", None, "ok"),
+            ("checks needed, not met", BANNER, "twoofthree", None, "missing_min_checks"),
+            ("checks needed, none", MEMBER, "twoofthree", None, "no_checks_succeeded"),
+            ("disabled command", ADMIN, "off", None, "command_disabled"),
+            ("command disabled by default", ADMIN, "hidden", None, "command_disabled"),
+            ("command disabled in channel", MEMBER, "open", Some(MUTED_CHANNEL), "command_disabled"),
+            ("command enabled in other channels", MEMBER, "open", Some(ChannelId::new(101)), "ok"),
+            ("module disabled by default", ADMIN, "limitcmd", None, "module_disabled"),
+            ("module enabled by guild", MEMBER, "gitcmd", None, "ok"),
+        ];
+
+        for (description, user_id, command, channel_id, expected) in tests {
+            let base_command = command.split(' ').next().unwrap();
+
+            let res = run_command_checks(
+                base_command,
+                command,
+                GuildId::new(1),
+                user_id,
+                channel_id,
+                &source,
+                None,
+                None,
+            )
             .await;
+
+            assert_eq!(res.code(), expected, "{} ({} running {})", description, user_id, command);
+        }
     }
 
-    perm_res
-}
\ No newline at end of file
+    #[tokio::test]
+    async fn test_inner_and_checks() {
+        let mut source = test_source();
+
+        // Restore the default perms of strict (kittycat moderation.kick AND native BAN_MEMBERS)
+        source.command_configurations.retain(|c| c.command != "strict");
+
+        // (user, expected code, expected code of the check itself)
+        let tests = [
+            (MODERATOR, "ok", "ok"),
+            (ADMIN, "no_checks_succeeded", "missing_kittycat_perms"),
+            (KICKER, "no_checks_succeeded", "missing_native_perms"),
+            (BANNER, "no_checks_succeeded", "missing_kittycat_perms"),
+        ];
+
+        for (user_id, expected, expected_check) in tests {
+            let mut trace = PermissionTrace::new("strict");
+            let res = run_command_checks("strict", "strict", GuildId::new(1), user_id, None, &source, None, Some(&mut trace)).await;
+
+            assert_eq!(res.code(), expected, "{} running strict", user_id);
+            assert_eq!(trace.checks[0].result.code(), expected_check, "{} running strict", user_id);
+        }
+
+        // Custom resolved kittycat perms (used by the API) replace the members own perms
+        let res = run_command_checks(
+            "strict",
+            "strict",
+            GuildId::new(1),
+            BANNER,
+            None,
+            &source,
+            Some(vec!["moderation.kick".to_string()]),
+            None,
+        )
+        .await;
+        assert_eq!(res.code(), "ok");
+    }
+
+    #[tokio::test]
+    async fn test_run_command_checks_traced() {
+        let source = test_source();
+
+        let mut trace = PermissionTrace::new("open");
+        let res = run_command_checks("open", "open", GuildId::new(1), MEMBER, Some(MUTED_CHANNEL), &source, None, Some(&mut trace)).await;
+
+        assert_eq!(res.code(), "command_disabled");
+        assert!(matches!(trace.layers[0], PermissionTraceLayer::CommandOverride { .. }));
+        assert!(matches!(trace.layers[1], PermissionTraceLayer::CommandConfiguration { .. }));
+    }
+
+    #[test]
+    fn test_cooldown_configuration() {
+        let parent = CooldownConfiguration { user: Some(5), guild: Some(10), channel: None };
+        let child = CooldownConfiguration { user: Some(1), guild: None, channel: None };
+
+        assert_eq!(CooldownConfiguration::layer([]), None);
+        assert_eq!(
+            CooldownConfiguration::layer([&parent, &child]),
+            Some(CooldownConfiguration { user: Some(1), guild: Some(10), channel: None })
+        );
+
+        // Set cooldowns replace the defaults of the command, 0 removes them and unset ones are kept
+        let mut config = poise::CooldownConfig {
+            guild: Some(std::time::Duration::from_secs(3)),
+            channel: Some(std::time::Duration::from_secs(4)),
+            ..Default::default()
+        };
+
+        CooldownConfiguration { user: Some(2), guild: Some(0), channel: None }.apply(&mut config);
+
+        assert_eq!(config.user, Some(std::time::Duration::from_secs(2)));
+        assert_eq!(config.guild, None);
+        assert_eq!(config.channel, Some(std::time::Duration::from_secs(4)));
+    }
+
+    #[tokio::test]
+    async fn test_command_configuration_cooldowns() {
+        let mut source = test_source();
+
+        for (command, cooldown) in [
+            ("open", CooldownConfiguration { user: Some(5), guild: Some(10), channel: None }),
+            ("open sub", CooldownConfiguration { user: Some(1), guild: None, channel: None }),
+        ] {
+            source.command_configurations.push(GuildCommandConfiguration {
+                id: "".to_string(),
+                guild_id: "1".to_string(),
+                command: command.to_string(),
+                perms: None,
+                disabled: None,
+                cooldown: Some(cooldown),
+            });
+        }
+
+        // The cooldown of the parent command is kept for the fields the subcommand does not set
+        let (_, command_config, _) = source.command_configuration(GuildId::new(1), "open sub").await.unwrap();
+        let command_config = command_config.unwrap();
+
+        assert_eq!(command_config.command, "open sub");
+        assert_eq!(
+            command_config.cooldown,
+            Some(CooldownConfiguration { user: Some(1), guild: Some(10), channel: None })
+        );
+    }
+}
@@ -4,6 +4,9 @@ use serde::ser::SerializeStruct;
 
 /// Contains variant with inner as default value
 /// Should be serde serialized as {"type": "String", "default": "value"}
+///
+/// Variants with extra data (such as the bounds of an integer) serialize it as extra fields
+/// next to type and default, e.g. {"type": "Integer", "default": 5, "min": 0, "max": 10}
 #[derive(Debug, Clone, PartialEq)]
 pub enum WebFieldType {
     /// A string
@@ -26,6 +29,32 @@ pub enum WebFieldType {
 
     /// An emoji id
     Emoji(EmojiId),
+
+    /// An integer, optionally bounded (inclusive)
+    Integer {
+        default: i64,
+        min: Option<i64>,
+        max: Option<i64>,
+    },
+
+    /// A boolean
+    Boolean(bool),
+
+    /// A duration in seconds
+    Duration(u64),
+
+    /// One of a fixed set of strings
+    Choice {
+        default: String,
+        choices: Vec<String>,
+    },
+
+    /// A list of values of the inner type. The default is always an empty list
+    List(Box<WebFieldType>),
+
+    /// A value of the inner type that may also be unset (null). The default is always null,
+    /// the default of the inner type is only used as a placeholder
+    Optional(Box<WebFieldType>),
 }
 
 // Macro to construct the serde serializer for WebFieldType
@@ -34,11 +63,12 @@ pub enum WebFieldType {
 // Macro to simplify the serialization process
 #[macro_export]
 macro_rules! serialize_web_field_type {
-    ($serializer:expr, $variant:expr, $value:expr) => {
+    ($serializer:expr, $variant:expr, $value:expr $(, $key:expr => $extra:expr)*) => {
         {
-            let mut state = $serializer.serialize_struct("WebFieldType", 2)?;
+            let mut state = $serializer.serialize_struct("WebFieldType", 2 $(+ { let _ = $key; 1 })*)?;
             state.serialize_field("type", $variant)?;
             state.serialize_field("default", $value)?;
+            $(state.serialize_field($key, $extra)?;)*
             state.end()
         }
     };
@@ -58,6 +88,20 @@ impl serde::Serialize for WebFieldType {
             WebFieldType::Message(s) => serialize_web_field_type!(serializer, "Message", s),
             WebFieldType::Guild(s) => serialize_web_field_type!(serializer, "Guild", s),
             WebFieldType::Emoji(s) => serialize_web_field_type!(serializer, "Emoji", s),
+            WebFieldType::Integer { default, min, max } => {
+                serialize_web_field_type!(serializer, "Integer", default, "min" => min, "max" => max)
+            }
+            WebFieldType::Boolean(s) => serialize_web_field_type!(serializer, "Boolean", s),
+            WebFieldType::Duration(s) => serialize_web_field_type!(serializer, "Duration", s),
+            WebFieldType::Choice { default, choices } => {
+                serialize_web_field_type!(serializer, "Choice", default, "choices" => choices)
+            }
+            WebFieldType::List(inner) => {
+                serialize_web_field_type!(serializer, "List", &Vec::<serde_json::Value>::new(), "inner" => inner)
+            }
+            WebFieldType::Optional(inner) => {
+                serialize_web_field_type!(serializer, "Optional", &serde_json::Value::Null, "inner" => inner)
+            }
         }
     }
 }
@@ -71,6 +115,43 @@ impl<'de> serde::Deserialize<'de> for WebFieldType {
         let value = serde_json::Value::deserialize(deserializer)?;
 
         let field_type = value["type"].as_str().ok_or(serde::de::Error::custom("Missing type field"))?;
+
+        // Types whose default is not a string
+        match field_type {
+            "Integer" => {
+                let default = value["default"].as_i64().ok_or(serde::de::Error::custom("Missing default field"))?;
+                let min: Option<i64> = serde_json::from_value(value["min"].clone()).map_err(serde::de::Error::custom)?;
+                let max: Option<i64> = serde_json::from_value(value["max"].clone()).map_err(serde::de::Error::custom)?;
+
+                if min.is_some_and(|min| default < min) || max.is_some_and(|max| default > max) {
+                    return Err(serde::de::Error::custom("Default must be between min and max"));
+                }
+
+                return Ok(WebFieldType::Integer { default, min, max });
+            }
+            "Boolean" => {
+                return Ok(WebFieldType::Boolean(
+                    value["default"].as_bool().ok_or(serde::de::Error::custom("Missing default field"))?,
+                ));
+            }
+            "Duration" => {
+                return Ok(WebFieldType::Duration(
+                    value["default"].as_u64().ok_or(serde::de::Error::custom("Missing default field"))?,
+                ));
+            }
+            "List" => {
+                return Ok(WebFieldType::List(Box::new(
+                    serde_json::from_value(value["inner"].clone()).map_err(serde::de::Error::custom)?,
+                )));
+            }
+            "Optional" => {
+                return Ok(WebFieldType::Optional(Box::new(
+                    serde_json::from_value(value["inner"].clone()).map_err(serde::de::Error::custom)?,
+                )));
+            }
+            _ => {}
+        }
+
         let default = value["default"].as_str().ok_or(serde::de::Error::custom("Missing default field"))?;
 
         match field_type {
@@ -81,6 +162,18 @@ impl<'de> serde::Deserialize<'de> for WebFieldType {
             "Message" => Ok(WebFieldType::Message(default.parse().map_err(serde::de::Error::custom)?)),
             "Guild" => Ok(WebFieldType::Guild(default.parse().map_err(serde::de::Error::custom)?)),
             "Emoji" => Ok(WebFieldType::Emoji(default.parse().map_err(serde::de::Error::custom)?)),
+            "Choice" => {
+                let choices: Vec<String> = serde_json::from_value(value["choices"].clone()).map_err(serde::de::Error::custom)?;
+
+                if !choices.iter().any(|c| c == default) {
+                    return Err(serde::de::Error::custom("Default must be one of the choices"));
+                }
+
+                Ok(WebFieldType::Choice {
+                    default: default.to_string(),
+                    choices,
+                })
+            }
             _ => Err(serde::de::Error::custom("Invalid type field")),
         }
     }
@@ -140,12 +233,225 @@ impl From<EmojiId> for WebFieldType {
     }
 }
 
+impl WebFieldType {
+    /// Returns the name of the type, as used in the serialized form
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            WebFieldType::String(_) => "String",
+            WebFieldType::User(_) => "User",
+            WebFieldType::Channel(_) => "Channel",
+            WebFieldType::Role(_) => "Role",
+            WebFieldType::Message(_) => "Message",
+            WebFieldType::Guild(_) => "Guild",
+            WebFieldType::Emoji(_) => "Emoji",
+            WebFieldType::Integer { .. } => "Integer",
+            WebFieldType::Boolean(_) => "Boolean",
+            WebFieldType::Duration(_) => "Duration",
+            WebFieldType::Choice { .. } => "Choice",
+            WebFieldType::List(_) => "List",
+            WebFieldType::Optional(_) => "Optional",
+        }
+    }
+
+    /// Returns the default value of the field as a JSON value
+    pub fn default_value(&self) -> serde_json::Value {
+        match self {
+            WebFieldType::String(s) => serde_json::Value::String(s.clone()),
+            WebFieldType::User(s) => serde_json::Value::String(s.to_string()),
+            WebFieldType::Channel(s) => serde_json::Value::String(s.to_string()),
+            WebFieldType::Role(s) => serde_json::Value::String(s.to_string()),
+            WebFieldType::Message(s) => serde_json::Value::String(s.to_string()),
+            WebFieldType::Guild(s) => serde_json::Value::String(s.to_string()),
+            WebFieldType::Emoji(s) => serde_json::Value::String(s.to_string()),
+            WebFieldType::Integer { default, .. } => serde_json::Value::from(*default),
+            WebFieldType::Boolean(b) => serde_json::Value::Bool(*b),
+            WebFieldType::Duration(d) => serde_json::Value::from(*d),
+            WebFieldType::Choice { default, .. } => serde_json::Value::String(default.clone()),
+            WebFieldType::List(_) => serde_json::Value::Array(vec![]),
+            WebFieldType::Optional(_) => serde_json::Value::Null,
+        }
+    }
+
+    /// Parses user input (such as from a slash command) into a JSON value of this type
+    ///
+    /// Discord IDs may also be given as mentions (e.g. `<#123>` or `<@&123>`), durations as
+    /// either seconds or a duration string (e.g. `5 minutes`) and lists as comma-separated values.
+    /// `none` unsets optional values
+    pub fn parse_value(&self, input: &str) -> Result<serde_json::Value, crate::Error> {
+        let input = input.trim();
+
+        let value = match self {
+            WebFieldType::String(_) => serde_json::Value::String(input.to_string()),
+            WebFieldType::Integer { .. } => {
+                let Ok(i) = input.parse::<i64>() else {
+                    return Err(format!("``{}`` is not a valid integer", input).into());
+                };
+
+                serde_json::Value::from(i)
+            }
+            WebFieldType::Boolean(_) => match input.to_lowercase().as_str() {
+                "true" | "yes" | "on" | "1" => serde_json::Value::Bool(true),
+                "false" | "no" | "off" | "0" => serde_json::Value::Bool(false),
+                _ => return Err(format!("``{}`` is not a valid boolean", input).into()),
+            },
+            WebFieldType::Duration(_) => {
+                if let Ok(secs) = input.parse::<u64>() {
+                    serde_json::Value::from(secs)
+                } else {
+                    let (number, unit) = crate::impls::utils::parse_duration_string(&input.replace(' ', ""))
+                        .map_err(|_| format!("``{}`` is not a valid duration", input))?;
+
+                    let secs = number
+                        .checked_mul(unit.to_seconds())
+                        .ok_or_else(|| format!("``{}`` is too long a duration", input))?;
+
+                    serde_json::Value::from(secs)
+                }
+            }
+            WebFieldType::Choice { .. } => serde_json::Value::String(input.to_string()),
+            WebFieldType::List(inner) => {
+                let mut values = Vec::new();
+
+                for item in input.split(',') {
+                    if item.trim().is_empty() {
+                        continue;
+                    }
+
+                    values.push(inner.parse_value(item)?);
+                }
+
+                serde_json::Value::Array(values)
+            }
+            WebFieldType::Optional(inner) => {
+                if input.is_empty() || input.eq_ignore_ascii_case("none") || input.eq_ignore_ascii_case("null") {
+                    serde_json::Value::Null
+                } else {
+                    inner.parse_value(input)?
+                }
+            }
+            _ => {
+                // Strip mention syntax from IDs
+                let id = input
+                    .trim_start_matches('<')
+                    .trim_end_matches('>')
+                    .trim_start_matches(['@', '#', '&', '!', ':'])
+                    .rsplit(':')
+                    .next()
+                    .unwrap_or_default();
+
+                serde_json::Value::String(id.to_string())
+            }
+        };
+
+        self.validate_value(&value)?;
+
+        Ok(value)
+    }
+
+    /// Validates that a JSON value is a valid value of this type
+    pub fn validate_value(&self, value: &serde_json::Value) -> Result<(), crate::Error> {
+        fn validate_id<T: std::str::FromStr>(value: &serde_json::Value, type_name: &str) -> Result<(), crate::Error> {
+            let Some(id) = value.as_str() else {
+                return Err(format!("Expected a {} ID as a string", type_name).into());
+            };
+
+            if id.parse::<T>().is_err() {
+                return Err(format!("``{}`` is not a valid {} ID", id, type_name).into());
+            }
+
+            Ok(())
+        }
+
+        match self {
+            WebFieldType::String(_) => {
+                if !value.is_string() {
+                    return Err("Expected a string".into());
+                }
+
+                Ok(())
+            }
+            WebFieldType::User(_) => validate_id::<UserId>(value, "user"),
+            WebFieldType::Channel(_) => validate_id::<ChannelId>(value, "channel"),
+            WebFieldType::Role(_) => validate_id::<RoleId>(value, "role"),
+            WebFieldType::Message(_) => validate_id::<MessageId>(value, "message"),
+            WebFieldType::Guild(_) => validate_id::<GuildId>(value, "guild"),
+            WebFieldType::Emoji(_) => validate_id::<EmojiId>(value, "emoji"),
+            WebFieldType::Integer { min, max, .. } => {
+                let Some(i) = value.as_i64() else {
+                    return Err("Expected an integer".into());
+                };
+
+                if let Some(min) = min {
+                    if i < *min {
+                        return Err(format!("{} is less than the minimum of {}", i, min).into());
+                    }
+                }
+
+                if let Some(max) = max {
+                    if i > *max {
+                        return Err(format!("{} is greater than the maximum of {}", i, max).into());
+                    }
+                }
+
+                Ok(())
+            }
+            WebFieldType::Boolean(_) => {
+                if !value.is_boolean() {
+                    return Err("Expected a boolean".into());
+                }
+
+                Ok(())
+            }
+            WebFieldType::Duration(_) => {
+                if !value.is_u64() {
+                    return Err("Expected a duration in seconds".into());
+                }
+
+                Ok(())
+            }
+            WebFieldType::Choice { choices, .. } => {
+                let Some(s) = value.as_str() else {
+                    return Err("Expected a string".into());
+                };
+
+                if !choices.iter().any(|c| c == s) {
+                    return Err(format!("``{}`` is not one of: {}", s, choices.join(", ")).into());
+                }
+
+                Ok(())
+            }
+            WebFieldType::List(inner) => {
+                let Some(values) = value.as_array() else {
+                    return Err("Expected a list".into());
+                };
+
+                for value in values {
+                    inner.validate_value(value)?;
+                }
+
+                Ok(())
+            }
+            WebFieldType::Optional(inner) => {
+                if value.is_null() {
+                    return Ok(());
+                }
+
+                inner.validate_value(value)
+            }
+        }
+    }
+}
+
+/// A configuration option of a module, stored per guild
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct ConfigOption {
     /// The ID of the option
     pub id: String,
     /// The name of the option
     pub name: String,
     /// The description of the option
+    pub description: String,
+    /// The type of the option, containing its default value
     pub field_type: WebFieldType,
 }
 
@@ -165,4 +471,123 @@ mod test {
         let deserialized: WebFieldType = serde_json::from_str(s).unwrap();
         assert_eq!(deserialized, WebFieldType::String("test".to_string()));
     }
+
+    #[test]
+    pub fn web_field_type_round_trip() {
+        let types = vec![
+            (WebFieldType::String("test".to_string()), r#"{"type":"String","default":"test"}"#),
+            (WebFieldType::Channel(ChannelId::new(1234)), r#"{"type":"Channel","default":"1234"}"#),
+            (
+                WebFieldType::Integer { default: 5, min: Some(0), max: None },
+                r#"{"type":"Integer","default":5,"min":0,"max":null}"#,
+            ),
+            (WebFieldType::Boolean(true), r#"{"type":"Boolean","default":true}"#),
+            (WebFieldType::Duration(3600), r#"{"type":"Duration","default":3600}"#),
+            (
+                WebFieldType::Choice { default: "ban".to_string(), choices: vec!["kick".to_string(), "ban".to_string()] },
+                r#"{"type":"Choice","default":"ban","choices":["kick","ban"]}"#,
+            ),
+            (
+                WebFieldType::List(Box::new(WebFieldType::Role(RoleId::new(1)))),
+                r#"{"type":"List","default":[],"inner":{"type":"Role","default":"1"}}"#,
+            ),
+            (
+                WebFieldType::Optional(Box::new(WebFieldType::Integer { default: 1, min: None, max: Some(10) })),
+                r#"{"type":"Optional","default":null,"inner":{"type":"Integer","default":1,"min":null,"max":10}}"#,
+            ),
+        ];
+
+        for (field_type, expected) in types {
+            let s = serde_json::to_string(&field_type).unwrap();
+            assert_eq!(s, expected);
+
+            let deserialized: WebFieldType = serde_json::from_str(&s).unwrap();
+            assert_eq!(deserialized, field_type);
+
+            // Defaults are always valid
+            assert!(field_type.validate_value(&field_type.default_value()).is_ok());
+        }
+    }
+
+    #[test]
+    pub fn deserialize_invalid_web_field_type() {
+        assert!(serde_json::from_str::<WebFieldType>(r#"{"type":"Integer","default":"5"}"#).is_err());
+        assert!(serde_json::from_str::<WebFieldType>(r#"{"type":"Boolean"}"#).is_err());
+        assert!(serde_json::from_str::<WebFieldType>(r#"{"type":"Choice","default":"a","choices":["b"]}"#).is_err());
+        assert!(serde_json::from_str::<WebFieldType>(r#"{"type":"Integer","default":0,"min":1,"max":null}"#).is_err());
+        assert!(serde_json::from_str::<WebFieldType>(r#"{"type":"Integer","default":11,"min":null,"max":10}"#).is_err());
+        assert!(serde_json::from_str::<WebFieldType>(r#"{"type":"List","default":[]}"#).is_err());
+        assert!(serde_json::from_str::<WebFieldType>(r#"{"type":"Unknown","default":"a"}"#).is_err());
+    }
+
+    #[test]
+    pub fn parse_rich_web_field_value() {
+        let integer = WebFieldType::Integer { default: 5, min: Some(1), max: Some(10) };
+        assert_eq!(integer.parse_value("7").unwrap(), serde_json::json!(7));
+        assert!(integer.parse_value("0").is_err());
+        assert!(integer.parse_value("11").is_err());
+        assert!(integer.parse_value("abc").is_err());
+
+        let boolean = WebFieldType::Boolean(false);
+        assert_eq!(boolean.parse_value("yes").unwrap(), serde_json::json!(true));
+        assert_eq!(boolean.parse_value("Off").unwrap(), serde_json::json!(false));
+        assert!(boolean.parse_value("maybe").is_err());
+
+        let duration = WebFieldType::Duration(0);
+        assert_eq!(duration.parse_value("90").unwrap(), serde_json::json!(90));
+        assert_eq!(duration.parse_value("5 minutes").unwrap(), serde_json::json!(300));
+        assert_eq!(duration.parse_value("2h").unwrap(), serde_json::json!(7200));
+        assert!(duration.parse_value("soon").is_err());
+        assert!(duration.parse_value("99999999999999999w").is_err());
+        assert!(duration.parse_value("99999999999999999999999s").is_err());
+
+        let choice = WebFieldType::Choice { default: "kick".to_string(), choices: vec!["kick".to_string(), "ban".to_string()] };
+        assert_eq!(choice.parse_value("ban").unwrap(), serde_json::json!("ban"));
+        assert!(choice.parse_value("mute").is_err());
+
+        let list = WebFieldType::List(Box::new(WebFieldType::Channel(ChannelId::new(1))));
+        assert_eq!(list.parse_value("<#1>, 2").unwrap(), serde_json::json!(["1", "2"]));
+        assert_eq!(list.parse_value("").unwrap(), serde_json::json!([]));
+        assert!(list.parse_value("1, general").is_err());
+
+        let optional = WebFieldType::Optional(Box::new(WebFieldType::Role(RoleId::new(1))));
+        assert_eq!(optional.parse_value("none").unwrap(), serde_json::Value::Null);
+        assert_eq!(optional.parse_value("<@&5>").unwrap(), serde_json::json!("5"));
+        assert!(optional.validate_value(&serde_json::Value::Null).is_ok());
+        assert!(WebFieldType::Role(RoleId::new(1)).validate_value(&serde_json::Value::Null).is_err());
+    }
+
+    #[test]
+    pub fn parse_web_field_value() {
+        let channel = WebFieldType::Channel(ChannelId::new(1));
+
+        assert_eq!(channel.parse_value("1234").unwrap(), serde_json::json!("1234"));
+        assert_eq!(channel.parse_value("<#1234>").unwrap(), serde_json::json!("1234"));
+        assert!(channel.parse_value("general").is_err());
+
+        let role = WebFieldType::Role(RoleId::new(1));
+        assert_eq!(role.parse_value("<@&1234>").unwrap(), serde_json::json!("1234"));
+
+        let user = WebFieldType::User(UserId::new(1));
+        assert_eq!(user.parse_value("<@!1234>").unwrap(), serde_json::json!("1234"));
+
+        let emoji = WebFieldType::Emoji(EmojiId::new(1));
+        assert_eq!(emoji.parse_value("<:name:1234>").unwrap(), serde_json::json!("1234"));
+
+        let string = WebFieldType::String("".to_string());
+        assert_eq!(string.parse_value("<#1234>").unwrap(), serde_json::json!("<#1234>"));
+    }
+
+    #[test]
+    pub fn validate_web_field_value() {
+        assert!(WebFieldType::String("".to_string()).validate_value(&serde_json::json!("abc")).is_ok());
+        assert!(WebFieldType::String("".to_string()).validate_value(&serde_json::json!(1)).is_err());
+        assert!(WebFieldType::Role(RoleId::new(1)).validate_value(&serde_json::json!("1234")).is_ok());
+        assert!(WebFieldType::Role(RoleId::new(1)).validate_value(&serde_json::json!(1234)).is_err());
+        assert!(WebFieldType::Role(RoleId::new(1)).validate_value(&serde_json::json!("abc")).is_err());
+
+        // Defaults are always valid
+        let channel = WebFieldType::Channel(ChannelId::new(5));
+        assert!(channel.validate_value(&channel.default_value()).is_ok());
+    }
 }
@@ -0,0 +1,87 @@
+use super::silverpelt_cache::SILVERPELT_CACHE;
+use crate::{Data, Error};
+use poise::serenity_prelude::FullEvent;
+use serenity::all::{GuildId, RoleId};
+use std::collections::HashSet;
+
+/// Core event listener of silverpelt, this runs for all guild events before any module event handlers
+///
+/// This keeps the resolved kittycat permissions and the command permission cache in sync with
+/// the roles of members so that removing a role (or its permissions) takes effect immediately
+pub async fn event_listener(
+    ctx: &serenity::client::Context,
+    event: &FullEvent,
+    guild_id: GuildId,
+) -> Result<(), Error> {
+    match event {
+        FullEvent::GuildMemberUpdate {
+            old_if_available,
+            event,
+            ..
+        } => {
+            let new_roles = event.roles.iter().copied().collect::<HashSet<RoleId>>();
+
+            // Without the old member, the roles have to be assumed to have changed
+            let roles_changed = match old_if_available {
+                Some(old) => old.roles.iter().copied().collect::<HashSet<RoleId>>() != new_roles,
+                None => true,
+            };
+
+            if !roles_changed {
+                return Ok(());
+            }
+
+            let data = ctx.data::<Data>();
+
+            sqlx::query!(
+                "UPDATE guild_members SET needs_perm_rederive = true WHERE guild_id = $1 AND user_id = $2",
+                guild_id.to_string(),
+                event.user.id.to_string()
+            )
+            .execute(&data.pool)
+            .await?;
+
+            SILVERPELT_CACHE.invalidate_member_local(guild_id, event.user.id);
+        }
+        FullEvent::GuildRoleUpdate {
+            old_data_if_available,
+            new,
+        } => {
+            // Only the native permissions of a role affect permission checks
+            if let Some(old) = old_data_if_available {
+                if old.permissions == new.permissions {
+                    return Ok(());
+                }
+            }
+
+            mark_role_members(ctx, guild_id, new.id).await?;
+        }
+        FullEvent::GuildRoleDelete {
+            removed_role_id, ..
+        } => {
+            mark_role_members(ctx, guild_id, *removed_role_id).await?;
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// Marks all members with a role as needing their permissions rederived and drops the cached command permissions of the guild
+///
+/// The whole guild is invalidated as members without a row in guild_members can also have the role
+async fn mark_role_members(ctx: &serenity::client::Context, guild_id: GuildId, role_id: RoleId) -> Result<(), Error> {
+    let data = ctx.data::<Data>();
+
+    sqlx::query!(
+        "UPDATE guild_members SET needs_perm_rederive = true WHERE guild_id = $1 AND $2 = ANY(roles)",
+        guild_id.to_string(),
+        role_id.to_string()
+    )
+    .execute(&data.pool)
+    .await?;
+
+    SILVERPELT_CACHE.invalidate_guild_permissions_local(guild_id);
+
+    Ok(())
+}
@@ -4,7 +4,7 @@ use log::warn;
 use serenity::all::{
     ActionExecution, ApplicationId, AuditLogEntryId, ChannelId, CommandId, EmojiId, EntitlementId,
     FullEvent, GenericId, GuildChannel, GuildId, MessageId, RoleId, RuleId as AutomodRuleId,
-    ScheduledEventId, StickerId, UserId, IntegrationId
+    ScheduledEventId, StickerId, UserId, IntegrationId, Interaction
 };
 use serenity::model::guild::automod::Action;
 use serenity::model::timestamp::Timestamp;
@@ -96,7 +96,18 @@ pub fn get_event_guild_id(event: &FullEvent) -> Result<GuildId, Option<Error>> {
                 return Err(None);
             }
         }
-        FullEvent::InteractionCreate { .. } => return Err(None), // We dont handle interactions create events in event handlers
+        // Only component interactions (such as buttons on messages sent by modules) are handled in event handlers,
+        // commands are handled by poise
+        FullEvent::InteractionCreate { interaction } => match interaction {
+            Interaction::Component(ci) => {
+                if let Some(guild_id) = ci.guild_id {
+                    guild_id.to_owned()
+                } else {
+                    return Err(None);
+                }
+            }
+            _ => return Err(None),
+        },
         FullEvent::InviteCreate { data, .. } => {
             if let Some(guild_id) = data.guild_id {
                 guild_id.to_owned()
@@ -185,6 +196,13 @@ pub fn get_event_guild_id(event: &FullEvent) -> Result<GuildId, Option<Error>> {
     Ok(guild_id)
 }
 
+/// The value of a field of an expanded event
+///
+/// Serializes as `{"type": "<variant>", "value": <value>}`. The type of a field value without the value
+/// itself (as used by the event schemas) is a `FieldKind`
+#[derive(Debug, Clone, serde::Serialize, strum_macros::EnumDiscriminants)]
+#[serde(tag = "type", content = "value")]
+#[strum_discriminants(name(FieldKind), derive(serde::Serialize, serde::Deserialize))]
 pub enum FieldType {
     /// A string
     Strings(Vec<String>),
@@ -414,10 +432,63 @@ impl From<i16> for FieldType {
     }
 }
 
-#[allow(dead_code)]
+impl FieldType {
+    /// Returns the values of the field as strings for matching
+    ///
+    /// IDs and primitive values are returned as-is, objects with an ID (users, emojis, stickers and
+    /// thread members) return their ID and all other values are returned as JSON
+    pub fn to_strings(&self) -> Vec<String> {
+        fn json<T: serde::Serialize>(values: &[T]) -> Vec<String> {
+            values
+                .iter()
+                .filter_map(|v| serde_json::to_string(v).ok())
+                .collect()
+        }
+
+        match self {
+            Self::Strings(s) => s.clone(),
+            Self::Bool(b) => vec![b.to_string()],
+            Self::Number(n) => vec![n.to_string()],
+            Self::UserIds(ids) => ids.iter().map(|id| id.to_string()).collect(),
+            Self::Channels(ids) => ids.iter().map(|id| id.to_string()).collect(),
+            Self::Roles(ids) => ids.iter().map(|id| id.to_string()).collect(),
+            Self::Messages(ids) => ids.iter().map(|id| id.to_string()).collect(),
+            Self::Guild(id) => vec![id.to_string()],
+            Self::Command(id) => vec![id.to_string()],
+            Self::Entitlement(id) => vec![id.to_string()],
+            Self::Application(id) => vec![id.to_string()],
+            Self::AuditLogId(id) => vec![id.to_string()],
+            Self::ScheduledEventId(id) => vec![id.to_string()],
+            Self::IntegrationId(id) => vec![id.to_string()],
+            Self::Emojis(ids) => ids.iter().map(|id| id.to_string()).collect(),
+            Self::GenericIds(ids) => ids.iter().map(|id| id.to_string()).collect(),
+            Self::AutomodActions(actions) => json(actions),
+            Self::AuditLogActions(actions) => json(actions),
+            Self::AutomodRuleIds(ids) => ids.iter().map(|id| id.to_string()).collect(),
+            Self::AutomodTrigger(trigger) => json(std::slice::from_ref(trigger)),
+            Self::TimeStamp(ts) => vec![ts.to_string()],
+            Self::AuditLogActionsChanges(changes) => json(changes),
+            Self::AuditLogOptions(options) => json(options),
+            Self::EmojiMap(emojis) => emojis.iter().map(|e| e.id.to_string()).collect(),
+            Self::StickerMap(stickers) => stickers.iter().map(|s| s.id.to_string()).collect(),
+            Self::Users(users) => users.iter().map(|u| u.id.to_string()).collect(),
+            Self::Embeds(embeds) => json(embeds),
+            Self::Attachments(attachments) => json(attachments),
+            Self::Components(components) => json(components),
+            Self::ThreadMembers(members) => members.iter().map(|m| m.user_id.to_string()).collect(),
+        }
+    }
+}
+
+/// A field of an expanded event. Fields may have multiple values (e.g. the old and new
+/// values of an update event)
+///
+/// Serializes as a list of field values
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(transparent)]
 pub struct Field {
     /// The value of the field
-    value: Vec<FieldType>,
+    pub value: Vec<FieldType>,
 }
 
 impl Field {
@@ -425,10 +496,53 @@ impl Field {
     pub fn new(value: FieldType) -> Self {
         Self { value: vec![value] }
     }
+
+    /// Returns all values of the field as strings, see `FieldType::to_strings`
+    pub fn to_strings(&self) -> Vec<String> {
+        self.value.iter().flat_map(|v| v.to_strings()).collect()
+    }
+
+    /// Returns whether any value of the field is equal to the given value
+    pub fn matches(&self, value: &str) -> bool {
+        self.value
+            .iter()
+            .any(|v| v.to_strings().iter().any(|s| s == value))
+    }
+}
+
+/// An expanded event with its name, for serialization and matching
+///
+/// Serializes as `{"event": "<event name>", "fields": {"<field>": [<field values>]}}`
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ExpandedEvent {
+    /// The name of the event, as in `event_list`
+    pub event: &'static str,
+
+    /// The fields of the event
+    pub fields: IndexMap<String, Field>,
+}
+
+impl ExpandedEvent {
+    /// Expands an event, returning None if the event is not supported
+    pub fn new(event: &FullEvent) -> Option<Self> {
+        Some(Self {
+            event: event.into(),
+            fields: expand_event(event)?,
+        })
+    }
+
+    /// Returns a field of the event
+    pub fn get(&self, field: &str) -> Option<&Field> {
+        self.fields.get(field)
+    }
+
+    /// Returns whether the field of the event has the given value. Missing fields never match
+    pub fn field_matches(&self, field: &str, value: &str) -> bool {
+        self.fields.get(field).map(|f| f.matches(value)).unwrap_or(false)
+    }
 }
 
 /// Given an event, expand it to a hashmap of fields
-#[allow(dead_code)]
 pub fn expand_event(event: &FullEvent) -> Option<IndexMap<String, Field>> {
     let mut fields = IndexMap::new();
 
@@ -612,7 +726,7 @@ pub fn expand_event(event: &FullEvent) -> Option<IndexMap<String, Field>> {
         insert_field(fields, "audit_log_id", entry.id);
         insert_optional_field(fields, "reason", entry.reason.clone());
         insert_optional_field(fields, "audit_log_target_id", entry.target_id);
-        insert_optional_field(fields, "audit_log_chages", entry.changes.clone());
+        insert_optional_field(fields, "audit_log_changes", entry.changes.clone());
         insert_optional_field(fields, "audit_log_options", entry.options.clone());
     }
 
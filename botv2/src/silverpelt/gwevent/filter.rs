@@ -0,0 +1,181 @@
+use super::core::ExpandedEvent;
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// The operator of a field filter
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum FilterOp {
+    /// Any value of the field must equal the filter value
+    Equals,
+
+    /// No value of the field may equal the filter value
+    NotEquals,
+
+    /// Any value of the field must contain the filter value
+    Contains,
+}
+
+impl FilterOp {
+    /// Returns the symbol of the operator
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            FilterOp::Equals => "==",
+            FilterOp::NotEquals => "!=",
+            FilterOp::Contains => "~=",
+        }
+    }
+}
+
+/// A filter on a field of an expanded event, e.g. `channel_id == 1234`
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct FieldFilter {
+    /// The name of the field
+    pub field: String,
+
+    /// The operator
+    pub op: FilterOp,
+
+    /// The value to compare against
+    pub value: String,
+}
+
+impl FieldFilter {
+    /// Returns whether the event matches the filter. Missing fields only match `!=`
+    pub fn matches(&self, event: &ExpandedEvent) -> bool {
+        let Some(field) = event.get(&self.field) else {
+            return self.op == FilterOp::NotEquals;
+        };
+
+        match self.op {
+            FilterOp::Equals => field.matches(&self.value),
+            FilterOp::NotEquals => !field.matches(&self.value),
+            FilterOp::Contains => field.to_strings().iter().any(|s| s.contains(&self.value)),
+        }
+    }
+}
+
+impl Display for FieldFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {} {}", self.field, self.op.symbol(), self.value)
+    }
+}
+
+impl FromStr for FieldFilter {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // The value may itself contain an operator (e.g. ``content ~= a==b``), so split on the first one found
+        let Some((index, op)) = [FilterOp::Equals, FilterOp::NotEquals, FilterOp::Contains]
+            .into_iter()
+            .filter_map(|op| s.find(op.symbol()).map(|index| (index, op)))
+            .min_by_key(|(index, _)| *index)
+        else {
+            return Err(format!("Filter ``{}`` must be of the form ``field == value``, ``field != value`` or ``field ~= value``", s).into());
+        };
+
+        let field = s[..index].trim();
+        let value = s[index + op.symbol().len()..].trim().trim_matches('"');
+
+        if field.is_empty() || field.contains(char::is_whitespace) {
+            return Err(format!("Invalid field name in filter ``{}``", s).into());
+        }
+
+        Ok(FieldFilter {
+            field: field.to_string(),
+            op,
+            value: value.to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::silverpelt::gwevent::core::{Field, FieldType};
+    use indexmap::indexmap;
+    use serenity::all::{ChannelId, UserId};
+
+    fn test_event() -> ExpandedEvent {
+        ExpandedEvent {
+            event: "CHANNEL_UPDATE",
+            fields: indexmap! {
+                "channel_id".to_string() => Field::new(ChannelId::new(1234).into()),
+                "channel_name".to_string() => Field {
+                    value: vec!["general".to_string().into(), "chat".to_string().into()],
+                },
+                "nsfw".to_string() => Field::new(false.into()),
+                "user_id".to_string() => Field::new(vec![UserId::new(1), UserId::new(2)].into()),
+            },
+        }
+    }
+
+    #[test]
+    fn test_serialize_expanded_event() {
+        let event = test_event();
+
+        assert_eq!(
+            serde_json::to_value(&event).unwrap(),
+            serde_json::json!({
+                "event": "CHANNEL_UPDATE",
+                "fields": {
+                    "channel_id": [{"type": "Channels", "value": ["1234"]}],
+                    "channel_name": [{"type": "Strings", "value": ["general"]}, {"type": "Strings", "value": ["chat"]}],
+                    "nsfw": [{"type": "Bool", "value": false}],
+                    "user_id": [{"type": "UserIds", "value": ["1", "2"]}],
+                }
+            })
+        );
+
+        assert_eq!(FieldType::Number(5).to_strings(), vec!["5"]);
+    }
+
+    #[test]
+    fn test_parse_field_filter() {
+        let filter: FieldFilter = "channel_id == 1234".parse().unwrap();
+        assert_eq!(filter.field, "channel_id");
+        assert_eq!(filter.op, FilterOp::Equals);
+        assert_eq!(filter.value, "1234");
+        assert_eq!(filter.to_string(), "channel_id == 1234");
+
+        let filter: FieldFilter = "channel_name ~= \"gen\"".parse().unwrap();
+        assert_eq!(filter.op, FilterOp::Contains);
+        assert_eq!(filter.value, "gen");
+
+        // Operators in the value are part of the value
+        let filter: FieldFilter = "channel_name ~= a==b".parse().unwrap();
+        assert_eq!(filter.field, "channel_name");
+        assert_eq!(filter.op, FilterOp::Contains);
+        assert_eq!(filter.value, "a==b");
+
+        let filter: FieldFilter = "channel_name != a~=b".parse().unwrap();
+        assert_eq!(filter.op, FilterOp::NotEquals);
+        assert_eq!(filter.value, "a~=b");
+
+        assert!("channel_id 1234".parse::<FieldFilter>().is_err());
+        assert!(" == 1234".parse::<FieldFilter>().is_err());
+        assert!("channel id == 1234".parse::<FieldFilter>().is_err());
+    }
+
+    #[test]
+    fn test_field_filter_matches() {
+        let event = test_event();
+
+        let tests = [
+            ("channel_id == 1234", true),
+            ("channel_id == 4321", false),
+            ("channel_id != 4321", true),
+            ("channel_name == chat", true),
+            ("channel_name ~= gen", true),
+            ("channel_name ~= foo", false),
+            ("nsfw == false", true),
+            ("user_id == 2", true),
+            ("missing == 1", false),
+            ("missing != 1", true),
+        ];
+
+        for (filter, expected) in tests {
+            let filter: FieldFilter = filter.parse().unwrap();
+            assert_eq!(filter.matches(&event), expected, "{}", filter);
+        }
+    }
+}
@@ -0,0 +1,901 @@
+use super::core::{event_list, FieldKind};
+use serde::{Deserialize, Serialize};
+
+/// The schema of a field of an expanded event
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FieldSchema {
+    /// The name of the field
+    pub name: String,
+
+    /// The type of the values of the field, this is the ``type`` of the serialized field values
+    #[serde(rename = "type")]
+    pub kind: FieldKind,
+
+    /// Whether the field may be missing from the event or have the string ``None`` as its value
+    pub optional: bool,
+}
+
+/// The schema of an expanded event, see `ExpandedEvent`
+///
+/// Serializes as `{"event": "<event name>", "fields": [{"name": "<field>", "type": "<type>", "optional": <bool>}]}`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct EventSchema {
+    /// The name of the event, as in `event_list`
+    pub event: String,
+
+    /// The fields of the event, in the order they are expanded in
+    pub fields: Vec<FieldSchema>,
+}
+
+impl EventSchema {
+    /// Returns a field of the event
+    pub fn field(&self, name: &str) -> Option<&FieldSchema> {
+        self.fields.iter().find(|f| f.name == name)
+    }
+}
+
+/// A list of (name, type, optional) tuples, one per field. These must be kept in sync with the `expand_*` functions of `expand_event`
+type Fields = &'static [(&'static str, FieldKind, bool)];
+
+const ACTION_EXECUTION: Fields = &[
+    ("guild_id", FieldKind::Guild, false),
+    ("action", FieldKind::AutomodActions, false),
+    ("rule_id", FieldKind::AutomodRuleIds, false),
+    ("trigger_type", FieldKind::Strings, false),
+    ("content", FieldKind::Strings, false),
+    ("user_id", FieldKind::UserIds, false),
+    ("channel_id", FieldKind::Channels, true),
+    ("message_id", FieldKind::Messages, true),
+    ("alert_system_message_id", FieldKind::Messages, true),
+    ("matched_keyword", FieldKind::Strings, true),
+    ("matched_content", FieldKind::Strings, true),
+];
+
+const RULE: Fields = &[
+    ("rule_id", FieldKind::AutomodRuleIds, false),
+    ("guild_id", FieldKind::Guild, false),
+    ("rule_name", FieldKind::Strings, false),
+    ("creator_id", FieldKind::UserIds, false),
+    ("event_type", FieldKind::Strings, false),
+    ("trigger", FieldKind::AutomodTrigger, false),
+    ("actions", FieldKind::AutomodActions, false),
+    ("rule_enabled", FieldKind::Bool, false),
+    ("exempt_roles", FieldKind::Roles, false),
+    ("exempt_channels", FieldKind::Channels, false),
+];
+
+const CHANNEL: Fields = &[
+    ("guild_id", FieldKind::Guild, false),
+    ("channel_name", FieldKind::Strings, false),
+    ("nsfw", FieldKind::Bool, false),
+    ("channel_type", FieldKind::Strings, false),
+    ("channel_topic", FieldKind::Strings, true),
+    ("rate_limit_per_user", FieldKind::Number, true),
+    ("parent_id", FieldKind::Channels, true),
+    ("user_limit", FieldKind::Number, true),
+    ("channel_id", FieldKind::Channels, false),
+    ("thread_id", FieldKind::Channels, true),
+];
+
+const CHANNEL_PINS: Fields = &[
+    ("channel_id", FieldKind::Channels, false),
+    ("last_pin_timestamp", FieldKind::TimeStamp, true),
+];
+
+const COMMAND_PERMISSIONS: Fields = &[
+    ("command_id", FieldKind::Command, false),
+    ("application_id", FieldKind::Application, false),
+];
+
+const ENTITLEMENT: Fields = &[
+    ("entitlement_id", FieldKind::Entitlement, false),
+    ("application_id", FieldKind::Application, false),
+    ("entitlement_type", FieldKind::Strings, false),
+    ("entitlement_deleted", FieldKind::Bool, false),
+    ("guild_id", FieldKind::Guild, true),
+    ("user_id", FieldKind::UserIds, true),
+    ("entitlement_starts_at", FieldKind::TimeStamp, true),
+    ("entitlement_ends_at", FieldKind::TimeStamp, true),
+];
+
+const AUDIT_LOG_ENTRY: Fields = &[
+    ("guild_id", FieldKind::Guild, false),
+    ("action", FieldKind::AuditLogActions, false),
+    ("user_id", FieldKind::UserIds, false),
+    ("audit_log_id", FieldKind::AuditLogId, false),
+    ("reason", FieldKind::Strings, true),
+    ("audit_log_target_id", FieldKind::GenericIds, true),
+    ("audit_log_changes", FieldKind::AuditLogActionsChanges, true),
+    ("audit_log_options", FieldKind::AuditLogOptions, true),
+];
+
+const USER: Fields = &[
+    ("guild_id", FieldKind::Guild, false),
+    ("user_id", FieldKind::UserIds, false),
+    ("username", FieldKind::Strings, false),
+    ("is_bot", FieldKind::Bool, false),
+    ("global_username", FieldKind::Strings, true),
+];
+
+const MEMBER: Fields = &[
+    ("roles", FieldKind::Roles, false),
+    ("nick", FieldKind::Strings, true),
+    ("joined_timestamp", FieldKind::TimeStamp, true),
+    ("premium_since", FieldKind::TimeStamp, true),
+];
+
+const ROLE: Fields = &[
+    ("role_id", FieldKind::Roles, false),
+    ("guild_id", FieldKind::Guild, false),
+    ("is_hoisted", FieldKind::Bool, false),
+    ("is_managed", FieldKind::Bool, false),
+    ("is_mentionable", FieldKind::Bool, false),
+    ("role_name", FieldKind::Strings, false),
+];
+
+const SCHEDULED_EVENT: Fields = &[
+    ("event_id", FieldKind::ScheduledEventId, false),
+    ("guild_id", FieldKind::Guild, false),
+    ("event_name", FieldKind::Strings, false),
+    ("event_start_time", FieldKind::TimeStamp, false),
+    ("event_privacy_level", FieldKind::Strings, false),
+    ("event_type", FieldKind::Strings, false),
+    ("event_channel_id", FieldKind::Channels, true),
+    ("creator_id", FieldKind::UserIds, true),
+    ("event_description", FieldKind::Strings, true),
+    ("event_end_time", FieldKind::TimeStamp, true),
+];
+
+const SCHEDULED_EVENT_USER: Fields = &[
+    ("guild_id", FieldKind::Guild, false),
+    ("event_id", FieldKind::ScheduledEventId, false),
+    ("user_id", FieldKind::UserIds, false),
+];
+
+const GUILD_ID: Fields = &[("guild_id", FieldKind::Guild, false)];
+
+const GUILD_CREATE: Fields = &[
+    ("guild_id", FieldKind::Guild, false),
+    ("is_new", FieldKind::Bool, true),
+];
+
+const EMOJI_MAP: Fields = &[
+    ("guild_id", FieldKind::Guild, false),
+    ("emojis", FieldKind::EmojiMap, false),
+];
+
+const STICKER_MAP: Fields = &[
+    ("guild_id", FieldKind::Guild, false),
+    ("stickers", FieldKind::StickerMap, false),
+];
+
+const GUILD: Fields = &[
+    ("guild_id", FieldKind::Guild, false),
+    ("guild_name", FieldKind::Strings, false),
+    ("guild_owner_id", FieldKind::UserIds, false),
+    ("guild_description", FieldKind::Strings, true),
+];
+
+const ROLE_DELETE: Fields = &[
+    ("guild_id", FieldKind::Guild, false),
+    ("role_id", FieldKind::Roles, false),
+];
+
+const INTEGRATION: Fields = &[
+    ("integration_id", FieldKind::IntegrationId, false),
+    ("integration_name", FieldKind::Strings, false),
+    ("integration_type", FieldKind::Strings, false),
+    ("integration_enabled", FieldKind::Bool, false),
+    ("integration_account_id", FieldKind::Strings, false),
+    ("integration_account_name", FieldKind::Strings, false),
+    ("integration_syncing_status", FieldKind::Bool, true),
+    ("integration_role_id", FieldKind::Roles, true),
+    ("integration_guild_id", FieldKind::Guild, true),
+    ("integration_user_id", FieldKind::UserIds, true),
+];
+
+const INTEGRATION_DELETE: Fields = &[
+    ("guild_id", FieldKind::Guild, false),
+    ("integration_id", FieldKind::IntegrationId, false),
+    ("integration_application_id", FieldKind::Application, true),
+];
+
+const INVITE_CREATE: Fields = &[
+    ("invite_code", FieldKind::Strings, false),
+    ("invite_channel_id", FieldKind::Channels, false),
+    ("invite_created_at", FieldKind::TimeStamp, false),
+    ("invite_max_age", FieldKind::Number, false),
+    ("invite_max_uses", FieldKind::Number, false),
+    ("invite_guild_id", FieldKind::Guild, true),
+];
+
+const INVITE_DELETE: Fields = &[
+    ("invite_code", FieldKind::Strings, false),
+    ("invite_channel_id", FieldKind::Channels, false),
+    ("invite_guild_id", FieldKind::Guild, true),
+];
+
+const MESSAGE: Fields = &[
+    ("message_id", FieldKind::Messages, false),
+    ("message_channel_id", FieldKind::Channels, false),
+    ("message_author", FieldKind::Users, false),
+    ("message_content", FieldKind::Strings, false),
+    ("message_created_at", FieldKind::TimeStamp, false),
+    ("message_embeds", FieldKind::Embeds, false),
+    ("message_attachments", FieldKind::Attachments, false),
+    ("message_components", FieldKind::Components, false),
+    ("message_kind", FieldKind::Strings, false),
+    ("message_updated_at", FieldKind::TimeStamp, true),
+    ("message_guild_id", FieldKind::Guild, true),
+];
+
+const MESSAGE_DELETE: Fields = &[
+    ("guild_id", FieldKind::Guild, true),
+    ("message_id", FieldKind::Messages, false),
+    ("channel_id", FieldKind::Channels, false),
+];
+
+const MESSAGE_DELETE_BULK: Fields = &[
+    ("guild_id", FieldKind::Guild, true),
+    ("channel_id", FieldKind::Channels, false),
+    ("message_ids", FieldKind::Messages, false),
+];
+
+const THREAD_DELETE: Fields = &[
+    ("guild_id", FieldKind::Guild, false),
+    ("thread_id", FieldKind::Channels, false),
+    ("channel_id", FieldKind::Channels, false),
+    ("channel_type", FieldKind::Strings, false),
+];
+
+const THREAD_MEMBER: Fields = &[
+    ("guild_id", FieldKind::Guild, true),
+    ("channel_id", FieldKind::Channels, false),
+    ("user_id", FieldKind::UserIds, false),
+];
+
+const THREAD_MEMBERS_UPDATE: Fields = &[
+    ("guild_id", FieldKind::Guild, false),
+    ("channel_id", FieldKind::Channels, false),
+    ("thread_member_count", FieldKind::Number, false),
+    ("removed_member_ids", FieldKind::UserIds, false),
+];
+
+/// The field lists making up the schema of every event expanded by `expand_event`. The boolean marks
+/// field lists that may be missing entirely (e.g. the old state of an update event when it was not cached)
+const EVENT_SCHEMAS: &[(&str, &[(Fields, bool)])] = &[
+    ("AUTO_MOD_ACTION_EXECUTION", &[(ACTION_EXECUTION, false)]),
+    ("AUTO_MOD_RULE_CREATE", &[(RULE, false)]),
+    ("AUTO_MOD_RULE_DELETE", &[(RULE, false)]),
+    ("AUTO_MOD_RULE_UPDATE", &[(RULE, false)]),
+    ("CATEGORY_CREATE", &[(CHANNEL, false)]),
+    ("CATEGORY_DELETE", &[(CHANNEL, false)]),
+    ("CHANNEL_CREATE", &[(CHANNEL, false)]),
+    ("CHANNEL_DELETE", &[(CHANNEL, false)]),
+    ("CHANNEL_PINS_UPDATE", &[(CHANNEL_PINS, false)]),
+    ("CHANNEL_UPDATE", &[(CHANNEL, false)]),
+    (
+        "COMMAND_PERMISSIONS_UPDATE",
+        &[(COMMAND_PERMISSIONS, false)],
+    ),
+    ("ENTITLEMENT_CREATE", &[(ENTITLEMENT, false)]),
+    ("ENTITLEMENT_DELETE", &[(ENTITLEMENT, false)]),
+    ("ENTITLEMENT_UPDATE", &[(ENTITLEMENT, false)]),
+    ("GUILD_AUDIT_LOG_ENTRY_CREATE", &[(AUDIT_LOG_ENTRY, false)]),
+    ("GUILD_BAN_ADDITION", &[(USER, false)]),
+    ("GUILD_BAN_REMOVAL", &[(USER, false)]),
+    ("GUILD_CREATE", &[(GUILD_CREATE, false)]),
+    ("GUILD_DELETE", &[(GUILD_ID, false)]),
+    ("GUILD_EMOJIS_UPDATE", &[(EMOJI_MAP, false)]),
+    ("GUILD_INTEGRATIONS_UPDATE", &[(GUILD_ID, false)]),
+    ("GUILD_MEMBER_ADDITION", &[(USER, false), (MEMBER, false)]),
+    ("GUILD_MEMBER_REMOVAL", &[(USER, false)]),
+    ("GUILD_MEMBER_UPDATE", &[(USER, true), (MEMBER, true)]),
+    ("GUILD_ROLE_CREATE", &[(ROLE, false)]),
+    ("GUILD_ROLE_DELETE", &[(ROLE_DELETE, false), (ROLE, true)]),
+    ("GUILD_ROLE_UPDATE", &[(ROLE, false)]),
+    ("GUILD_SCHEDULED_EVENT_CREATE", &[(SCHEDULED_EVENT, false)]),
+    ("GUILD_SCHEDULED_EVENT_DELETE", &[(SCHEDULED_EVENT, false)]),
+    ("GUILD_SCHEDULED_EVENT_UPDATE", &[(SCHEDULED_EVENT, false)]),
+    (
+        "GUILD_SCHEDULED_EVENT_USER_ADD",
+        &[(SCHEDULED_EVENT_USER, false)],
+    ),
+    (
+        "GUILD_SCHEDULED_EVENT_USER_REMOVE",
+        &[(SCHEDULED_EVENT_USER, false)],
+    ),
+    ("GUILD_STICKERS_UPDATE", &[(STICKER_MAP, false)]),
+    ("GUILD_UPDATE", &[(GUILD, false)]),
+    ("INTEGRATION_CREATE", &[(INTEGRATION, false)]),
+    ("INTEGRATION_DELETE", &[(INTEGRATION_DELETE, false)]),
+    ("INTEGRATION_UPDATE", &[(INTEGRATION, false)]),
+    ("INVITE_CREATE", &[(INVITE_CREATE, false)]),
+    ("INVITE_DELETE", &[(INVITE_DELETE, false)]),
+    ("MESSAGE", &[(MESSAGE, false)]),
+    ("MESSAGE_DELETE", &[(MESSAGE_DELETE, false)]),
+    ("MESSAGE_DELETE_BULK", &[(MESSAGE_DELETE_BULK, false)]),
+    ("MESSAGE_UPDATE", &[(MESSAGE, true)]),
+    ("THREAD_CREATE", &[(CHANNEL, false)]),
+    ("THREAD_DELETE", &[(THREAD_DELETE, false)]),
+    (
+        "THREAD_MEMBER_UPDATE",
+        &[(USER, true), (MEMBER, true), (THREAD_MEMBER, false)],
+    ),
+    ("THREAD_MEMBERS_UPDATE", &[(THREAD_MEMBERS_UPDATE, false)]),
+    ("THREAD_UPDATE", &[(CHANNEL, false)]),
+];
+
+/// Returns the schema of an event, or None if the event is not expanded by `expand_event`
+pub fn event_schema(event: &str) -> Option<EventSchema> {
+    let (_, parts) = EVENT_SCHEMAS.iter().find(|(name, _)| *name == event)?;
+
+    let mut fields: Vec<FieldSchema> = Vec::new();
+
+    for (part, part_optional) in parts.iter() {
+        for (name, kind, optional) in part.iter() {
+            let optional = *optional || *part_optional;
+
+            // Fields shared between field lists are only optional if they are optional in all of them
+            match fields.iter_mut().find(|f| f.name == *name) {
+                Some(field) => field.optional &= optional,
+                None => fields.push(FieldSchema {
+                    name: name.to_string(),
+                    kind: *kind,
+                    optional,
+                }),
+            }
+        }
+    }
+
+    Some(EventSchema {
+        event: event.to_string(),
+        fields,
+    })
+}
+
+/// Returns the schemas of all events expanded by `expand_event`, in the order of `event_list`
+pub fn event_schemas() -> Vec<EventSchema> {
+    event_list()
+        .iter()
+        .filter_map(|event| event_schema(event))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::silverpelt::gwevent::core::ExpandedEvent;
+    use serde_json::{json, Value};
+    use serenity::all::{FullEvent, Guild, GuildId, IntegrationId, RoleId};
+    use std::collections::HashMap;
+
+    const TIMESTAMP: &str = "2024-01-01T00:00:00+00:00";
+
+    /// Deserializes a model from a Discord payload
+    fn model<T: serde::de::DeserializeOwned>(value: Value) -> T {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn user() -> Value {
+        json!({
+            "id": "10",
+            "username": "user",
+            "discriminator": "0",
+            "global_name": "User",
+            "avatar": null,
+        })
+    }
+
+    fn member() -> Value {
+        json!({
+            "guild_id": "1",
+            "user": user(),
+            "roles": ["5"],
+            "nick": "nick",
+            "joined_at": TIMESTAMP,
+            "premium_since": null,
+            "deaf": false,
+            "mute": false,
+            "pending": false,
+            "flags": 0,
+        })
+    }
+
+    fn channel(kind: u8) -> Value {
+        json!({
+            "id": "2",
+            "guild_id": "1",
+            "type": kind,
+            "name": "general",
+            "position": 0,
+            "permission_overwrites": [],
+            "nsfw": false,
+            "topic": "topic",
+            "rate_limit_per_user": 0,
+            "parent_id": null,
+        })
+    }
+
+    fn thread() -> Value {
+        json!({
+            "id": "3",
+            "guild_id": "1",
+            "type": 11,
+            "name": "thread",
+            "parent_id": "2",
+            "owner_id": "10",
+            "nsfw": false,
+            "rate_limit_per_user": 0,
+            "message_count": 1,
+            "member_count": 1,
+            "thread_metadata": {
+                "archived": false,
+                "auto_archive_duration": 60,
+                "archive_timestamp": TIMESTAMP,
+                "locked": false,
+            },
+        })
+    }
+
+    fn role() -> Value {
+        json!({
+            "id": "5",
+            "guild_id": "1",
+            "name": "mod",
+            "color": 0,
+            "hoist": false,
+            "icon": null,
+            "unicode_emoji": null,
+            "position": 1,
+            "permissions": "0",
+            "managed": false,
+            "mentionable": true,
+            "flags": 0,
+        })
+    }
+
+    fn message() -> Value {
+        json!({
+            "id": "50",
+            "channel_id": "2",
+            "guild_id": "1",
+            "author": user(),
+            "content": "hello",
+            "timestamp": TIMESTAMP,
+            "edited_timestamp": null,
+            "tts": false,
+            "mention_everyone": false,
+            "mentions": [],
+            "mention_roles": [],
+            "attachments": [],
+            "embeds": [],
+            "components": [],
+            "pinned": false,
+            "type": 0,
+        })
+    }
+
+    fn rule() -> Value {
+        json!({
+            "id": "60",
+            "guild_id": "1",
+            "name": "rule",
+            "creator_id": "10",
+            "event_type": 1,
+            "trigger_type": 1,
+            "trigger_metadata": {"keyword_filter": ["bad"], "regex_patterns": [], "allow_list": []},
+            "actions": [{"type": 1, "metadata": {}}],
+            "enabled": true,
+            "exempt_roles": ["5"],
+            "exempt_channels": ["2"],
+        })
+    }
+
+    fn scheduled_event() -> Value {
+        json!({
+            "id": "70",
+            "guild_id": "1",
+            "channel_id": null,
+            "creator_id": "10",
+            "name": "event",
+            "description": "description",
+            "scheduled_start_time": TIMESTAMP,
+            "scheduled_end_time": TIMESTAMP,
+            "privacy_level": 2,
+            "status": 1,
+            "entity_type": 3,
+            "entity_id": null,
+            "entity_metadata": {"location": "here"},
+            "image": null,
+        })
+    }
+
+    fn integration() -> Value {
+        json!({
+            "id": "80",
+            "name": "integration",
+            "type": "twitch",
+            "enabled": true,
+            "syncing": false,
+            "role_id": "5",
+            "guild_id": "1",
+            "user": user(),
+            "account": {"id": "81", "name": "account"},
+        })
+    }
+
+    fn entitlement(deleted: bool) -> Value {
+        json!({
+            "id": "30",
+            "sku_id": "31",
+            "application_id": "21",
+            "user_id": "10",
+            "guild_id": "1",
+            "type": 8,
+            "deleted": deleted,
+            "starts_at": null,
+            "ends_at": null,
+        })
+    }
+
+    fn partial_guild() -> Value {
+        json!({
+            "id": "1",
+            "name": "guild",
+            "icon": null,
+            "splash": null,
+            "discovery_splash": null,
+            "owner_id": "10",
+            "afk_channel_id": null,
+            "afk_timeout": 300,
+            "verification_level": 0,
+            "default_message_notifications": 0,
+            "explicit_content_filter": 0,
+            "roles": [role()],
+            "emojis": [],
+            "features": [],
+            "mfa_level": 0,
+            "application_id": null,
+            "system_channel_id": null,
+            "system_channel_flags": 0,
+            "rules_channel_id": null,
+            "vanity_url_code": null,
+            "description": "description",
+            "banner": null,
+            "premium_tier": 0,
+            "preferred_locale": "en-US",
+            "public_updates_channel_id": null,
+            "nsfw_level": 0,
+            "premium_progress_bar_enabled": false,
+            "stickers": [],
+        })
+    }
+
+    fn guild() -> Guild {
+        let mut guild = Guild::default();
+        guild.id = GuildId::new(1);
+        guild.owner_id = model(json!("10"));
+        guild
+    }
+
+    /// One event per event of `EVENT_SCHEMAS`, with as many optional fields set as possible
+    fn events() -> Vec<FullEvent> {
+        vec![
+            FullEvent::AutoModActionExecution {
+                execution: model(json!({
+                    "guild_id": "1",
+                    "action": {"type": 1, "metadata": {}},
+                    "rule_id": "60",
+                    "rule_trigger_type": 1,
+                    "user_id": "10",
+                    "channel_id": "2",
+                    "message_id": "50",
+                    "alert_system_message_id": null,
+                    "content": "bad",
+                    "matched_keyword": "bad",
+                    "matched_content": "bad",
+                })),
+            },
+            FullEvent::AutoModRuleCreate {
+                rule: model(rule()),
+            },
+            FullEvent::AutoModRuleDelete {
+                rule: model(rule()),
+            },
+            FullEvent::AutoModRuleUpdate {
+                rule: model(rule()),
+            },
+            FullEvent::CategoryCreate {
+                category: model(channel(4)),
+            },
+            FullEvent::CategoryDelete {
+                category: model(channel(4)),
+            },
+            FullEvent::ChannelCreate {
+                channel: model(channel(0)),
+            },
+            FullEvent::ChannelDelete {
+                channel: model(channel(2)),
+                messages: None,
+            },
+            FullEvent::ChannelPinsUpdate {
+                pin: model(json!({
+                    "guild_id": "1",
+                    "channel_id": "2",
+                    "last_pin_timestamp": TIMESTAMP,
+                })),
+            },
+            FullEvent::ChannelUpdate {
+                old: Some(model(channel(0))),
+                new: model(channel(5)),
+            },
+            FullEvent::CommandPermissionsUpdate {
+                permission: model(json!({
+                    "id": "20",
+                    "application_id": "21",
+                    "guild_id": "1",
+                    "permissions": [],
+                })),
+            },
+            FullEvent::EntitlementCreate {
+                entitlement: model(entitlement(false)),
+            },
+            FullEvent::EntitlementDelete {
+                entitlement: model(entitlement(true)),
+            },
+            FullEvent::EntitlementUpdate {
+                entitlement: model(entitlement(false)),
+            },
+            FullEvent::GuildAuditLogEntryCreate {
+                entry: model(json!({
+                    "id": "40",
+                    "user_id": "10",
+                    "target_id": "2",
+                    "action_type": 11,
+                    "changes": [{"key": "name", "old_value": "general", "new_value": "chat"}],
+                    "reason": "reason",
+                })),
+                guild_id: GuildId::new(1),
+            },
+            FullEvent::GuildBanAddition {
+                guild_id: GuildId::new(1),
+                banned_user: model(user()),
+            },
+            FullEvent::GuildBanRemoval {
+                guild_id: GuildId::new(1),
+                unbanned_user: model(user()),
+            },
+            FullEvent::GuildCreate {
+                guild: guild(),
+                is_new: Some(true),
+            },
+            FullEvent::GuildDelete {
+                incomplete: model(json!({"id": "1", "unavailable": false})),
+                full: None,
+            },
+            FullEvent::GuildEmojisUpdate {
+                guild_id: GuildId::new(1),
+                current_state: HashMap::new(),
+            },
+            FullEvent::GuildIntegrationsUpdate {
+                guild_id: GuildId::new(1),
+            },
+            FullEvent::GuildMemberAddition {
+                new_member: model(member()),
+            },
+            FullEvent::GuildMemberRemoval {
+                guild_id: GuildId::new(1),
+                user: model(user()),
+                member_data_if_available: None,
+            },
+            FullEvent::GuildMemberUpdate {
+                old_if_available: None,
+                new: Some(model(member())),
+                event: model(member()),
+            },
+            FullEvent::GuildRoleCreate { new: model(role()) },
+            FullEvent::GuildRoleDelete {
+                guild_id: GuildId::new(1),
+                removed_role_id: RoleId::new(5),
+                removed_role_data_if_available: Some(model(role())),
+            },
+            FullEvent::GuildRoleUpdate {
+                old_data_if_available: Some(model(role())),
+                new: model(role()),
+            },
+            FullEvent::GuildScheduledEventCreate {
+                event: model(scheduled_event()),
+            },
+            FullEvent::GuildScheduledEventDelete {
+                event: model(scheduled_event()),
+            },
+            FullEvent::GuildScheduledEventUpdate {
+                event: model(scheduled_event()),
+            },
+            FullEvent::GuildScheduledEventUserAdd {
+                subscribed: model(json!({
+                    "guild_scheduled_event_id": "70",
+                    "user_id": "10",
+                    "guild_id": "1",
+                })),
+            },
+            FullEvent::GuildScheduledEventUserRemove {
+                unsubscribed: model(json!({
+                    "guild_scheduled_event_id": "70",
+                    "user_id": "10",
+                    "guild_id": "1",
+                })),
+            },
+            FullEvent::GuildStickersUpdate {
+                guild_id: GuildId::new(1),
+                current_state: HashMap::new(),
+            },
+            FullEvent::GuildUpdate {
+                old_data_if_available: Some(guild()),
+                new_data: model(partial_guild()),
+            },
+            FullEvent::IntegrationCreate {
+                integration: model(integration()),
+            },
+            FullEvent::IntegrationDelete {
+                guild_id: GuildId::new(1),
+                integration_id: IntegrationId::new(80),
+                application_id: Some(model(json!("21"))),
+            },
+            FullEvent::IntegrationUpdate {
+                integration: model(integration()),
+            },
+            FullEvent::InviteCreate {
+                data: model(json!({
+                    "channel_id": "2",
+                    "code": "invite",
+                    "created_at": TIMESTAMP,
+                    "guild_id": "1",
+                    "max_age": 0,
+                    "max_uses": 0,
+                    "temporary": false,
+                    "uses": 0,
+                })),
+            },
+            FullEvent::InviteDelete {
+                data: model(json!({"channel_id": "2", "guild_id": "1", "code": "invite"})),
+            },
+            FullEvent::Message {
+                new_message: model(message()),
+            },
+            FullEvent::MessageDelete {
+                channel_id: model(json!("2")),
+                deleted_message_id: model(json!("50")),
+                guild_id: Some(GuildId::new(1)),
+            },
+            FullEvent::MessageDeleteBulk {
+                channel_id: model(json!("2")),
+                multiple_deleted_messages_ids: vec![model(json!("50")), model(json!("51"))],
+                guild_id: None,
+            },
+            FullEvent::MessageUpdate {
+                old_if_available: None,
+                new: Some(model(message())),
+                event: model(message()),
+            },
+            FullEvent::ThreadCreate {
+                thread: model(thread()),
+            },
+            FullEvent::ThreadDelete {
+                thread: model(json!({"id": "3", "guild_id": "1", "parent_id": "2", "type": 11})),
+                full_thread_data: None,
+            },
+            FullEvent::ThreadMemberUpdate {
+                thread_member: model(json!({
+                    "id": "3",
+                    "user_id": "10",
+                    "guild_id": "1",
+                    "join_timestamp": TIMESTAMP,
+                    "flags": 0,
+                    "member": member(),
+                })),
+            },
+            FullEvent::ThreadMembersUpdate {
+                thread_members_update: model(json!({
+                    "id": "3",
+                    "guild_id": "1",
+                    "member_count": 1,
+                    "added_members": [],
+                    "removed_member_ids": ["11"],
+                })),
+            },
+            FullEvent::ThreadUpdate {
+                old: None,
+                new: model(thread()),
+            },
+        ]
+    }
+
+    /// Checks that an expanded event conforms to the schema of the event
+    fn assert_conforms(event: &FullEvent) {
+        let expanded = ExpandedEvent::new(event).unwrap();
+        let schema = event_schema(expanded.event).unwrap();
+
+        for (name, field) in expanded.fields.iter() {
+            let field_schema = schema
+                .field(name)
+                .unwrap_or_else(|| panic!("{} is not in the schema of {}", name, expanded.event));
+
+            for value in field.value.iter() {
+                let is_none = field_schema.optional && value.to_strings() == ["None"];
+                assert!(
+                    FieldKind::from(value) == field_schema.kind || is_none,
+                    "{} has the wrong type",
+                    name
+                );
+            }
+        }
+
+        for field_schema in schema.fields.iter().filter(|f| !f.optional) {
+            assert!(
+                expanded.get(&field_schema.name).is_some(),
+                "{} is missing",
+                field_schema.name
+            );
+        }
+    }
+
+    #[test]
+    fn test_event_schemas() {
+        for (event, _) in EVENT_SCHEMAS {
+            assert!(event_list().contains(event), "{} is not an event", event);
+        }
+
+        assert_eq!(event_schemas().len(), EVENT_SCHEMAS.len());
+        assert!(event_schema("TYPING_START").is_none());
+
+        let schema = event_schema("GUILD_ROLE_DELETE").unwrap();
+        assert!(!schema.field("role_id").unwrap().optional);
+        assert!(schema.field("role_name").unwrap().optional);
+
+        // The serialized schema is part of the API and must remain stable
+        assert_eq!(
+            serde_json::to_value(event_schema("INTEGRATION_DELETE").unwrap()).unwrap(),
+            serde_json::json!({
+                "event": "INTEGRATION_DELETE",
+                "fields": [
+                    {"name": "guild_id", "type": "Guild", "optional": false},
+                    {"name": "integration_id", "type": "IntegrationId", "optional": false},
+                    {"name": "integration_application_id", "type": "Application", "optional": true},
+                ]
+            })
+        );
+
+        assert_conforms(&FullEvent::IntegrationDelete {
+            guild_id: GuildId::new(1),
+            integration_id: IntegrationId::new(2),
+            application_id: None,
+        });
+
+        assert_conforms(&FullEvent::GuildRoleDelete {
+            guild_id: GuildId::new(1),
+            removed_role_id: RoleId::new(2),
+            removed_role_data_if_available: None,
+        });
+    }
+
+    #[test]
+    fn test_expanded_events_conform_to_schemas() {
+        let events = events();
+
+        for event in events.iter() {
+            assert_conforms(event);
+        }
+
+        for (name, _) in EVENT_SCHEMAS {
+            assert!(
+                events
+                    .iter()
+                    .any(|e| ExpandedEvent::new(e).map(|e| e.event) == Some(*name)),
+                "{} has no test event",
+                name
+            );
+        }
+    }
+}
@@ -35,7 +35,7 @@ pub async fn rederive_perms(
             }.resolve();
 
             sqlx::query!(
-                "UPDATE guild_members SET roles = $1, resolved_perms_cache = $2 WHERE guild_id = $3 AND user_id = $4",
+                "UPDATE guild_members SET roles = $1, resolved_perms_cache = $2, needs_perm_rederive = false WHERE guild_id = $3 AND user_id = $4",
                 &roles_str,
                 &resolved_perms,
                 guild_id.to_string(),
@@ -133,14 +133,9 @@ pub async fn get_kittycat_perms(
         // Check user roles against db roles
         let db_roles = rec.roles;
 
-        let mut roles_changed = false;
-
-        for role in roles {
-            if !db_roles.contains(&role.to_string()) {
-                roles_changed = true;
-                break;
-            }
-        }
+        // Roles may have been added or removed
+        let roles_changed = db_roles.len() != roles.len()
+            || roles.iter().any(|role| !db_roles.contains(&role.to_string()));
 
         if !roles_changed {
             Ok(rec.resolved_perms_cache) // Then use the resolved perms cache
@@ -1,8 +1,13 @@
 pub mod canonical_module;
 pub mod cmd;
+pub mod events;
 pub mod permissions;
+pub mod perm_expr;
+pub mod perm_presets;
+pub mod permission_source;
 pub mod member_permission_calc;
 pub mod module_config;
+pub mod module_registry;
 pub mod poise_ext;
 pub mod silverpelt_cache;
 pub mod utils;
@@ -56,9 +61,23 @@ pub struct Module {
     /// Whether the module is enabled or disabled by default
     pub is_default_enabled: bool,
 
+    /// The IDs of the modules that must be enabled for this module to be enabled
+    pub depends_on: Vec<&'static str>,
+
+    /// The IDs of the modules that cannot be enabled at the same time as this module
+    pub conflicts_with: Vec<&'static str>,
+
     /// The commands in the module
     pub commands: Vec<(Command, CommandExtendedDataMap)>,
 
+    /// The configuration options of the module, stored per guild
+    pub config_options: Vec<config_opt::ConfigOption>,
+
+    /// The names of the events (see `gwevent::core::event_list`) the event handlers of the module should receive
+    ///
+    /// If empty, the event handlers receive all events
+    pub event_filter: Vec<&'static str>,
+
     /// Event handlers (if any)
     pub event_handlers: Vec<ModuleEventHandler>,
 }
@@ -187,6 +206,8 @@ pub struct GuildCommandConfiguration {
     pub perms: Option<PermissionChecks>,
     /// Whether or not the command is disabled. None means to use the default command configuration
     pub disabled: Option<bool>,
+    /// The cooldowns of the command. None means to use the module/default cooldowns
+    pub cooldown: Option<CooldownConfiguration>,
 }
 
 /// Guild module configuration data
@@ -200,5 +221,201 @@ pub struct GuildModuleConfiguration {
     pub module: String,
     /// Whether ot not the module is disabled or not. None means to use the default module configuration
     pub disabled: Option<bool>,
+    /// The cooldowns of all commands in the module. None means to use the default cooldowns of each command
+    pub cooldown: Option<CooldownConfiguration>,
+}
+
+/// Guild-configured cooldowns (in seconds) of a command or module
+///
+/// Each cooldown that is set replaces the corresponding default cooldown of the command, a
+/// cooldown of 0 removes it
+#[derive(Default, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize, Debug)]
+pub struct CooldownConfiguration {
+    /// Cooldown per user
+    pub user: Option<u64>,
+    /// Cooldown per guild
+    pub guild: Option<u64>,
+    /// Cooldown per channel
+    pub channel: Option<u64>,
+}
+
+impl CooldownConfiguration {
+    /// Layers another cooldown configuration on top of this one
+    pub fn merge(&self, other: &CooldownConfiguration) -> CooldownConfiguration {
+        CooldownConfiguration {
+            user: other.user.or(self.user),
+            guild: other.guild.or(self.guild),
+            channel: other.channel.or(self.channel),
+        }
+    }
+
+    /// Layers cooldown configurations ordered from lowest to highest precedence, returning None if there are none
+    pub fn layer<'a>(
+        layers: impl IntoIterator<Item = &'a CooldownConfiguration>,
+    ) -> Option<CooldownConfiguration> {
+        layers
+            .into_iter()
+            .fold(None, |acc, c| Some(acc.unwrap_or_default().merge(c)))
+    }
+
+    /// Applies the cooldown configuration to a poise cooldown configuration
+    pub fn apply(&self, config: &mut poise::CooldownConfig) {
+        fn to_duration(secs: u64) -> Option<std::time::Duration> {
+            if secs == 0 {
+                None
+            } else {
+                Some(std::time::Duration::from_secs(secs))
+            }
+        }
+
+        if let Some(user) = self.user {
+            config.user = to_duration(user);
+        }
+
+        if let Some(guild) = self.guild {
+            config.guild = to_duration(guild);
+        }
+
+        if let Some(channel) = self.channel {
+            config.channel = to_duration(channel);
+        }
+    }
+}
+
+impl Display for CooldownConfiguration {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fn fmt_secs(secs: Option<u64>) -> String {
+            match secs {
+                Some(secs) => format!("{}s", secs),
+                None => "default".to_string(),
+            }
+        }
+
+        write!(
+            f,
+            "user: {}, guild: {}, channel: {}",
+            fmt_secs(self.user),
+            fmt_secs(self.guild),
+            fmt_secs(self.channel)
+        )
+    }
+}
+
+
+/// The scope a command override applies to
+#[derive(Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize, Debug)]
+#[serde(tag = "type", content = "id")]
+pub enum CommandOverrideScope {
+    /// Applies to a single channel (threads use their parent channel)
+    Channel(serenity::all::ChannelId),
+    /// Applies to all channels within a category
+    Category(serenity::all::ChannelId),
+    /// Applies to members with a role
+    Role(serenity::all::RoleId),
+}
+
+impl CommandOverrideScope {
+    /// The scope type stored in the database
+    pub fn scope_type(&self) -> &'static str {
+        match self {
+            CommandOverrideScope::Channel(_) => "channel",
+            CommandOverrideScope::Category(_) => "category",
+            CommandOverrideScope::Role(_) => "role",
+        }
+    }
+
+    /// The scope id stored in the database
+    pub fn scope_id(&self) -> String {
+        match self {
+            CommandOverrideScope::Channel(id) => id.to_string(),
+            CommandOverrideScope::Category(id) => id.to_string(),
+            CommandOverrideScope::Role(id) => id.to_string(),
+        }
+    }
+
+    /// Parses a scope from its database representation
+    pub fn from_db(scope_type: &str, scope_id: &str) -> Result<Self, crate::Error> {
+        match scope_type {
+            "channel" => Ok(CommandOverrideScope::Channel(scope_id.parse()?)),
+            "category" => Ok(CommandOverrideScope::Category(scope_id.parse()?)),
+            "role" => Ok(CommandOverrideScope::Role(scope_id.parse()?)),
+            _ => Err(format!("Unknown command override scope: {}", scope_type).into()),
+        }
+    }
+
+    /// The priority of the scope when layering overrides, higher values take precedence
+    pub fn priority(&self) -> u8 {
+        match self {
+            CommandOverrideScope::Role(_) => 0,
+            CommandOverrideScope::Category(_) => 1,
+            CommandOverrideScope::Channel(_) => 2,
+        }
+    }
+}
+
+impl Display for CommandOverrideScope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CommandOverrideScope::Channel(id) => write!(f, "<#{}>", id),
+            CommandOverrideScope::Category(id) => write!(f, "<#{}> (category)", id),
+            CommandOverrideScope::Role(id) => write!(f, "<@&{}>", id),
+        }
+    }
+}
+
+/// A channel, category or role scoped override of a guilds command configuration
+///
+/// Overrides are layered on top of the guild command configuration, more specific scopes winning
+/// (channel > category > role)
+#[derive(Clone, PartialEq, serde::Serialize, serde::Deserialize, Debug)]
+pub struct GuildCommandOverride {
+    /// The ID
+    pub id: String,
+    /// The guild id (from db)
+    pub guild_id: String,
+    /// The command name
+    pub command: String,
+    /// The scope of the override
+    pub scope: CommandOverrideScope,
+    /// The permission checks to use within this scope. None means to use the guild command configuration
+    pub perms: Option<PermissionChecks>,
+    /// Whether or not the command is disabled within this scope. None means to use the guild command configuration
+    pub disabled: Option<bool>,
+}
+
+/// What temporary (time-limited) kittycat permissions were granted to
+#[derive(Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize, Debug)]
+#[serde(tag = "type", content = "id")]
+pub enum TemporaryPermsScope {
+    /// The permissions were added to the kittycat permissions of a role
+    Role(serenity::all::RoleId),
+    /// The permissions were added to the permission overrides of a member
+    Member(serenity::all::UserId),
 }
 
+impl TemporaryPermsScope {
+    /// The scope type stored in the database
+    pub fn scope_type(&self) -> &'static str {
+        match self {
+            TemporaryPermsScope::Role(_) => "role",
+            TemporaryPermsScope::Member(_) => "member",
+        }
+    }
+
+    /// The scope ID stored in the database
+    pub fn scope_id(&self) -> String {
+        match self {
+            TemporaryPermsScope::Role(id) => id.to_string(),
+            TemporaryPermsScope::Member(id) => id.to_string(),
+        }
+    }
+
+    /// Parses a scope from its database representation
+    pub fn from_db(scope_type: &str, scope_id: &str) -> Result<Self, crate::Error> {
+        match scope_type {
+            "role" => Ok(TemporaryPermsScope::Role(scope_id.parse()?)),
+            "member" => Ok(TemporaryPermsScope::Member(scope_id.parse()?)),
+            _ => Err(format!("Unknown temporary permissions scope: {}", scope_type).into()),
+        }
+    }
+}
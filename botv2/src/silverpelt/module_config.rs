@@ -1,8 +1,12 @@
 use sqlx::PgPool;
-use serenity::all::GuildId;
+use serenity::all::{ChannelId, GuildId, RoleId};
 use super::{
     CommandExtendedData, 
+    CommandExtendedDataMap,
+    CommandOverrideScope,
+    CooldownConfiguration,
     GuildCommandConfiguration, 
+    GuildCommandOverride,
     GuildModuleConfiguration, 
     silverpelt_cache::SILVERPELT_CACHE,
     utils::permute_command_names
@@ -38,7 +42,8 @@ pub async fn is_module_enabled(
                 Ok(!disabled)
             } else {
                 // User wants to use the default value
-                let module = SILVERPELT_CACHE.module_id_cache.get(module).ok_or::<crate::Error>(
+                let registry = SILVERPELT_CACHE.registry();
+                let module = registry.module(module).ok_or::<crate::Error>(
                     format!("Could not find module {} in cache", module).into()
                 )?;
 
@@ -47,7 +52,8 @@ pub async fn is_module_enabled(
             }
         } else {
             // User wants to use the default value
-            let module = SILVERPELT_CACHE.module_id_cache.get(module).ok_or::<crate::Error>(
+            let registry = SILVERPELT_CACHE.registry();
+            let module = registry.module(module).ok_or::<crate::Error>(
                 format!("Could not find module {} in cache", module).into()
             )?;
 
@@ -57,6 +63,71 @@ pub async fn is_module_enabled(
     }
 }
 
+/// Returns the enabled state of all modules in a guild, fetched directly from the database
+///
+/// This is used by module enable/disable to check dependencies and conflicts and as such
+/// intentionally bypasses the module enabled cache
+pub async fn get_module_states(
+    conn: &mut sqlx::PgConnection,
+    guild_id: GuildId,
+) -> Result<std::collections::HashMap<String, bool>, crate::Error> {
+    let recs = sqlx::query!(
+        "SELECT module, disabled FROM guild_module_configurations WHERE guild_id = $1",
+        guild_id.to_string()
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let mut states = std::collections::HashMap::new();
+
+    for module in SILVERPELT_CACHE.registry().modules() {
+        let disabled = recs
+            .iter()
+            .find(|r| r.module == module.id)
+            .and_then(|r| r.disabled);
+
+        states.insert(
+            module.id.to_string(),
+            match disabled {
+                Some(disabled) => !disabled,
+                None => module.is_default_enabled,
+            },
+        );
+    }
+
+    Ok(states)
+}
+
+/// Returns the extended data of a command given the extended data map of its root command
+///
+/// `permutations` are the permutations of the command name as returned by `permute_command_names`. The
+/// deepest subcommand with extended data is used, falling back to the root command and then to kittycat_or_admin
+pub fn resolve_command_extended_data(
+    root_cmd_data: &CommandExtendedDataMap,
+    permutations: &[String],
+) -> CommandExtendedData {
+    let root_cmd = permutations.first().map(|c| c.as_str()).unwrap_or_default();
+
+    let mut cmd_data = root_cmd_data
+        .get("")
+        .unwrap_or(
+            &CommandExtendedData::kittycat_or_admin(root_cmd, "*")
+        )
+        .clone();
+
+    for command in permutations.iter() {
+        let cmd_replaced = command
+            .replace(root_cmd, "")
+            .trim()
+            .to_string();
+        if let Some(data) = root_cmd_data.get(&cmd_replaced.as_str()) {
+            cmd_data = data.clone();
+        }
+    }
+
+    cmd_data
+}
+
 /// Returns the configuration of a command
 pub async fn get_command_configuration(
     pool: &PgPool,
@@ -73,7 +144,9 @@ pub async fn get_command_configuration(
     let permutations = permute_command_names(name);
     let root_cmd = permutations.first().unwrap();
 
-    let root_cmd_data = SILVERPELT_CACHE.command_extra_data_map.get(root_cmd);
+    let registry = SILVERPELT_CACHE.registry();
+
+    let root_cmd_data = registry.command_extra_data(root_cmd);
 
     let Some(root_cmd_data) = root_cmd_data else {
         return Err(format!(
@@ -83,45 +156,37 @@ pub async fn get_command_configuration(
         .into());
     };
 
-    let module = SILVERPELT_CACHE.command_id_module_map.get(root_cmd).ok_or::<crate::Error>("Unknown error determining module of command".into())?;
+    let module = registry.module_of_command(root_cmd).ok_or::<crate::Error>("Unknown error determining module of command".into())?;
 
     // Check if theres any module configuration
     let module_configuration = sqlx::query!(
-        "SELECT id, guild_id, module, disabled FROM guild_module_configurations WHERE guild_id = $1 AND module = $2",
+        "SELECT id, guild_id, module, disabled, cooldown FROM guild_module_configurations WHERE guild_id = $1 AND module = $2",
         guild_id,
         module,
     )
     .fetch_optional(pool)
     .await?
-    .map(|rec| GuildModuleConfiguration {
-        id: rec.id.hyphenated().to_string(),
-        guild_id: rec.guild_id,
-        module: rec.module,
-        disabled: rec.disabled,
-    });
+    .map(|rec| -> Result<GuildModuleConfiguration, crate::Error> {
+        Ok(GuildModuleConfiguration {
+            id: rec.id.hyphenated().to_string(),
+            guild_id: rec.guild_id,
+            module: rec.module,
+            disabled: rec.disabled,
+            cooldown: rec.cooldown.map(serde_json::from_value).transpose()?,
+        })
+    })
+    .transpose()?;
 
-    let mut cmd_data = root_cmd_data
-        .get("")
-        .unwrap_or(
-            &CommandExtendedData::kittycat_or_admin(root_cmd, "*")
-        )
-        .clone();
+    let cmd_data = resolve_command_extended_data(root_cmd_data, &permutations);
 
-    for command in permutations.iter() {
-        let cmd_replaced = command
-            .replace(&root_cmd.to_string(), "")
-            .trim()
-            .to_string();
-        if let Some(data) = root_cmd_data.get(&cmd_replaced.as_str()) {
-            cmd_data = data.clone();
-        }
-    }
+    let mut command_configuration: Option<GuildCommandConfiguration> = None;
 
-    let mut command_configuration = None;
+    // Cooldowns are layered per field, so a subcommand only overrides the cooldowns it sets itself
+    let mut cooldowns = Vec::new();
 
     for permutation in permutations.iter() {
         let rec = sqlx::query!(
-            "SELECT id, guild_id, command, perms, disabled FROM guild_command_configurations WHERE guild_id = $1 AND command = $2",
+            "SELECT id, guild_id, command, perms, disabled, cooldown FROM guild_command_configurations WHERE guild_id = $1 AND command = $2",
             guild_id,
             permutation,
         )
@@ -131,6 +196,11 @@ pub async fn get_command_configuration(
         // We are deeper in the tree, so we can overwrite the command configuration
         let mut _cmd_perms_overriden = false; // Not used currently but will be used in the future for module no_admin etc.
         if let Some(rec) = rec {
+            let cooldown: Option<CooldownConfiguration> =
+                rec.cooldown.map(serde_json::from_value).transpose()?;
+
+            cooldowns.extend(cooldown);
+
             command_configuration = Some(GuildCommandConfiguration {
                 id: rec.id.hyphenated().to_string(),
                 guild_id: rec.guild_id,
@@ -138,15 +208,167 @@ pub async fn get_command_configuration(
                 perms: {
                     if let Some(perms) = rec.perms {
                         _cmd_perms_overriden = true;
-                        serde_json::from_value(perms).unwrap()
+                        super::perm_expr::checks_from_value(perms)?
                     } else {
                         None
                     }
                 },
                 disabled: rec.disabled,
+                cooldown: None,
             });
         }
     }
 
+    if let Some(ref mut command_configuration) = command_configuration {
+        command_configuration.cooldown = CooldownConfiguration::layer(cooldowns.iter());
+    }
+
     Ok((cmd_data, command_configuration, module_configuration))
 }
+
+/// Returns the command overrides applying to a command in a given channel for a member with the given roles
+///
+/// `channel` is the (channel, category) pair as returned by `cmd::resolve_channel_scope`. The overrides are
+/// returned ordered from lowest to highest precedence: shallower commands first and then by scope (role < category < channel)
+pub async fn get_command_overrides(
+    pool: &PgPool,
+    guild_id: GuildId,
+    name: &str,
+    channel: Option<(ChannelId, Option<ChannelId>)>,
+    roles: &[RoleId],
+) -> Result<Vec<GuildCommandOverride>, crate::Error> {
+    let permutations = permute_command_names(name);
+
+    let (channel_id, category_id) = match channel {
+        Some((channel_id, category_id)) => (Some(channel_id.to_string()), category_id.map(|c| c.to_string())),
+        None => (None, None),
+    };
+
+    let roles = roles.iter().map(|r| r.to_string()).collect::<Vec<String>>();
+
+    let recs = sqlx::query!(
+        "SELECT id, guild_id, command, scope_type, scope_id, perms, disabled FROM guild_command_overrides WHERE guild_id = $1 AND command = ANY($2) AND ((scope_type = 'channel' AND scope_id = $3) OR (scope_type = 'category' AND scope_id = $4) OR (scope_type = 'role' AND scope_id = ANY($5)))",
+        guild_id.to_string(),
+        &permutations,
+        channel_id,
+        category_id,
+        &roles,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut overrides = Vec::with_capacity(recs.len());
+
+    for rec in recs {
+        overrides.push(GuildCommandOverride {
+            id: rec.id.hyphenated().to_string(),
+            guild_id: rec.guild_id,
+            command: rec.command,
+            scope: CommandOverrideScope::from_db(&rec.scope_type, &rec.scope_id)?,
+            perms: {
+                if let Some(perms) = rec.perms {
+                    super::perm_expr::checks_from_value(perms)?
+                } else {
+                    None
+                }
+            },
+            disabled: rec.disabled,
+        });
+    }
+
+    overrides.sort_by_key(|o| {
+        (
+            permutations.iter().position(|p| p == &o.command).unwrap_or_default(),
+            o.scope.priority(),
+            o.scope.scope_id(),
+        )
+    });
+
+    Ok(overrides)
+}
+
+/// Returns the configuration option of a module given its ID
+pub fn get_config_option(module: &str, option: &str) -> Result<super::config_opt::ConfigOption, crate::Error> {
+    let registry = SILVERPELT_CACHE.registry();
+    let Some(module_data) = registry.module(module) else {
+        return Err(format!("The module ``{}`` does not exist", module).into());
+    };
+
+    let Some(config_option) = module_data.config_options.iter().find(|o| o.id == option) else {
+        return Err(format!("The module ``{}`` has no configuration option ``{}``", module, option).into());
+    };
+
+    Ok(config_option.clone())
+}
+
+/// Returns the value of a modules configuration option in a guild, falling back to its default value
+pub async fn get_module_option(
+    pool: &PgPool,
+    guild_id: GuildId,
+    module: &str,
+    option: &str,
+) -> Result<serde_json::Value, crate::Error> {
+    let config_option = get_config_option(module, option)?;
+
+    let rec = sqlx::query!(
+        "SELECT value FROM guild_module_options WHERE guild_id = $1 AND module = $2 AND option_id = $3",
+        guild_id.to_string(),
+        module,
+        option
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    match rec {
+        // Values are validated on set, but the schema of the option may have changed since
+        Some(rec) if config_option.field_type.validate_value(&rec.value).is_ok() => Ok(rec.value),
+        _ => Ok(config_option.field_type.default_value()),
+    }
+}
+
+/// Validates and sets the value of a modules configuration option in a guild
+pub async fn set_module_option(
+    pool: &PgPool,
+    guild_id: GuildId,
+    module: &str,
+    option: &str,
+    value: serde_json::Value,
+) -> Result<(), crate::Error> {
+    let config_option = get_config_option(module, option)?;
+
+    config_option.field_type.validate_value(&value)?;
+
+    sqlx::query!(
+        "INSERT INTO guild_module_options (guild_id, module, option_id, value) VALUES ($1, $2, $3, $4) ON CONFLICT (guild_id, module, option_id) DO UPDATE SET value = EXCLUDED.value, last_updated = NOW()",
+        guild_id.to_string(),
+        module,
+        option,
+        value
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Resets a modules configuration option in a guild to its default value
+pub async fn reset_module_option(
+    pool: &PgPool,
+    guild_id: GuildId,
+    module: &str,
+    option: &str,
+) -> Result<(), crate::Error> {
+    // Ensure the option exists
+    get_config_option(module, option)?;
+
+    sqlx::query!(
+        "DELETE FROM guild_module_options WHERE guild_id = $1 AND module = $2 AND option_id = $3",
+        guild_id.to_string(),
+        module,
+        option
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,267 @@
+use super::{canonical_module::CanonicalModule, CommandExtendedDataMap, Module};
+use indexmap::IndexMap;
+
+/// The module registry contains all loaded modules along with the lookup maps derived from them
+///
+/// A registry is built in a single pass over a list of modules and is immutable once built. To
+/// change the loaded modules at runtime, build a new registry and swap it in using
+/// `SilverpeltCache::reload_modules`
+pub struct ModuleRegistry {
+    /// Module ID to module, in the order the modules were registered
+    modules: IndexMap<String, Module>,
+
+    /// Command ID to the extended data of the command (the extended data map stores the default base permissions and other data per command)
+    command_extra_data_map: IndexMap<String, CommandExtendedDataMap>,
+
+    /// Module name to module ID
+    module_id_name_map: IndexMap<String, String>,
+
+    /// Command ID (including subcommands) to module ID
+    command_id_module_map: IndexMap<String, String>,
+
+    /// Module ID to the canonical form of the module
+    canonical_modules: IndexMap<String, CanonicalModule>,
+
+    /// Module ID to the kittycat permissions declared in the default permissions of its commands
+    kittycat_perms: IndexMap<String, Vec<String>>,
+}
+
+impl ModuleRegistry {
+    /// Builds a registry from a list of modules, validating their dependencies, conflicts and event filters
+    pub fn new(modules: Vec<Module>) -> Result<Self, crate::Error> {
+        {
+            let modules = modules.iter().collect::<Vec<&Module>>();
+
+            super::utils::validate_module_graph(&modules)?;
+            super::utils::validate_module_event_filters(&modules)?;
+        }
+
+        let mut registry = Self {
+            modules: IndexMap::new(),
+            command_extra_data_map: IndexMap::new(),
+            module_id_name_map: IndexMap::new(),
+            command_id_module_map: IndexMap::new(),
+            canonical_modules: IndexMap::new(),
+            kittycat_perms: IndexMap::new(),
+        };
+
+        for module in modules {
+            if registry.modules.contains_key(module.id) {
+                return Err(format!("Module {} is registered more than once", module.id).into());
+            }
+
+            let mut kittycat_perms = Vec::new();
+
+            for (command, extended_data) in module.commands.iter() {
+                for perm in extended_data
+                    .values()
+                    .flat_map(|d| d.default_perms.checks.iter())
+                    .flat_map(|c| c.kittycat_perms.iter())
+                {
+                    if !kittycat_perms.contains(perm) {
+                        kittycat_perms.push(perm.clone());
+                    }
+                }
+
+                registry
+                    .command_extra_data_map
+                    .insert(command.name.clone(), extended_data.clone());
+
+                registry
+                    .command_id_module_map
+                    .insert(command.name.to_string(), module.id.to_string());
+
+                for sub in command.subcommands.iter() {
+                    registry
+                        .command_id_module_map
+                        .insert(sub.name.to_string(), module.id.to_string());
+                }
+            }
+
+            registry
+                .kittycat_perms
+                .insert(module.id.to_string(), kittycat_perms);
+
+            registry
+                .module_id_name_map
+                .insert(module.name.to_string(), module.id.to_string());
+
+            registry
+                .canonical_modules
+                .insert(module.id.to_string(), CanonicalModule::from(&module));
+
+            registry.modules.insert(module.id.to_string(), module);
+        }
+
+        Ok(registry)
+    }
+
+    /// Returns a module given its ID
+    pub fn module(&self, id: &str) -> Option<&Module> {
+        self.modules.get(id)
+    }
+
+    /// Returns all modules, in the order they were registered
+    pub fn modules(&self) -> impl Iterator<Item = &Module> {
+        self.modules.values()
+    }
+
+    /// Returns the ID of a module given its name
+    pub fn module_id_by_name(&self, name: &str) -> Option<&str> {
+        self.module_id_name_map.get(name).map(|id| id.as_str())
+    }
+
+    /// Returns the ID of the module a command (or subcommand) belongs to
+    pub fn module_of_command(&self, command: &str) -> Option<&str> {
+        self.command_id_module_map.get(command).map(|id| id.as_str())
+    }
+
+    /// Returns whether a command exists, given its fully qualified name (e.g. ``limits exempt add``)
+    pub fn command_exists(&self, command: &str) -> bool {
+        let mut names = command.split_whitespace();
+
+        let Some(base_command) = names.next() else {
+            return false;
+        };
+
+        let Some(mut cmd) = self
+            .modules
+            .values()
+            .flat_map(|m| m.commands.iter())
+            .map(|(c, _)| c)
+            .find(|c| c.name == base_command)
+        else {
+            return false;
+        };
+
+        for name in names {
+            match cmd.subcommands.iter().find(|c| c.name == name) {
+                Some(sub) => cmd = sub,
+                None => return false,
+            }
+        }
+
+        true
+    }
+
+    /// Returns the extended data of a (root) command
+    pub fn command_extra_data(&self, command: &str) -> Option<&CommandExtendedDataMap> {
+        self.command_extra_data_map.get(command)
+    }
+
+    /// Returns the kittycat permissions declared by the commands of a module
+    pub fn module_kittycat_perms(&self, id: &str) -> &[String] {
+        self.kittycat_perms
+            .get(id)
+            .map(|perms| perms.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Returns all kittycat permissions declared by the commands of all modules, without duplicates
+    pub fn kittycat_perms(&self) -> Vec<&str> {
+        let mut perms = Vec::new();
+
+        for perm in self.kittycat_perms.values().flatten() {
+            if !perms.contains(&perm.as_str()) {
+                perms.push(perm.as_str());
+            }
+        }
+
+        perms
+    }
+
+    /// Returns the canonical forms of all modules
+    pub fn canonical_modules(&self) -> impl Iterator<Item = &CanonicalModule> {
+        self.canonical_modules.values()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::silverpelt::{CommandExtendedData, PermissionChecks};
+    use indexmap::indexmap;
+
+    fn test_module(id: &'static str, commands: Vec<&'static str>) -> Module {
+        Module {
+            id,
+            name: id,
+            description: "",
+            web_hidden: false,
+            toggleable: true,
+            commands_configurable: true,
+            virtual_module: false,
+            is_default_enabled: true,
+            depends_on: vec![],
+            conflicts_with: vec![],
+            commands: commands
+                .into_iter()
+                .map(|name| {
+                    let command = crate::silverpelt::Command {
+                        name: name.to_string(),
+                        subcommands: vec![crate::silverpelt::Command {
+                            name: "sub".to_string(),
+                            ..Default::default()
+                        }],
+                        ..Default::default()
+                    };
+
+                    (
+                        command,
+                        indexmap! {
+                            "" => CommandExtendedData {
+                                default_perms: PermissionChecks {
+                                    checks: vec![crate::silverpelt::PermissionCheck {
+                                        kittycat_perms: vec![format!("{}.{}", id, name), "shared.perm".to_string()],
+                                        native_perms: vec![],
+                                        outer_and: false,
+                                        inner_and: false,
+                                    }],
+                                    checks_needed: 0,
+                                },
+                                is_default_enabled: false,
+                            },
+                        },
+                    )
+                })
+                .collect(),
+            config_options: vec![],
+            event_filter: vec![],
+            event_handlers: vec![],
+        }
+    }
+
+    #[test]
+    fn test_module_registry() {
+        let registry = ModuleRegistry::new(vec![
+            test_module("a", vec!["foo", "bar"]),
+            test_module("b", vec!["baz"]),
+        ])
+        .unwrap();
+
+        assert_eq!(registry.modules().map(|m| m.id).collect::<Vec<_>>(), vec!["a", "b"]);
+        assert_eq!(registry.module("b").map(|m| m.id), Some("b"));
+        assert!(registry.module("c").is_none());
+        assert_eq!(registry.module_id_by_name("a"), Some("a"));
+        assert_eq!(registry.module_of_command("bar"), Some("a"));
+        assert_eq!(registry.module_of_command("baz"), Some("b"));
+        assert_eq!(registry.module_of_command("sub"), Some("b"));
+        assert!(registry.command_exists("foo"));
+        assert!(registry.command_exists("foo  sub"));
+        assert!(!registry.command_exists("foo subb"));
+        assert!(!registry.command_exists("foo sub sub"));
+        assert!(!registry.command_exists("qux"));
+        assert!(!registry.command_exists(""));
+        assert!(!registry.command_extra_data("foo").unwrap()[""].is_default_enabled);
+        assert_eq!(registry.canonical_modules().count(), 2);
+        assert_eq!(registry.module_kittycat_perms("a"), ["a.foo", "shared.perm", "a.bar"]);
+        assert!(registry.module_kittycat_perms("c").is_empty());
+        assert_eq!(registry.kittycat_perms(), vec!["a.foo", "shared.perm", "a.bar", "b.baz"]);
+
+        // Duplicate modules
+        assert!(ModuleRegistry::new(vec![test_module("a", vec![]), test_module("a", vec![])]).is_err());
+
+        // The real modules must always produce a valid registry
+        assert!(ModuleRegistry::new(crate::modules::modules()).is_ok());
+    }
+}
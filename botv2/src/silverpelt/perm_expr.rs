@@ -0,0 +1,544 @@
+//! Permission expressions are a human-writable boolean format for permission checks
+//!
+//! An expression is made up of terms joined by `&` (AND) and `|` (OR) with parenthesis for grouping.
+//! AND binds tighter than OR, so `native:BAN_MEMBERS & kc:moderation.ban | native:ADMINISTRATOR` means
+//! `(native:BAN_MEMBERS & kc:moderation.ban) | native:ADMINISTRATOR`
+//!
+//! Terms are either `native:<DISCORD_PERMISSION>` or `kc:<namespace>.<permission>`
+//!
+//! Expressions compile to [`PermissionChecks`] by converting them to disjunctive normal form, where
+//! each AND group becomes one inner_and [`PermissionCheck`] and the checks are OR'd together.
+use super::{PermissionCheck, PermissionChecks};
+use serenity::all::Permissions;
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// The maximum number of checks an expression may compile to
+///
+/// Distributing AND over OR can blow up the number of checks, so we cap it
+pub const MAX_COMPILED_CHECKS: usize = 32;
+
+/// A single permission term in an expression
+#[derive(Clone, PartialEq, Debug)]
+pub enum PermissionTerm {
+    /// A native Discord permission (single flag)
+    Native(Permissions),
+    /// A kittycat permission
+    Kittycat(String),
+}
+
+impl Display for PermissionTerm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PermissionTerm::Native(perm) => {
+                let name = perm.iter_names().next().map(|(name, _)| name).unwrap_or("UNKNOWN");
+                write!(f, "native:{}", name)
+            }
+            PermissionTerm::Kittycat(perm) => write!(f, "kc:{}", perm),
+        }
+    }
+}
+
+/// A parsed permission expression
+#[derive(Clone, PartialEq, Debug)]
+pub enum PermissionExpr {
+    Term(PermissionTerm),
+    And(Vec<PermissionExpr>),
+    Or(Vec<PermissionExpr>),
+}
+
+impl Display for PermissionExpr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PermissionExpr::Term(term) => write!(f, "{}", term),
+            PermissionExpr::And(exprs) => {
+                for (i, expr) in exprs.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, " & ")?;
+                    }
+
+                    // OR binds looser than AND, so it needs parenthesis here
+                    if matches!(expr, PermissionExpr::Or(_)) {
+                        write!(f, "({})", expr)?;
+                    } else {
+                        write!(f, "{}", expr)?;
+                    }
+                }
+
+                Ok(())
+            }
+            PermissionExpr::Or(exprs) => {
+                for (i, expr) in exprs.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, " | ")?;
+                    }
+
+                    write!(f, "{}", expr)?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+impl FromStr for PermissionExpr {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let tokens = tokenize(s)?;
+
+        if tokens.is_empty() {
+            return Err("Permission expression cannot be empty".into());
+        }
+
+        let mut parser = Parser { tokens, pos: 0 };
+
+        let expr = parser.parse_or()?;
+
+        if let Some(token) = parser.tokens.get(parser.pos) {
+            return Err(format!("Unexpected token ``{}`` in permission expression", token).into());
+        }
+
+        Ok(expr)
+    }
+}
+
+impl PermissionExpr {
+    /// Converts the expression to disjunctive normal form (an OR of AND groups of terms)
+    pub fn to_dnf(&self) -> Result<Vec<Vec<PermissionTerm>>, crate::Error> {
+        match self {
+            PermissionExpr::Term(term) => Ok(vec![vec![term.clone()]]),
+            PermissionExpr::Or(exprs) => {
+                let mut groups = Vec::new();
+
+                for expr in exprs {
+                    groups.extend(expr.to_dnf()?);
+
+                    if groups.len() > MAX_COMPILED_CHECKS {
+                        return Err(format!("Permission expression is too complex (more than {} checks)", MAX_COMPILED_CHECKS).into());
+                    }
+                }
+
+                Ok(groups)
+            }
+            PermissionExpr::And(exprs) => {
+                let mut groups: Vec<Vec<PermissionTerm>> = vec![vec![]];
+
+                for expr in exprs {
+                    let expr_groups = expr.to_dnf()?;
+
+                    if groups.len() * expr_groups.len() > MAX_COMPILED_CHECKS {
+                        return Err(format!("Permission expression is too complex (more than {} checks)", MAX_COMPILED_CHECKS).into());
+                    }
+
+                    let mut new_groups = Vec::with_capacity(groups.len() * expr_groups.len());
+
+                    for group in &groups {
+                        for expr_group in &expr_groups {
+                            let mut new_group = group.clone();
+                            new_group.extend(expr_group.iter().cloned());
+                            new_groups.push(new_group);
+                        }
+                    }
+
+                    groups = new_groups;
+                }
+
+                Ok(groups)
+            }
+        }
+    }
+
+    /// Compiles the expression to the permission checks used by the permission system
+    pub fn to_checks(&self) -> Result<PermissionChecks, crate::Error> {
+        let mut checks = Vec::new();
+
+        for group in self.to_dnf()? {
+            let mut check = PermissionCheck {
+                kittycat_perms: vec![],
+                native_perms: vec![],
+                outer_and: false,
+                inner_and: true,
+            };
+
+            for term in group {
+                match term {
+                    PermissionTerm::Native(perm) => {
+                        if !check.native_perms.contains(&perm) {
+                            check.native_perms.push(perm);
+                        }
+                    }
+                    PermissionTerm::Kittycat(perm) => {
+                        if !check.kittycat_perms.contains(&perm) {
+                            check.kittycat_perms.push(perm);
+                        }
+                    }
+                }
+            }
+
+            checks.push(check);
+        }
+
+        Ok(PermissionChecks {
+            checks,
+            checks_needed: 1,
+        })
+    }
+
+    /// Converts existing permission checks back to an expression
+    ///
+    /// Checks using `outer_and` or needing more than one check to succeed have no exact
+    /// equivalent and will error. Returns `None` if there are no checks (no permissions needed)
+    pub fn from_checks(checks: &PermissionChecks) -> Result<Option<Self>, crate::Error> {
+        if checks.checks_needed > 1 {
+            return Err("Permission checks needing more than one check to succeed cannot be expressed as an expression".into());
+        }
+
+        let mut or_exprs = Vec::new();
+
+        for (i, check) in checks.checks.iter().enumerate() {
+            if check.outer_and && i < checks.checks.len() - 1 {
+                return Err("Permission checks using outer_and cannot be expressed as an expression".into());
+            }
+
+            let terms = check
+                .native_perms
+                .iter()
+                .flat_map(|perm| perm.iter().map(|p| PermissionExpr::Term(PermissionTerm::Native(p))))
+                .chain(
+                    check
+                        .kittycat_perms
+                        .iter()
+                        .map(|perm| PermissionExpr::Term(PermissionTerm::Kittycat(perm.clone()))),
+                )
+                .collect::<Vec<_>>();
+
+            match terms.len() {
+                // An empty check always succeeds, so the whole expression does too
+                0 => return Ok(None),
+                1 => or_exprs.extend(terms),
+                _ => {
+                    if check.inner_and {
+                        or_exprs.push(PermissionExpr::And(terms));
+                    } else {
+                        or_exprs.extend(terms);
+                    }
+                }
+            }
+        }
+
+        match or_exprs.len() {
+            0 => Ok(None),
+            1 => Ok(or_exprs.pop()),
+            _ => Ok(Some(PermissionExpr::Or(or_exprs))),
+        }
+    }
+}
+
+/// Parses a permission expression directly into permission checks
+pub fn parse_checks(s: &str) -> Result<PermissionChecks, crate::Error> {
+    s.parse::<PermissionExpr>()?.to_checks()
+}
+
+/// Parses stored command permissions, which may either be a permission expression string or serialized permission checks
+pub fn checks_from_value(value: serde_json::Value) -> Result<Option<PermissionChecks>, crate::Error> {
+    match value {
+        serde_json::Value::Null => Ok(None),
+        serde_json::Value::String(s) => Ok(Some(parse_checks(&s)?)),
+        value => Ok(Some(serde_json::from_value(value)?)),
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+enum Token {
+    And,
+    Or,
+    LParen,
+    RParen,
+    Term(PermissionTerm),
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::And => write!(f, "&"),
+            Token::Or => write!(f, "|"),
+            Token::LParen => write!(f, "("),
+            Token::RParen => write!(f, ")"),
+            Token::Term(term) => write!(f, "{}", term),
+        }
+    }
+}
+
+fn parse_term(word: &str) -> Result<PermissionTerm, crate::Error> {
+    if let Some(name) = word.strip_prefix("native:") {
+        let Some(perm) = Permissions::from_name(&name.to_uppercase()) else {
+            return Err(format!("Unknown Discord permission ``{}``", name).into());
+        };
+
+        Ok(PermissionTerm::Native(perm))
+    } else if let Some(perm) = word.strip_prefix("kc:") {
+        if !perm.contains('.') {
+            return Err(format!("Invalid kittycat permission ``{}``. Permission must be in format `<namespace>.<permission>`", perm).into());
+        }
+
+        Ok(PermissionTerm::Kittycat(perm.to_string()))
+    } else {
+        Err(format!("Invalid permission term ``{}``. Terms must start with `native:` or `kc:`", word).into())
+    }
+}
+
+fn tokenize(s: &str) -> Result<Vec<Token>, crate::Error> {
+    let mut tokens = Vec::new();
+    let mut chars = s.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        match c {
+            '&' => tokens.push(Token::And),
+            '|' => tokens.push(Token::Or),
+            '(' => tokens.push(Token::LParen),
+            ')' => tokens.push(Token::RParen),
+            c if c.is_whitespace() => continue,
+            _ => {
+                let mut end = start + c.len_utf8();
+
+                while let Some((i, c)) = chars.peek() {
+                    if c.is_whitespace() || ['&', '|', '(', ')'].contains(c) {
+                        break;
+                    }
+
+                    end = i + c.len_utf8();
+                    chars.next();
+                }
+
+                tokens.push(Token::Term(parse_term(&s[start..end])?));
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn parse_or(&mut self) -> Result<PermissionExpr, crate::Error> {
+        let mut exprs = vec![self.parse_and()?];
+
+        while self.peek() == Some(&Token::Or) {
+            self.pos += 1;
+            exprs.push(self.parse_and()?);
+        }
+
+        Ok(flatten(exprs, false))
+    }
+
+    fn parse_and(&mut self) -> Result<PermissionExpr, crate::Error> {
+        let mut exprs = vec![self.parse_atom()?];
+
+        while self.peek() == Some(&Token::And) {
+            self.pos += 1;
+            exprs.push(self.parse_atom()?);
+        }
+
+        Ok(flatten(exprs, true))
+    }
+
+    fn parse_atom(&mut self) -> Result<PermissionExpr, crate::Error> {
+        match self.next() {
+            Some(Token::Term(term)) => Ok(PermissionExpr::Term(term)),
+            Some(Token::LParen) => {
+                let expr = self.parse_or()?;
+
+                if self.next() != Some(Token::RParen) {
+                    return Err("Missing closing parenthesis in permission expression".into());
+                }
+
+                Ok(expr)
+            }
+            Some(token) => Err(format!("Unexpected token ``{}`` in permission expression", token).into()),
+            None => Err("Unexpected end of permission expression".into()),
+        }
+    }
+}
+
+/// Merges a list of expressions into one AND/OR expression, flattening nested expressions of the same kind
+fn flatten(exprs: Vec<PermissionExpr>, and: bool) -> PermissionExpr {
+    if exprs.len() == 1 {
+        return exprs.into_iter().next().unwrap();
+    }
+
+    let mut flat = Vec::new();
+
+    for expr in exprs {
+        match expr {
+            PermissionExpr::And(inner) if and => flat.extend(inner),
+            PermissionExpr::Or(inner) if !and => flat.extend(inner),
+            _ => flat.push(expr),
+        }
+    }
+
+    if and {
+        PermissionExpr::And(flat)
+    } else {
+        PermissionExpr::Or(flat)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_precedence() {
+        let expr: PermissionExpr = "native:BAN_MEMBERS & kc:moderation.ban | native:ADMINISTRATOR"
+            .parse()
+            .unwrap();
+
+        assert_eq!(
+            expr,
+            PermissionExpr::Or(vec![
+                PermissionExpr::And(vec![
+                    PermissionExpr::Term(PermissionTerm::Native(Permissions::BAN_MEMBERS)),
+                    PermissionExpr::Term(PermissionTerm::Kittycat("moderation.ban".to_string())),
+                ]),
+                PermissionExpr::Term(PermissionTerm::Native(Permissions::ADMINISTRATOR)),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for s in [
+            "native:BAN_MEMBERS",
+            "kc:moderation.ban",
+            "native:BAN_MEMBERS & kc:moderation.ban | native:ADMINISTRATOR",
+            "native:BAN_MEMBERS & (kc:moderation.ban | kc:moderation.*)",
+            "(kc:a.b | kc:c.d) & (kc:e.f | native:KICK_MEMBERS)",
+        ] {
+            let expr: PermissionExpr = s.parse().unwrap();
+            assert_eq!(expr.to_string(), s);
+            assert_eq!(expr.to_string().parse::<PermissionExpr>().unwrap(), expr);
+        }
+
+        // Redundant parenthesis and whitespace are normalized
+        let expr: PermissionExpr = "((native:BAN_MEMBERS&kc:moderation.ban))|(native:ADMINISTRATOR)"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            expr.to_string(),
+            "native:BAN_MEMBERS & kc:moderation.ban | native:ADMINISTRATOR"
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for s in [
+            "",
+            "BAN_MEMBERS",
+            "native:NOT_A_PERMISSION",
+            "kc:nodot",
+            "native:BAN_MEMBERS &",
+            "(native:BAN_MEMBERS",
+            "native:BAN_MEMBERS)",
+            "| kc:a.b",
+        ] {
+            assert!(s.parse::<PermissionExpr>().is_err(), "{} should not parse", s);
+        }
+    }
+
+    #[test]
+    fn test_to_checks() {
+        let checks = parse_checks("native:BAN_MEMBERS & (kc:moderation.ban | native:ADMINISTRATOR)").unwrap();
+
+        assert_eq!(checks.checks_needed, 1);
+        assert_eq!(
+            checks.checks,
+            vec![
+                PermissionCheck {
+                    kittycat_perms: vec!["moderation.ban".to_string()],
+                    native_perms: vec![Permissions::BAN_MEMBERS],
+                    outer_and: false,
+                    inner_and: true,
+                },
+                PermissionCheck {
+                    kittycat_perms: vec![],
+                    native_perms: vec![Permissions::BAN_MEMBERS, Permissions::ADMINISTRATOR],
+                    outer_and: false,
+                    inner_and: true,
+                },
+            ]
+        );
+
+        // Compiled checks convert back to an equivalent expression
+        let expr = PermissionExpr::from_checks(&checks).unwrap().unwrap();
+        assert_eq!(
+            expr.to_string(),
+            "native:BAN_MEMBERS & kc:moderation.ban | native:BAN_MEMBERS & native:ADMINISTRATOR"
+        );
+        assert_eq!(expr.to_checks().unwrap(), checks);
+    }
+
+    #[test]
+    fn test_checks_from_value() {
+        assert_eq!(checks_from_value(serde_json::Value::Null).unwrap(), None);
+
+        let checks = parse_checks("kc:moderation.ban | native:ADMINISTRATOR").unwrap();
+
+        assert_eq!(
+            checks_from_value(serde_json::json!("kc:moderation.ban | native:ADMINISTRATOR")).unwrap(),
+            Some(checks.clone())
+        );
+        assert_eq!(
+            checks_from_value(serde_json::to_value(&checks).unwrap()).unwrap(),
+            Some(checks)
+        );
+        assert!(checks_from_value(serde_json::json!("kc:nodot")).is_err());
+    }
+
+    #[test]
+    fn test_from_checks() {
+        assert_eq!(
+            PermissionExpr::from_checks(&PermissionChecks::default()).unwrap(),
+            None
+        );
+
+        let expr = PermissionExpr::from_checks(&crate::silverpelt::CommandExtendedData::kittycat_or_admin("test", "abc").default_perms)
+            .unwrap()
+            .unwrap();
+        assert_eq!(expr.to_string(), "native:ADMINISTRATOR | kc:test.abc");
+
+        assert!(PermissionExpr::from_checks(&PermissionChecks {
+            checks: vec![
+                PermissionCheck {
+                    kittycat_perms: vec!["a.b".to_string()],
+                    native_perms: vec![],
+                    outer_and: true,
+                    inner_and: false,
+                },
+                PermissionCheck {
+                    kittycat_perms: vec!["c.d".to_string()],
+                    native_perms: vec![],
+                    outer_and: false,
+                    inner_and: false,
+                },
+            ],
+            checks_needed: 1,
+        })
+        .is_err());
+    }
+}
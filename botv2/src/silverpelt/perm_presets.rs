@@ -0,0 +1,201 @@
+use super::module_registry::ModuleRegistry;
+
+/// A named set of kittycat permissions that can be given to a role in one go
+pub struct PermissionPreset {
+    /// The ID of the preset
+    pub id: &'static str,
+
+    /// The name of the preset
+    pub name: &'static str,
+
+    /// The modules whose declared kittycat permissions make up the preset
+    pub modules: &'static [&'static str],
+}
+
+/// The permission presets available in `/perms modrole`
+pub const PRESETS: &[PermissionPreset] = &[
+    PermissionPreset {
+        id: "moderator",
+        name: "Moderator",
+        modules: &["moderation"],
+    },
+    PermissionPreset {
+        id: "backup_manager",
+        name: "Backup Manager",
+        modules: &["server_backups", "server_member_backups"],
+    },
+    PermissionPreset {
+        id: "limits_admin",
+        name: "Limits Admin",
+        modules: &["limits"],
+    },
+    PermissionPreset {
+        id: "auditlogs_manager",
+        name: "Audit Logs Manager",
+        modules: &["auditlogs"],
+    },
+    PermissionPreset {
+        id: "gitlogs_manager",
+        name: "Git Logs Manager",
+        modules: &["gitlogs"],
+    },
+    PermissionPreset {
+        id: "settings_manager",
+        name: "Settings Manager",
+        modules: &["settings"],
+    },
+];
+
+impl PermissionPreset {
+    /// Finds a preset given its ID or name (case-insensitive)
+    pub fn find(name: &str) -> Option<&'static PermissionPreset> {
+        PRESETS
+            .iter()
+            .find(|p| p.id == name || p.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Returns the kittycat permissions of the preset, these are the permissions declared by the commands of its modules
+    pub fn perms(&self, registry: &ModuleRegistry) -> Vec<String> {
+        let mut perms = Vec::new();
+
+        for module in self.modules {
+            for perm in registry.module_kittycat_perms(module) {
+                if !perms.contains(perm) {
+                    perms.push(perm.clone());
+                }
+            }
+        }
+
+        perms
+    }
+}
+
+/// Validates a single kittycat permission against the permissions declared by the loaded modules
+///
+/// Negated permissions (`~namespace.permission`), namespace wildcards (`namespace.*`) and `global.*`
+/// are accepted as long as the namespace is known
+pub fn validate_kittycat_perm(perm: &str, known_perms: &[&str]) -> Result<(), crate::Error> {
+    let perm_name = perm.strip_prefix('~').unwrap_or(perm);
+
+    let Some((namespace, permission)) = perm_name.split_once('.') else {
+        return Err(format!(
+            "Invalid permission ``{}``. Permission must be in format ``<namespace>.<permission>``",
+            perm
+        )
+        .into());
+    };
+
+    if namespace.is_empty() || permission.is_empty() {
+        return Err(format!(
+            "Invalid permission ``{}``. Permission must be in format ``<namespace>.<permission>``",
+            perm
+        )
+        .into());
+    }
+
+    if perm_name == "global.*" || known_perms.contains(&perm_name) {
+        return Ok(());
+    }
+
+    let namespace_known = known_perms
+        .iter()
+        .any(|p| p.split_once('.').map(|(ns, _)| ns) == Some(namespace));
+
+    if permission == "*" && namespace_known {
+        return Ok(());
+    }
+
+    if namespace_known {
+        Err(format!(
+            "Unknown permission ``{}``. The namespace ``{}`` has no such permission",
+            perm, namespace
+        )
+        .into())
+    } else {
+        Err(format!("Unknown permission ``{}``. No module uses the namespace ``{}``", perm, namespace).into())
+    }
+}
+
+/// Parses and validates a comma-separated list of kittycat permissions, removing duplicates
+pub fn parse_kittycat_perms(perms: &str, known_perms: &[&str]) -> Result<Vec<String>, crate::Error> {
+    let mut parsed = Vec::new();
+
+    for perm in perms.split(',') {
+        let perm = perm.trim();
+
+        if perm.is_empty() {
+            continue;
+        }
+
+        validate_kittycat_perm(perm, known_perms)?;
+
+        if !parsed.iter().any(|p| p == perm) {
+            parsed.push(perm.to_string());
+        }
+    }
+
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_kittycat_perm() {
+        let known = ["moderation.kick", "moderation.ban", "server_backups.*"];
+
+        let tests = [
+            ("moderation.kick", true),
+            ("~moderation.ban", true),
+            ("moderation.*", true),
+            ("server_backups.*", true),
+            ("server_backups.create", false),
+            ("global.*", true),
+            ("moderation.kickk", false),
+            ("moderatoin.kick", false),
+            ("moderation", false),
+            ("moderation.", false),
+            (".kick", false),
+        ];
+
+        for (perm, valid) in tests {
+            assert_eq!(validate_kittycat_perm(perm, &known).is_ok(), valid, "{}", perm);
+        }
+    }
+
+    #[test]
+    fn test_parse_kittycat_perms() {
+        let known = ["moderation.kick", "moderation.ban"];
+
+        assert_eq!(
+            parse_kittycat_perms("moderation.kick, moderation.ban,,moderation.kick", &known).unwrap(),
+            vec!["moderation.kick", "moderation.ban"]
+        );
+        assert!(parse_kittycat_perms("", &known).unwrap().is_empty());
+        assert!(parse_kittycat_perms("moderation.kick,moderation.warn", &known).is_err());
+    }
+
+    #[test]
+    fn test_presets() {
+        let registry = ModuleRegistry::new(crate::modules::modules()).unwrap();
+        let known_perms = registry.kittycat_perms();
+
+        for preset in PRESETS {
+            for module in preset.modules {
+                assert!(registry.module(module).is_some(), "preset {} uses unknown module {}", preset.id, module);
+            }
+
+            let perms = preset.perms(&registry);
+            assert!(!perms.is_empty(), "preset {} has no permissions", preset.id);
+
+            for perm in perms {
+                assert!(validate_kittycat_perm(&perm, &known_perms).is_ok(), "{}", perm);
+            }
+        }
+
+        assert_eq!(PermissionPreset::find("moderator").map(|p| p.id), Some("moderator"));
+        assert_eq!(PermissionPreset::find("backup manager").map(|p| p.id), Some("backup_manager"));
+        assert!(PermissionPreset::find("nope").is_none());
+    }
+}
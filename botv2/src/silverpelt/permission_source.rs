@@ -0,0 +1,255 @@
+use super::{
+    cmd, member_permission_calc, module_config, permissions::PermissionResult,
+    silverpelt_cache::SILVERPELT_CACHE, CommandExtendedData, GuildCommandConfiguration,
+    GuildCommandOverride, GuildModuleConfiguration,
+};
+use crate::impls::cache::CacheHttpImpl;
+use serenity::all::{ChannelId, GuildId, Permissions, RoleId, UserId};
+use small_fixed_array::FixedArray;
+use sqlx::PgPool;
+use std::future::Future;
+
+/// The configuration of a command in a guild as returned by `module_config::get_command_configuration`
+pub type CommandConfigurationData = (
+    CommandExtendedData,
+    Option<GuildCommandConfiguration>,
+    Option<GuildModuleConfiguration>,
+);
+
+/// Whether or not a member is the guild owner along with their native permissions and roles
+pub type MemberPermInfo = (bool, Permissions, FixedArray<RoleId>);
+
+/// A source of the configuration and member data needed to check whether a member can run a command
+///
+/// The bot itself uses [`DatabaseSource`] which fetches this data from the database and Discord. Abstracting
+/// over these lookups allows the permission checks to be run (and tested) without a live database or gateway
+pub trait PermissionSource {
+    /// Returns the ID of the module a command (or subcommand) belongs to
+    fn module_of_command(&self, base_command: &str) -> Option<String>;
+
+    /// Returns the extended data and the guild command and module configuration of a command
+    fn command_configuration(
+        &self,
+        guild_id: GuildId,
+        command: &str,
+    ) -> impl Future<Output = Result<CommandConfigurationData, crate::Error>> + Send;
+
+    /// Returns the command overrides applying to a command for a member with the given roles, see `module_config::get_command_overrides`
+    fn command_overrides(
+        &self,
+        guild_id: GuildId,
+        command: &str,
+        channel_id: Option<ChannelId>,
+        roles: &[RoleId],
+    ) -> impl Future<Output = Result<Vec<GuildCommandOverride>, crate::Error>> + Send;
+
+    /// Returns whether or not a member is the guild owner along with their native permissions and roles
+    fn member_perm_info(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+    ) -> impl Future<Output = Result<MemberPermInfo, PermissionResult>> + Send;
+
+    /// Returns the resolved kittycat permissions of a member
+    fn kittycat_perms(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+        roles: &[RoleId],
+    ) -> impl Future<Output = Result<Vec<String>, crate::Error>> + Send;
+}
+
+/// The default permission source, backed by the database and Discord (using the cache where possible)
+pub struct DatabaseSource<'a, 'b> {
+    pub pool: &'a PgPool,
+    pub cache_http: &'a CacheHttpImpl,
+    /// If a poise::Context is available and originates from a Application Command, the member can be fetched from it directly
+    pub poise_ctx: &'a Option<crate::Context<'b>>,
+}
+
+impl PermissionSource for DatabaseSource<'_, '_> {
+    fn module_of_command(&self, base_command: &str) -> Option<String> {
+        SILVERPELT_CACHE
+            .registry()
+            .module_of_command(base_command)
+            .map(|m| m.to_string())
+    }
+
+    async fn command_configuration(
+        &self,
+        guild_id: GuildId,
+        command: &str,
+    ) -> Result<CommandConfigurationData, crate::Error> {
+        module_config::get_command_configuration(self.pool, guild_id.to_string().as_str(), command)
+            .await
+    }
+
+    async fn command_overrides(
+        &self,
+        guild_id: GuildId,
+        command: &str,
+        channel_id: Option<ChannelId>,
+        roles: &[RoleId],
+    ) -> Result<Vec<GuildCommandOverride>, crate::Error> {
+        module_config::get_command_overrides(
+            self.pool,
+            guild_id,
+            command,
+            channel_id.map(|c| cmd::resolve_channel_scope(guild_id, c, self.cache_http)),
+            roles,
+        )
+        .await
+    }
+
+    async fn member_perm_info(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+    ) -> Result<MemberPermInfo, PermissionResult> {
+        cmd::get_perm_info(guild_id, user_id, self.cache_http, self.poise_ctx).await
+    }
+
+    async fn kittycat_perms(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+        roles: &[RoleId],
+    ) -> Result<Vec<String>, crate::Error> {
+        member_permission_calc::get_kittycat_perms(self.pool, guild_id, user_id, roles).await
+    }
+}
+
+#[cfg(test)]
+pub mod fake {
+    use super::*;
+    use crate::silverpelt::{
+        utils::permute_command_names, CommandExtendedDataMap, CommandOverrideScope, CooldownConfiguration,
+    };
+    use small_fixed_array::TruncatingInto;
+    use std::collections::HashMap;
+
+    /// A member of a [`MemorySource`] guild
+    #[derive(Default, Clone)]
+    pub struct MemoryMember {
+        pub native_perms: Permissions,
+        pub roles: Vec<RoleId>,
+        /// The resolved kittycat permissions of the member
+        pub kittycat_perms: Vec<String>,
+    }
+
+    /// An in-memory permission source for a single guild, for use in tests
+    pub struct MemorySource {
+        pub owner_id: UserId,
+        pub members: HashMap<UserId, MemoryMember>,
+        /// Root command name to the module it belongs to and its extended data
+        pub commands: HashMap<String, (String, CommandExtendedDataMap)>,
+        pub command_configurations: Vec<GuildCommandConfiguration>,
+        pub module_configurations: Vec<GuildModuleConfiguration>,
+        pub command_overrides: Vec<GuildCommandOverride>,
+        /// Channel ID to the category it is in
+        pub categories: HashMap<ChannelId, ChannelId>,
+    }
+
+    impl PermissionSource for MemorySource {
+        fn module_of_command(&self, base_command: &str) -> Option<String> {
+            self.commands.get(base_command).map(|(module, _)| module.clone())
+        }
+
+        async fn command_configuration(
+            &self,
+            _guild_id: GuildId,
+            command: &str,
+        ) -> Result<CommandConfigurationData, crate::Error> {
+            let permutations = permute_command_names(command);
+
+            let Some((module, root_cmd_data)) = self.commands.get(&permutations[0]) else {
+                return Err(format!("The command ``{}`` does not exist", command).into());
+            };
+
+            let cmd_data = module_config::resolve_command_extended_data(root_cmd_data, &permutations);
+
+            let configs = permutations
+                .iter()
+                .filter_map(|p| self.command_configurations.iter().find(|c| &c.command == p))
+                .collect::<Vec<_>>();
+
+            // The deepest command configuration wins with the cooldowns layered per field, like in the database
+            let command_config = configs.last().map(|c| GuildCommandConfiguration {
+                cooldown: CooldownConfiguration::layer(configs.iter().filter_map(|c| c.cooldown.as_ref())),
+                ..(*c).clone()
+            });
+
+            let module_config = self
+                .module_configurations
+                .iter()
+                .find(|m| &m.module == module)
+                .cloned();
+
+            Ok((cmd_data, command_config, module_config))
+        }
+
+        async fn command_overrides(
+            &self,
+            _guild_id: GuildId,
+            command: &str,
+            channel_id: Option<ChannelId>,
+            roles: &[RoleId],
+        ) -> Result<Vec<GuildCommandOverride>, crate::Error> {
+            let permutations = permute_command_names(command);
+            let category_id = channel_id.and_then(|c| self.categories.get(&c).copied());
+
+            let mut overrides = self
+                .command_overrides
+                .iter()
+                .filter(|o| permutations.contains(&o.command))
+                .filter(|o| match o.scope {
+                    CommandOverrideScope::Channel(id) => Some(id) == channel_id,
+                    CommandOverrideScope::Category(id) => Some(id) == category_id,
+                    CommandOverrideScope::Role(id) => roles.contains(&id),
+                })
+                .cloned()
+                .collect::<Vec<_>>();
+
+            overrides.sort_by_key(|o| {
+                (
+                    permutations.iter().position(|p| p == &o.command).unwrap_or_default(),
+                    o.scope.priority(),
+                    o.scope.scope_id(),
+                )
+            });
+
+            Ok(overrides)
+        }
+
+        async fn member_perm_info(
+            &self,
+            _guild_id: GuildId,
+            user_id: UserId,
+        ) -> Result<MemberPermInfo, PermissionResult> {
+            if user_id == self.owner_id {
+                return Ok((true, Permissions::all(), FixedArray::new()));
+            }
+
+            let Some(member) = self.members.get(&user_id) else {
+                return Err(PermissionResult::DiscordError {
+                    error: "Unknown Member".to_string(),
+                });
+            };
+
+            Ok((false, member.native_perms, member.roles.clone().trunc_into()))
+        }
+
+        async fn kittycat_perms(
+            &self,
+            _guild_id: GuildId,
+            user_id: UserId,
+            _roles: &[RoleId],
+        ) -> Result<Vec<String>, crate::Error> {
+            Ok(self
+                .members
+                .get(&user_id)
+                .map(|m| m.kittycat_perms.clone())
+                .unwrap_or_default())
+        }
+    }
+}
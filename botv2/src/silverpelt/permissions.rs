@@ -1,5 +1,5 @@
 use super::{
-    CommandExtendedData, CommandExtendedDataMap, GuildCommandConfiguration,
+    CommandExtendedData, CommandExtendedDataMap, GuildCommandConfiguration, GuildCommandOverride,
     GuildModuleConfiguration, PermissionCheck, PermissionChecks, 
     silverpelt_cache::SILVERPELT_CACHE,
 };
@@ -37,6 +37,10 @@ pub enum PermissionResult {
     MissingMinChecks {
         checks: PermissionChecks,
     },
+    /// The command is on cooldown, remaining is in milliseconds
+    Cooldown {
+        remaining: u64,
+    },
     DiscordError {
         error: String,
     },
@@ -66,6 +70,7 @@ impl PermissionResult {
             PermissionResult::ModuleDisabled { .. } => "module_disabled",
             PermissionResult::NoChecksSucceeded { .. } => "no_checks_succeeded",
             PermissionResult::MissingMinChecks { .. } => "missing_min_checks",
+            PermissionResult::Cooldown { .. } => "cooldown",
             PermissionResult::DiscordError { .. } => "discord_error",
             PermissionResult::GenericError { .. } => "generic_error",
         }
@@ -121,6 +126,12 @@ impl PermissionResult {
                     checks.checks_needed, checks
                 )
             }
+            PermissionResult::Cooldown { remaining } => {
+                format!(
+                    "This command is on cooldown, please try again in {:.2} seconds",
+                    *remaining as f64 / 1000.0
+                )
+            }
             PermissionResult::DiscordError { error } => {
                 format!("A Discord-related error seems to have occurred: {}.\n\nPlease try again later, it might work!", error)
             }
@@ -188,6 +199,224 @@ pub fn check_perms_single(
     PermissionResult::Ok {}
 }
 
+/// A layer of configuration (or a short-circuit) that was consulted while checking a command
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "layer")]
+pub enum PermissionTraceLayer {
+    /// The command was decided without running any permission checks (root commands, server owner etc.)
+    ShortCircuit { reason: String },
+    /// A channel, category or role override that was layered on top of the guild command configuration
+    CommandOverride {
+        command_override: GuildCommandOverride,
+    },
+    /// The guild command configuration that was used (after applying any overrides)
+    CommandConfiguration {
+        command_config: GuildCommandConfiguration,
+    },
+    /// The guild module configuration that was used
+    ModuleConfiguration {
+        module_config: GuildModuleConfiguration,
+    },
+    /// The permission checks that were run, `overriden` is true if these come from the guild command configuration
+    /// and false if these are the default permissions of the command
+    Permissions {
+        perms: PermissionChecks,
+        overriden: bool,
+    },
+}
+
+/// The outcome of a single permission check within a [`PermissionTrace`]
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PermissionCheckTrace {
+    /// The check that was run
+    pub check: PermissionCheck,
+    /// Whether or not the member has each native permission of the check (ADMINISTRATOR counts as having all of them)
+    pub native_perms: Vec<(serenity::all::Permissions, bool)>,
+    /// Whether or not the member has each kittycat permission of the check
+    pub kittycat_perms: Vec<(String, bool)>,
+    /// Whether or not this check was ANDed to the previous one (the outer_and of the previous check)
+    pub outer_and: bool,
+    /// The result of the check
+    pub result: PermissionResult,
+}
+
+/// A trace of a permission check explaining why a member can or cannot run a command
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PermissionTrace {
+    /// The command that was checked
+    pub command: String,
+    /// The native permissions of the member
+    pub member_native_perms: serenity::all::Permissions,
+    /// The resolved kittycat permissions of the member
+    pub member_kittycat_perms: Vec<String>,
+    /// The configuration layers consulted, in order
+    pub layers: Vec<PermissionTraceLayer>,
+    /// The individual permission checks that were run, in order
+    pub checks: Vec<PermissionCheckTrace>,
+    /// The number of checks that need to succeed
+    pub checks_needed: usize,
+    /// The number of checks that succeeded before a decision was made
+    pub checks_succeeded: usize,
+    /// The final decision
+    pub result: PermissionResult,
+}
+
+impl PermissionTrace {
+    pub fn new(command: &str) -> Self {
+        Self {
+            command: command.to_string(),
+            member_native_perms: serenity::all::Permissions::empty(),
+            member_kittycat_perms: Vec::new(),
+            layers: Vec::new(),
+            checks: Vec::new(),
+            checks_needed: 0,
+            checks_succeeded: 0,
+            result: PermissionResult::Ok {},
+        }
+    }
+
+    /// Formats the trace for display in a Discord message
+    pub fn to_markdown(&self) -> String {
+        let mut md = format!(
+            "**Command:** ``{}``\n**Decision:** ``{}``\n{}\n",
+            self.command,
+            self.result.code(),
+            self.result.to_markdown()
+        );
+
+        md.push_str("\n**Layers**\n");
+
+        for layer in &self.layers {
+            match layer {
+                PermissionTraceLayer::ShortCircuit { reason } => {
+                    md.push_str(&format!("- Short-circuited: ``{}``\n", reason));
+                }
+                PermissionTraceLayer::CommandOverride { command_override } => {
+                    md.push_str(&format!(
+                        "- Override for ``{}`` in {}: disabled=``{:?}``, custom perms=``{}``\n",
+                        command_override.command,
+                        command_override.scope,
+                        command_override.disabled,
+                        command_override.perms.is_some()
+                    ));
+                }
+                PermissionTraceLayer::CommandConfiguration { command_config } => {
+                    md.push_str(&format!(
+                        "- Command configuration for ``{}``: disabled=``{:?}``, custom perms=``{}``\n",
+                        command_config.command,
+                        command_config.disabled,
+                        command_config.perms.is_some()
+                    ));
+                }
+                PermissionTraceLayer::ModuleConfiguration { module_config } => {
+                    md.push_str(&format!(
+                        "- Module configuration for ``{}``: disabled=``{:?}``\n",
+                        module_config.module, module_config.disabled
+                    ));
+                }
+                PermissionTraceLayer::Permissions { perms, overriden } => {
+                    md.push_str(&format!(
+                        "- Using {} permissions (checks needed: {}): {}\n",
+                        if *overriden { "server-configured" } else { "default" },
+                        perms.checks_needed,
+                        perms
+                    ));
+                }
+            }
+        }
+
+        if !self.checks.is_empty() {
+            md.push_str(&format!(
+                "\n**Checks** ({} of {} needed succeeded)\n",
+                self.checks_succeeded, self.checks_needed
+            ));
+
+            for (i, check) in self.checks.iter().enumerate() {
+                md.push_str(&format!(
+                    "{}. {}[{}] ``{}``\n",
+                    i + 1,
+                    if i == 0 {
+                        ""
+                    } else if check.outer_and {
+                        "AND "
+                    } else {
+                        "OR "
+                    },
+                    if check.check.inner_and { "all of" } else { "any of" },
+                    check.result.code()
+                ));
+
+                for (perm, has) in &check.native_perms {
+                    md.push_str(&format!(
+                        "  - {} Discord: {}\n",
+                        if *has { ":white_check_mark:" } else { ":x:" },
+                        perm
+                    ));
+                }
+
+                for (perm, has) in &check.kittycat_perms {
+                    md.push_str(&format!(
+                        "  - {} Kittycat: {}\n",
+                        if *has { ":white_check_mark:" } else { ":x:" },
+                        perm
+                    ));
+                }
+            }
+        }
+
+        md
+    }
+}
+
+/// Explains a single permission check, recording which permissions of the check the member has
+pub fn explain_check_single(
+    check: &PermissionCheck,
+    outer_and: bool,
+    member_native_perms: serenity::all::Permissions,
+    member_kittycat_perms: &[String],
+) -> PermissionCheckTrace {
+    let is_discord_admin = member_native_perms.contains(serenity::all::Permissions::ADMINISTRATOR);
+
+    PermissionCheckTrace {
+        check: check.clone(),
+        native_perms: check
+            .native_perms
+            .iter()
+            .map(|perm| (*perm, is_discord_admin || member_native_perms.contains(*perm)))
+            .collect(),
+        kittycat_perms: check
+            .kittycat_perms
+            .iter()
+            .map(|perm| (perm.clone(), kittycat::perms::has_perm(member_kittycat_perms, perm)))
+            .collect(),
+        outer_and,
+        result: check_perms_single(check, member_native_perms, member_kittycat_perms),
+    }
+}
+
+/// Layers command overrides on top of a guild command configuration
+///
+/// Overrides must be ordered from lowest to highest precedence, each override replacing
+/// the fields it sets
+pub fn apply_command_overrides(
+    command_config: &GuildCommandConfiguration,
+    overrides: &[GuildCommandOverride],
+) -> GuildCommandConfiguration {
+    let mut command_config = command_config.clone();
+
+    for command_override in overrides {
+        if let Some(ref perms) = command_override.perms {
+            command_config.perms = Some(perms.clone());
+        }
+
+        if let Some(disabled) = command_override.disabled {
+            command_config.disabled = Some(disabled);
+        }
+    }
+
+    command_config
+}
+
 pub fn can_run_command(
     cmd_data: &CommandExtendedData,
     command_config: &GuildCommandConfiguration,
@@ -195,17 +424,80 @@ pub fn can_run_command(
     cmd_qualified_name: &str,
     member_native_perms: serenity::all::Permissions,
     member_kittycat_perms: &[String],
+) -> PermissionResult {
+    can_run_command_impl(
+        cmd_data,
+        command_config,
+        module_config,
+        cmd_qualified_name,
+        member_native_perms,
+        member_kittycat_perms,
+        None,
+    )
+}
+
+/// Same as can_run_command, but records each layer and check consulted into the given trace
+///
+/// The final decision is both returned and stored in the trace
+pub fn can_run_command_traced(
+    cmd_data: &CommandExtendedData,
+    command_config: &GuildCommandConfiguration,
+    module_config: &GuildModuleConfiguration,
+    cmd_qualified_name: &str,
+    member_native_perms: serenity::all::Permissions,
+    member_kittycat_perms: &[String],
+    trace: &mut PermissionTrace,
+) -> PermissionResult {
+    trace.member_native_perms = member_native_perms;
+    trace.member_kittycat_perms = member_kittycat_perms.to_vec();
+
+    let res = can_run_command_impl(
+        cmd_data,
+        command_config,
+        module_config,
+        cmd_qualified_name,
+        member_native_perms,
+        member_kittycat_perms,
+        Some(&mut *trace),
+    );
+
+    trace.result = res.clone();
+
+    res
+}
+
+fn can_run_command_impl(
+    cmd_data: &CommandExtendedData,
+    command_config: &GuildCommandConfiguration,
+    module_config: &GuildModuleConfiguration,
+    cmd_qualified_name: &str,
+    member_native_perms: serenity::all::Permissions,
+    member_kittycat_perms: &[String],
+    mut trace: Option<&mut PermissionTrace>,
 ) -> PermissionResult {
     log::debug!("Command config: {:?} [{}]", command_config, cmd_qualified_name);
 
+    if let Some(trace) = trace.as_deref_mut() {
+        trace.layers.push(PermissionTraceLayer::CommandConfiguration {
+            command_config: command_config.clone(),
+        });
+    }
+
     if command_config.disabled.unwrap_or(!cmd_data.is_default_enabled) {
         return PermissionResult::CommandDisabled {
             command_config: command_config.clone(),
         };
     }
 
+    if let Some(trace) = trace.as_deref_mut() {
+        trace.layers.push(PermissionTraceLayer::ModuleConfiguration {
+            module_config: module_config.clone(),
+        });
+    }
+
     {
-        let Some(module) = SILVERPELT_CACHE.module_id_cache.get(&module_config.module) else {
+        let registry = SILVERPELT_CACHE.registry();
+        let Some(module) = registry.module(&module_config.module) else {
             return PermissionResult::UnknownModule {
                 module_config: module_config.clone(),
             };
@@ -223,6 +515,14 @@ pub fn can_run_command(
         .as_ref()
         .unwrap_or(&cmd_data.default_perms);
 
+    if let Some(trace) = trace.as_deref_mut() {
+        trace.layers.push(PermissionTraceLayer::Permissions {
+            perms: perms.clone(),
+            overriden: command_config.perms.is_some(),
+        });
+        trace.checks_needed = perms.checks_needed;
+    }
+
     if perms.checks.is_empty() {
         return PermissionResult::Ok {};
     }
@@ -239,6 +539,15 @@ pub fn can_run_command(
             member_kittycat_perms,
         );
 
+        if let Some(trace) = trace.as_deref_mut() {
+            trace.checks.push(explain_check_single(
+                check,
+                outer_and,
+                member_native_perms,
+                member_kittycat_perms,
+            ));
+        }
+
         if outer_and {
             // Question mark needs cloning which may harm performance
             if !res.is_ok() {
@@ -258,6 +567,10 @@ pub fn can_run_command(
 
         if res.is_ok() {
             success += 1;
+
+            if let Some(trace) = trace.as_deref_mut() {
+                trace.checks_succeeded = success;
+            }
         }
 
         // Set the outer AND to the new outer AND
@@ -336,6 +649,7 @@ mod tests {
             guild_id: "testing".into(),
             module: name.into(),
             disabled: None,
+            cooldown: None,
         }
     }
 
@@ -432,6 +746,7 @@ mod tests {
                 command: "test".into(),
                 perms: None,
                 disabled: None,
+                cooldown: None,
             },
             &gen_module_config("core"),
             "test",
@@ -458,6 +773,7 @@ mod tests {
                         checks_needed: 0,
                     }),
                     disabled: None,
+                    cooldown: None,
                 },
                 &gen_module_config("core"),
                 "test",
@@ -484,6 +800,7 @@ mod tests {
                         checks_needed: 1,
                     }),
                     disabled: None,
+                    cooldown: None,
                 },
                 &gen_module_config("core"),
                 "test",
@@ -518,6 +835,7 @@ mod tests {
                         checks_needed: 2,
                     }),
                     disabled: None,
+                    cooldown: None,
                 },
                 &gen_module_config("core"),
                 "test",
@@ -537,6 +855,7 @@ mod tests {
                     command: "test".into(),
                     perms: None,
                     disabled: None,
+                    cooldown: None,
                 },
                 &gen_module_config("core"),
                 "backups create",
@@ -555,6 +874,7 @@ mod tests {
                 command: "test".into(),
                 perms: None,
                 disabled: None,
+                cooldown: None,
             },
             &gen_module_config("core"),
             "backups create",
@@ -587,6 +907,7 @@ mod tests {
                     checks_needed: 1,
                 }),
                 disabled: None,
+                cooldown: None,
             },
             &gen_module_config("core"),
             "test",
@@ -595,4 +916,111 @@ mod tests {
         )
         .is_ok());
     }
+
+    #[test]
+    fn test_can_run_command_traced() {
+        let mut trace = PermissionTrace::new("test");
+
+        let res = can_run_command_traced(
+            &CommandExtendedData::none().get("").unwrap().clone(),
+            &GuildCommandConfiguration {
+                id: "test".into(),
+                guild_id: "test".into(),
+                command: "test".into(),
+                perms: Some(PermissionChecks {
+                    checks: vec![
+                        PermissionCheck {
+                            kittycat_perms: vec!["abc.test".into()],
+                            native_perms: vec![],
+                            outer_and: true,
+                            inner_and: false,
+                        },
+                        PermissionCheck {
+                            kittycat_perms: vec![],
+                            native_perms: vec![serenity::all::Permissions::BAN_MEMBERS],
+                            outer_and: false,
+                            inner_and: false,
+                        },
+                    ],
+                    checks_needed: 1,
+                }),
+                disabled: None,
+                cooldown: None,
+            },
+            &gen_module_config("core"),
+            "test",
+            serenity::all::Permissions::empty(),
+            &["abc.test".into()],
+            &mut trace,
+        );
+
+        // Should match the untraced result
+        assert!(err_with_code(res, "missing_any_perms"));
+        assert!(err_with_code(trace.result.clone(), "missing_any_perms"));
+
+        assert_eq!(trace.layers.len(), 3);
+        assert!(matches!(
+            trace.layers[2],
+            PermissionTraceLayer::Permissions { overriden: true, .. }
+        ));
+
+        assert_eq!(trace.checks.len(), 2);
+        assert_eq!(trace.checks_succeeded, 1);
+        assert_eq!(trace.checks[0].kittycat_perms, vec![("abc.test".to_string(), true)]);
+        assert!(trace.checks[1].outer_and);
+        assert_eq!(
+            trace.checks[1].native_perms,
+            vec![(serenity::all::Permissions::BAN_MEMBERS, false)]
+        );
+    }
+
+    #[test]
+    fn test_apply_command_overrides() {
+        let command_config = GuildCommandConfiguration {
+            id: "test".into(),
+            guild_id: "test".into(),
+            command: "backups".into(),
+            perms: None,
+            disabled: Some(true),
+            cooldown: None,
+        };
+
+        let gen_override = |scope: CommandOverrideScope, perms: Option<PermissionChecks>, disabled: Option<bool>| {
+            GuildCommandOverride {
+                id: "test".into(),
+                guild_id: "test".into(),
+                command: "backups".into(),
+                scope,
+                perms,
+                disabled,
+            }
+        };
+
+        // No overrides
+        assert_eq!(apply_command_overrides(&command_config, &[]), command_config);
+
+        // Channel enables the command, category perms are kept
+        let category_perms = CommandExtendedData::kittycat_simple("backups", "create").default_perms;
+        let res = apply_command_overrides(
+            &command_config,
+            &[
+                gen_override(CommandOverrideScope::Category(serenity::all::ChannelId::new(1)), Some(category_perms.clone()), None),
+                gen_override(CommandOverrideScope::Channel(serenity::all::ChannelId::new(2)), None, Some(false)),
+            ],
+        );
+
+        assert_eq!(res.disabled, Some(false));
+        assert_eq!(res.perms, Some(category_perms));
+
+        // Later (more specific) overrides win
+        let res = apply_command_overrides(
+            &command_config,
+            &[
+                gen_override(CommandOverrideScope::Role(serenity::all::RoleId::new(3)), None, Some(false)),
+                gen_override(CommandOverrideScope::Channel(serenity::all::ChannelId::new(2)), None, Some(true)),
+            ],
+        );
+
+        assert_eq!(res.disabled, Some(true));
+    }
 }
@@ -0,0 +1,51 @@
+use crate::silverpelt::silverpelt_cache::SILVERPELT_CACHE;
+use serenity::all::{AutocompleteChoice, CommandDataOption, CommandDataOptionValue};
+use crate::Context;
+
+/// Finds the value of a string option (including in subcommands) of a command interaction
+fn find_string_option(options: &[CommandDataOption], name: &str) -> Option<String> {
+    for option in options {
+        match &option.value {
+            CommandDataOptionValue::SubCommand(options) | CommandDataOptionValue::SubCommandGroup(options) => {
+                if let Some(value) = find_string_option(options, name) {
+                    return Some(value);
+                }
+            }
+            CommandDataOptionValue::String(value) if option.name.as_str() == name => {
+                return Some(value.to_string());
+            }
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// Autocompletes the configuration options of a module, using the `module` argument of the command if set
+pub async fn autocomplete<'a>(
+    ctx: Context<'_>,
+    partial: &'a str,
+) -> Vec<AutocompleteChoice<'a>> {
+    let module = match ctx {
+        poise::Context::Application(a) => find_string_option(&a.interaction.data.options, "module"),
+        _ => None,
+    };
+
+    let mut ac = Vec::new();
+
+    for module_data in SILVERPELT_CACHE.registry().modules() {
+        if let Some(ref module) = module {
+            if module_data.id != module {
+                continue;
+            }
+        }
+
+        for option in module_data.config_options.iter() {
+            if option.name.to_lowercase().contains(&partial.to_lowercase()) || option.id.to_lowercase().contains(&partial.to_lowercase()) {
+                ac.push(AutocompleteChoice::new(format!("{} ({})", option.name, module_data.name), option.id.clone()));
+            }
+        }
+    }
+
+    ac
+}
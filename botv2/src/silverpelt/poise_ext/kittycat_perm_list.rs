@@ -0,0 +1,48 @@
+use crate::silverpelt::silverpelt_cache::SILVERPELT_CACHE;
+use serenity::all::AutocompleteChoice;
+use crate::Context;
+
+/// Discord allows a maximum of 25 autocomplete choices
+const MAX_CHOICES: usize = 25;
+
+/// Discord allows a maximum of 100 characters per autocomplete choice
+const MAX_CHOICE_LENGTH: usize = 100;
+
+/// Autocompletes the last permission of a comma-separated list of kittycat permissions
+pub async fn autocomplete<'a>(
+    _ctx: Context<'_>,
+    partial: &'a str,
+) -> Vec<AutocompleteChoice<'a>> {
+    let (prefix, last) = match partial.rsplit_once(',') {
+        Some((prefix, last)) => (format!("{},", prefix), last.trim()),
+        None => (String::new(), partial.trim()),
+    };
+
+    let (negator, last) = match last.strip_prefix('~') {
+        Some(last) => ("~", last),
+        None => ("", last),
+    };
+
+    let registry = SILVERPELT_CACHE.registry();
+
+    let mut ac = Vec::new();
+
+    for perm in registry.kittycat_perms() {
+        if ac.len() >= MAX_CHOICES {
+            break;
+        }
+
+        if perm.to_lowercase().contains(&last.to_lowercase()) {
+            let value = format!("{}{}{}", prefix, negator, perm);
+
+            // Long lists can't be autocompleted any further, they can still be typed out manually
+            if value.chars().count() > MAX_CHOICE_LENGTH {
+                continue;
+            }
+
+            ac.push(AutocompleteChoice::new(value.clone(), value));
+        }
+    }
+
+    ac
+}
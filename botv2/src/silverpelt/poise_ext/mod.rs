@@ -1 +1,4 @@
-pub mod module_list;
\ No newline at end of file
+pub mod config_option_list;
+pub mod kittycat_perm_list;
+pub mod module_list;
+pub mod perm_preset_list;
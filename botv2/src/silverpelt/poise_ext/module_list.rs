@@ -8,9 +8,7 @@ pub async fn autocomplete<'a>(
 ) -> Vec<AutocompleteChoice<'a>> {
     let mut ac = Vec::new();
 
-    for mv in SILVERPELT_CACHE.module_id_cache.iter() {
-        let module = mv.value();
-
+    for module in SILVERPELT_CACHE.registry().modules() {
         if module.name.to_lowercase().contains(&partial.to_lowercase()) || module.id.to_lowercase().contains(&partial.to_lowercase()) {
             ac.push(AutocompleteChoice::new(module.name, module.id));
         }
@@ -0,0 +1,30 @@
+use crate::silverpelt::perm_presets::PRESETS;
+use crate::silverpelt::silverpelt_cache::SILVERPELT_CACHE;
+use serenity::all::AutocompleteChoice;
+use crate::Context;
+
+/// Discord allows a maximum of 100 characters per autocomplete choice name
+const MAX_NAME_LENGTH: usize = 100;
+
+pub async fn autocomplete<'a>(
+    _ctx: Context<'_>,
+    partial: &'a str,
+) -> Vec<AutocompleteChoice<'a>> {
+    let registry = SILVERPELT_CACHE.registry();
+
+    let mut ac = Vec::new();
+
+    for preset in PRESETS {
+        if preset.name.to_lowercase().contains(&partial.to_lowercase()) || preset.id.contains(&partial.to_lowercase()) {
+            let mut name = format!("{} ({})", preset.name, preset.perms(&registry).join(", "));
+
+            if name.chars().count() > MAX_NAME_LENGTH {
+                name = name.chars().take(MAX_NAME_LENGTH - 3).collect::<String>() + "...";
+            }
+
+            ac.push(AutocompleteChoice::new(name, preset.id));
+        }
+    }
+
+    ac
+}
@@ -0,0 +1,44 @@
+-- Silverpelt (core command/permission system) schema additions
+
+-- Stores channel, category and role scoped overrides of a guilds command configuration
+CREATE TABLE guild_command_overrides (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    guild_id TEXT NOT NULL REFERENCES guilds(id) ON DELETE CASCADE ON UPDATE CASCADE,
+    command TEXT NOT NULL,
+    scope_type TEXT NOT NULL CHECK (scope_type IN ('channel', 'category', 'role')),
+    scope_id TEXT NOT NULL,
+    perms JSONB,
+    disabled BOOLEAN,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (guild_id, command, scope_type, scope_id)
+);
+
+-- Guild-configured cooldowns of commands and modules (see silverpelt::CooldownConfiguration)
+ALTER TABLE guild_command_configurations ADD COLUMN cooldown JSONB;
+ALTER TABLE guild_module_configurations ADD COLUMN cooldown JSONB;
+
+-- Stores the values of module configuration options (see silverpelt::config_opt::ConfigOption) per guild
+CREATE TABLE guild_module_options (
+    guild_id TEXT NOT NULL REFERENCES guilds(id) ON DELETE CASCADE ON UPDATE CASCADE,
+    module TEXT NOT NULL,
+    option_id TEXT NOT NULL,
+    value JSONB NOT NULL,
+    last_updated TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (guild_id, module, option_id)
+);
+
+-- Kittycat permissions granted temporarily to a role (guild_roles.perms) or member (guild_members.perm_overrides)
+--
+-- The expire_temporary_perms task removes the permissions again once they expire
+CREATE TABLE guild_temporary_perms (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    guild_id TEXT NOT NULL REFERENCES guilds(id) ON DELETE CASCADE ON UPDATE CASCADE,
+    scope_type TEXT NOT NULL CHECK (scope_type IN ('role', 'member')),
+    scope_id TEXT NOT NULL,
+    perms TEXT[] NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_by TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX guild_temporary_perms_expires_at_idx ON guild_temporary_perms (expires_at);
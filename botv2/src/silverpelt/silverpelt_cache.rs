@@ -1,117 +1,195 @@
+use crate::ipc::animus_magic::bot::BotAnimusMessage;
+use crate::ipc::animus_magic::client::{AnimusMagicClient, AnimusMessage};
 use indexmap::IndexMap;
 use moka::future::Cache;
 use once_cell::sync::Lazy;
-use serenity::all::{GuildId, UserId};
-use super::{CommandExtendedDataMap, Module, ModuleEventHandler, canonical_module::CanonicalModule, permissions::PermissionResult};
+use serenity::all::{ChannelId, GuildId, UserId};
+use splashcore_rs::animusmagic_ext::AnimusMagicClientExt;
+use splashcore_rs::animusmagic_protocol::{new_command_id, AnimusOp, AnimusTarget};
+use std::sync::{Arc, RwLock};
+use super::{CooldownConfiguration, Module, module_registry::ModuleRegistry, permissions::PermissionResult};
 
 /// The silverpelt cache is a structure that contains the core state for the bot
 pub struct SilverpeltCache {
     /// Cache of whether a (GuildId, String) pair has said module enabled or disabled
     pub module_enabled_cache: Cache<(GuildId, String), bool>,
 
-    /// Cache of whether a (GuildId, UserId) pair has the permission to run a command
-    pub command_permission_cache: Cache<(GuildId, UserId), IndexMap<String, PermissionResult>>,
-
-    /// Cache of the extended data given a command (the extended data map stores the default base permissions and other data per command)
-    pub command_extra_data_map: dashmap::DashMap<String, CommandExtendedDataMap>,
-
-    /// A commonly needed operation is mapping a module id to its respective module
+    /// Cache of whether a (GuildId, UserId, ChannelId) tuple has the permission to run a command
     ///
-    /// Module_id_cache is a cache of module id to module
-    pub module_id_cache: dashmap::DashMap<String, Module>,
+    /// The channel is part of the key as command overrides may be channel/category scoped
+    pub command_permission_cache: Cache<(GuildId, UserId, Option<ChannelId>), IndexMap<String, PermissionResult>>,
 
-    // A commonly needed operation is mapping the name of a module to its module id
-    //
-    // module_id_name_cache is a cache of module name to module id
-    pub module_id_name_cache: dashmap::DashMap<String, String>,
+    /// Cache of the guild-configured cooldowns of a (GuildId, command) pair
+    pub command_cooldown_cache: Cache<(GuildId, String), CooldownConfiguration>,
 
-    /// Command ID to module map
+    /// The registry of all loaded modules
     ///
-    /// This uses an indexmap for now to avoid sending values over await point
-    pub command_id_module_map: indexmap::IndexMap<String, String>,
-
-    /// Cache of the canonical forms of all modules
-    pub canonical_module_cache: dashmap::DashMap<String, CanonicalModule>,
-
-    /// Cache of all event listeners for a given module
-    pub module_event_listeners_cache: indexmap::IndexMap<String, Vec<ModuleEventHandler>>,
+    /// Use `registry` to access the registry and `reload_modules` to replace it at runtime
+    registry: RwLock<Arc<ModuleRegistry>>,
 }
 
 impl SilverpeltCache {
-    pub fn new() -> Self {
+    pub fn new(registry: ModuleRegistry) -> Self {
         log::info!("Making new SilverpeltCache");
         Self {
             command_permission_cache: Cache::builder()
                 .time_to_live(std::time::Duration::from_secs(60))
+                .support_invalidation_closures()
+                .build(),
+            command_cooldown_cache: Cache::builder()
+                .time_to_live(std::time::Duration::from_secs(60))
+                .support_invalidation_closures()
+                .build(),
+            module_enabled_cache: Cache::builder()
+                .support_invalidation_closures()
                 .build(),
-            module_enabled_cache: Cache::builder().build(),
-            command_extra_data_map: {
-                let map = dashmap::DashMap::new();
-
-                for module in crate::modules::modules() {
-                    for (command, extended_data) in module.commands {
-                        map.insert(command.name.clone(), extended_data);
-                    }
-                }
-
-                map
-            },
-            module_id_cache: {
-                let map = dashmap::DashMap::new();
-
-                for module in crate::modules::modules() {
-                    map.insert(module.id.to_string(), module);
-                }
-
-                map
-            },
-            module_id_name_cache: {
-                let map = dashmap::DashMap::new();
-
-                for module in crate::modules::modules() {
-                    map.insert(module.name.to_string(), module.id.to_string());
-                }
-
-                map
-            },
-            command_id_module_map: {
-                let mut map = indexmap::IndexMap::new();
-
-                for module in crate::modules::modules() {
-                    for command in module.commands.iter() {
-                        map.insert(command.0.name.to_string(), module.id.to_string());
-
-                        for sub in command.0.subcommands.iter() {
-                            map.insert(sub.name.to_string(), module.id.to_string());
-                        }
-                    }
-                }
-
-                map
-            },
-            canonical_module_cache: {
-                let map = dashmap::DashMap::new();
-
-                for module in crate::modules::modules() {
-                    map.insert(
-                        module.id.to_string(),
-                        CanonicalModule::from(module),
-                    );
-                }
-
-                map
-            },
-            module_event_listeners_cache: {
-                let mut map = indexmap::IndexMap::new();
-
-                for module in crate::modules::modules() {
-                    map.insert(module.id.to_string(), module.event_handlers);
-                }
-
-                map
-            },
+            registry: RwLock::new(Arc::new(registry)),
         }
     }
+
+    /// Returns the current module registry
+    ///
+    /// The returned registry stays valid (but may become outdated) even if the modules are reloaded
+    pub fn registry(&self) -> Arc<ModuleRegistry> {
+        self.registry
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Replaces the loaded modules at runtime, e.g. to roll out or pull a module without a restart
+    ///
+    /// All cached module states and command permissions are dropped as they may depend on the
+    /// old modules. Note that the commands of newly added modules are not registered with
+    /// Discord until the next restart
+    pub fn reload_modules(&self, modules: Vec<Module>) -> Result<(), crate::Error> {
+        let registry = ModuleRegistry::new(modules)?;
+
+        *self.registry.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(registry);
+
+        self.module_enabled_cache.invalidate_all();
+        self.command_permission_cache.invalidate_all();
+        self.command_cooldown_cache.invalidate_all();
+
+        log::info!("Reloaded module registry");
+
+        Ok(())
+    }
 }
 
-pub static SILVERPELT_CACHE: Lazy<SilverpeltCache> = Lazy::new(SilverpeltCache::new);
+impl SilverpeltCache {
+    /// Drops all cached module states, command permissions and cooldowns of a guild on this cluster
+    ///
+    /// Use `invalidate_guild_cache` to invalidate the cache of a guild on all clusters
+    pub fn invalidate_guild_local(&self, guild_id: GuildId) {
+        if let Err(err) = self.module_enabled_cache.invalidate_entries_if(move |k, _| k.0 == guild_id) {
+            log::error!("Failed to invalidate module enabled cache for guild {}: {}", guild_id, err);
+        }
+
+        if let Err(err) = self.command_permission_cache.invalidate_entries_if(move |k, _| k.0 == guild_id) {
+            log::error!("Failed to invalidate command permission cache for guild {}: {}", guild_id, err);
+        }
+
+        if let Err(err) = self.command_cooldown_cache.invalidate_entries_if(move |k, _| k.0 == guild_id) {
+            log::error!("Failed to invalidate command cooldown cache for guild {}: {}", guild_id, err);
+        }
+
+        log::info!("Invalidated cache for guild {}", guild_id);
+    }
+
+    /// Drops all cached command permissions of a guild on this cluster, keeping module states and cooldowns
+    pub fn invalidate_guild_permissions_local(&self, guild_id: GuildId) {
+        if let Err(err) = self.command_permission_cache.invalidate_entries_if(move |k, _| k.0 == guild_id) {
+            log::error!("Failed to invalidate command permission cache for guild {}: {}", guild_id, err);
+        }
+    }
+
+    /// Drops all cached command permissions of a member on this cluster
+    ///
+    /// Use `invalidate_member_cache` to invalidate the cache of a member on all clusters
+    pub fn invalidate_member_local(&self, guild_id: GuildId, user_id: UserId) {
+        if let Err(err) = self
+            .command_permission_cache
+            .invalidate_entries_if(move |k, _| k.0 == guild_id && k.1 == user_id)
+        {
+            log::error!("Failed to invalidate command permission cache for member {} in guild {}: {}", user_id, guild_id, err);
+        }
+    }
+}
+
+/// Broadcasts a cache invalidation message to all other clusters through the wildcard cluster (u16::MAX)
+///
+/// The message is sent as a notification, so clusters do not respond to it and the sending cluster ignores it
+async fn broadcast_invalidation(animus_magic_ipc: &AnimusMagicClient, message: BotAnimusMessage) {
+    let payload = match animus_magic_ipc.create_payload_simplex(
+        &new_command_id(),
+        u16::MAX,
+        AnimusTarget::Bot,
+        AnimusOp::Notify,
+        &AnimusMessage::Bot(message),
+    ) {
+        Ok(payload) => payload,
+        Err(err) => {
+            log::error!("Failed to create cache invalidation payload: {}", err);
+            return;
+        }
+    };
+
+    if let Err(err) = animus_magic_ipc.publish_next(payload).await {
+        log::error!("Failed to broadcast cache invalidation: {}", err);
+    }
+}
+
+/// Invalidates the cache of a guild on all clusters
+///
+/// The cache of this cluster is invalidated immediately, all other clusters are notified
+/// through an animus magic broadcast to the wildcard cluster (u16::MAX)
+pub async fn invalidate_guild_cache(animus_magic_ipc: &AnimusMagicClient, guild_id: GuildId) {
+    SILVERPELT_CACHE.invalidate_guild_local(guild_id);
+
+    broadcast_invalidation(animus_magic_ipc, BotAnimusMessage::InvalidateGuildCache { guild_id }).await;
+}
+
+/// Invalidates the cached command permissions of a member on all clusters
+///
+/// This must be called whenever the roles or permission overrides of a member change
+pub async fn invalidate_member_cache(animus_magic_ipc: &AnimusMagicClient, guild_id: GuildId, user_id: UserId) {
+    SILVERPELT_CACHE.invalidate_member_local(guild_id, user_id);
+
+    broadcast_invalidation(animus_magic_ipc, BotAnimusMessage::InvalidateMemberCache { guild_id, user_id }).await;
+}
+
+pub static SILVERPELT_CACHE: Lazy<SilverpeltCache> = Lazy::new(|| {
+    match ModuleRegistry::new(crate::modules::modules()) {
+        Ok(registry) => SilverpeltCache::new(registry),
+        Err(e) => panic!("Failed to build module registry: {}", e),
+    }
+});
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reload_modules() {
+        let cache = SilverpeltCache::new(ModuleRegistry::new(crate::modules::modules()).unwrap());
+
+        let modules = crate::modules::modules()
+            .into_iter()
+            .filter(|m| m.id != "server_member_backups")
+            .collect::<Vec<_>>();
+
+        cache.reload_modules(modules).unwrap();
+        assert!(cache.registry().module("server_member_backups").is_none());
+        assert!(cache.registry().module("server_backups").is_some());
+
+        // A registry with missing dependencies is rejected and the current registry is kept
+        let modules = crate::modules::modules()
+            .into_iter()
+            .filter(|m| m.id != "server_backups")
+            .collect::<Vec<_>>();
+
+        assert!(cache.reload_modules(modules).is_err());
+        assert!(cache.registry().module("server_backups").is_some());
+    }
+}
@@ -1,3 +1,6 @@
+use super::Module;
+use std::collections::HashMap;
+
 /// From name_split, construct a list of all permutations of the command name from the root till the end
 ///
 /// E.g: If subcommand is `limits hit`, then `limits` and `limits hit` will be constructed
@@ -27,6 +30,174 @@ pub fn permute_command_names(name: &str) -> Vec<String> {
     commands_to_check
 }
 
+/// Validates the dependencies and conflicts declared by a set of modules
+///
+/// This ensures that all referenced modules exist, that there are no dependency cycles, that no
+/// module both depends on and conflicts with the same module and that the modules enabled by
+/// default satisfy their own dependencies and conflicts
+pub fn validate_module_graph(modules: &[&Module]) -> Result<(), crate::Error> {
+    let by_id = modules.iter().map(|m| (m.id, *m)).collect::<HashMap<&str, &Module>>();
+
+    for module in modules {
+        for dep in module.depends_on.iter().chain(module.conflicts_with.iter()) {
+            if *dep == module.id {
+                return Err(format!("Module {} cannot depend on or conflict with itself", module.id).into());
+            }
+
+            if !by_id.contains_key(dep) {
+                return Err(format!("Module {} references unknown module {}", module.id, dep).into());
+            }
+        }
+
+        for dep in module.depends_on.iter() {
+            if module.conflicts_with.contains(dep) {
+                return Err(format!("Module {} both depends on and conflicts with {}", module.id, dep).into());
+            }
+
+            let dep_module = by_id[dep];
+
+            if module.is_default_enabled && !dep_module.is_default_enabled {
+                return Err(format!(
+                    "Module {} is enabled by default but depends on {} which is not",
+                    module.id, dep
+                )
+                .into());
+            }
+        }
+
+        for conflict in module.conflicts_with.iter() {
+            if module.is_default_enabled && by_id[conflict].is_default_enabled {
+                return Err(format!(
+                    "Module {} conflicts with {} but both are enabled by default",
+                    module.id, conflict
+                )
+                .into());
+            }
+        }
+    }
+
+    // Check for cycles using a depth-first search, states are 1 = visiting, 2 = done
+    fn visit<'a>(
+        id: &'a str,
+        by_id: &HashMap<&'a str, &'a Module>,
+        state: &mut HashMap<&'a str, u8>,
+        path: &mut Vec<&'a str>,
+    ) -> Result<(), crate::Error> {
+        match state.get(id) {
+            Some(2) => return Ok(()),
+            Some(1) => {
+                path.push(id);
+                return Err(format!("Module dependency cycle detected: {}", path.join(" -> ")).into());
+            }
+            _ => {}
+        }
+
+        state.insert(id, 1);
+        path.push(id);
+
+        for dep in by_id[id].depends_on.iter() {
+            visit(dep, by_id, state, path)?;
+        }
+
+        path.pop();
+        state.insert(id, 2);
+
+        Ok(())
+    }
+
+    let mut state = HashMap::new();
+
+    for module in modules {
+        visit(module.id, &by_id, &mut state, &mut Vec::new())?;
+    }
+
+    Ok(())
+}
+
+/// Validates that the event filters of a set of modules only contain known event names
+pub fn validate_module_event_filters(modules: &[&Module]) -> Result<(), crate::Error> {
+    let events = super::gwevent::core::event_list();
+
+    for module in modules {
+        for event in module.event_filter.iter() {
+            if !events.contains(event) {
+                return Err(format!("Module {} filters on unknown event {}", module.id, event).into());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks whether a module can be enabled or disabled given the current enabled state of all modules
+///
+/// `states` maps module IDs to whether they are enabled on the guild. On failure, the error
+/// lists the modules that would break
+pub fn check_module_toggle(
+    modules: &[&Module],
+    states: &HashMap<String, bool>,
+    module: &str,
+    enable: bool,
+) -> Result<(), crate::Error> {
+    let is_enabled = |id: &str| states.get(id).copied().unwrap_or_default();
+
+    let Some(module_data) = modules.iter().find(|m| m.id == module) else {
+        return Err(format!("The module ``{}`` does not exist", module).into());
+    };
+
+    if enable {
+        let missing = module_data
+            .depends_on
+            .iter()
+            .filter(|dep| !is_enabled(dep))
+            .map(|dep| format!("``{}``", dep))
+            .collect::<Vec<String>>();
+
+        if !missing.is_empty() {
+            return Err(format!(
+                "The module ``{}`` depends on the following modules which are not enabled: {}",
+                module,
+                missing.join(", ")
+            )
+            .into());
+        }
+
+        // Conflicts are symmetric, so check both this modules conflicts and modules conflicting with it
+        let conflicts = modules
+            .iter()
+            .filter(|m| m.id != module && is_enabled(m.id))
+            .filter(|m| module_data.conflicts_with.contains(&m.id) || m.conflicts_with.contains(&module))
+            .map(|m| format!("``{}``", m.id))
+            .collect::<Vec<String>>();
+
+        if !conflicts.is_empty() {
+            return Err(format!(
+                "The module ``{}`` conflicts with the following enabled modules which must be disabled first: {}",
+                module,
+                conflicts.join(", ")
+            )
+            .into());
+        }
+    } else {
+        let dependents = modules
+            .iter()
+            .filter(|m| is_enabled(m.id) && m.depends_on.contains(&module))
+            .map(|m| format!("``{}``", m.id))
+            .collect::<Vec<String>>();
+
+        if !dependents.is_empty() {
+            return Err(format!(
+                "The following enabled modules depend on ``{}`` and must be disabled first: {}",
+                module,
+                dependents.join(", ")
+            )
+            .into());
+        }
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -44,4 +215,114 @@ mod tests {
             vec!["limits", "limits hit", "limits hit add"]
         );
     }
+
+    fn test_module(
+        id: &'static str,
+        is_default_enabled: bool,
+        depends_on: Vec<&'static str>,
+        conflicts_with: Vec<&'static str>,
+    ) -> Module {
+        Module {
+            id,
+            name: id,
+            description: "",
+            web_hidden: false,
+            toggleable: true,
+            commands_configurable: true,
+            virtual_module: false,
+            is_default_enabled,
+            depends_on,
+            conflicts_with,
+            commands: vec![],
+            config_options: vec![],
+            event_filter: vec![],
+            event_handlers: vec![],
+        }
+    }
+
+    #[test]
+    fn test_validate_module_graph() {
+        let a = test_module("a", true, vec![], vec![]);
+        let b = test_module("b", false, vec!["a"], vec![]);
+        let c = test_module("c", false, vec!["b"], vec!["d"]);
+        let d = test_module("d", false, vec![], vec![]);
+        assert!(validate_module_graph(&[&a, &b, &c, &d]).is_ok());
+
+        // The real modules must always be valid
+        let modules = crate::modules::modules();
+        assert!(validate_module_graph(&modules.iter().collect::<Vec<_>>()).is_ok());
+
+        // Unknown module
+        let unknown = test_module("e", false, vec!["nope"], vec![]);
+        assert!(validate_module_graph(&[&a, &unknown]).is_err());
+
+        // Self dependency
+        let itself = test_module("e", false, vec!["e"], vec![]);
+        assert!(validate_module_graph(&[&itself]).is_err());
+
+        // Cycle
+        let x = test_module("x", false, vec!["y"], vec![]);
+        let y = test_module("y", false, vec!["z"], vec![]);
+        let z = test_module("z", false, vec!["x"], vec![]);
+        let err = validate_module_graph(&[&x, &y, &z]).unwrap_err();
+        assert!(err.to_string().contains("x -> y -> z -> x"));
+
+        // Depends on and conflicts with the same module
+        let both = test_module("e", false, vec!["a"], vec!["a"]);
+        assert!(validate_module_graph(&[&a, &both]).is_err());
+
+        // Default enabled module depending on a default disabled one
+        let default_dep = test_module("e", true, vec!["d"], vec![]);
+        assert!(validate_module_graph(&[&d, &default_dep]).is_err());
+
+        // Default enabled modules conflicting
+        let default_conflict = test_module("e", true, vec![], vec!["a"]);
+        assert!(validate_module_graph(&[&a, &default_conflict]).is_err());
+    }
+
+    #[test]
+    fn test_validate_module_event_filters() {
+        let modules = crate::modules::modules();
+        assert!(validate_module_event_filters(&modules.iter().collect::<Vec<_>>()).is_ok());
+
+        let mut module = test_module("a", false, vec![], vec![]);
+        module.event_filter = vec![crate::silverpelt::gwevent::core::event_list()[0]];
+        assert!(validate_module_event_filters(&[&module]).is_ok());
+
+        module.event_filter = vec!["NOT_AN_EVENT"];
+        assert!(validate_module_event_filters(&[&module]).is_err());
+    }
+
+    #[test]
+    fn test_check_module_toggle() {
+        let a = test_module("a", true, vec![], vec![]);
+        let b = test_module("b", false, vec!["a"], vec![]);
+        let c = test_module("c", false, vec![], vec!["b"]);
+        let modules = [&a, &b, &c];
+
+        let states = |enabled: &[&str]| {
+            ["a", "b", "c"]
+                .iter()
+                .map(|m| (m.to_string(), enabled.contains(m)))
+                .collect::<HashMap<String, bool>>()
+        };
+
+        // Dependencies must be enabled first
+        assert!(check_module_toggle(&modules, &states(&["a"]), "b", true).is_ok());
+        let err = check_module_toggle(&modules, &states(&[]), "b", true).unwrap_err();
+        assert!(err.to_string().contains("``a``"));
+
+        // Dependents must be disabled first
+        assert!(check_module_toggle(&modules, &states(&["a"]), "a", false).is_ok());
+        let err = check_module_toggle(&modules, &states(&["a", "b"]), "a", false).unwrap_err();
+        assert!(err.to_string().contains("``b``"));
+
+        // Conflicts are checked in both directions
+        assert!(check_module_toggle(&modules, &states(&["a", "b"]), "c", true).is_err());
+        assert!(check_module_toggle(&modules, &states(&["a", "c"]), "b", true).is_err());
+        assert!(check_module_toggle(&modules, &states(&["a"]), "c", true).is_ok());
+
+        // Unknown modules
+        assert!(check_module_toggle(&modules, &states(&[]), "nope", true).is_err());
+    }
 }
\ No newline at end of file
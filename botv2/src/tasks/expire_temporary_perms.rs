@@ -0,0 +1,193 @@
+use crate::silverpelt::{
+    member_permission_calc::rederive_perms, silverpelt_cache::SILVERPELT_CACHE, TemporaryPermsScope,
+};
+use serenity::all::{GuildId, RoleId, UserId};
+use sqlx::types::Uuid;
+
+/// Removes expired temporary permissions from roles and members, rederiving the permissions of all affected members
+///
+/// Only guilds on this cluster are handled as the cached command permissions of a guild live on the cluster serving it
+pub async fn expire_temporary_perms(
+    pool: &sqlx::PgPool,
+    cache_http: &crate::impls::cache::CacheHttpImpl,
+    _ctx: &serenity::client::Context,
+) -> Result<(), crate::Error> {
+    let expired = sqlx::query!(
+        "SELECT id, guild_id, scope_type, scope_id, perms FROM guild_temporary_perms WHERE expires_at <= NOW()"
+    )
+    .fetch_all(pool)
+    .await?;
+
+    for rec in expired {
+        let guild_id = rec.guild_id.parse::<GuildId>()?;
+
+        if cache_http.cache.guild(guild_id).is_none() {
+            continue;
+        }
+
+        let scope = match TemporaryPermsScope::from_db(&rec.scope_type, &rec.scope_id) {
+            Ok(scope) => scope,
+            Err(e) => {
+                log::error!("Invalid temporary permissions {}: {}", rec.id, e);
+                continue;
+            }
+        };
+
+        if let Err(e) = expire_entry(pool, rec.id, guild_id, scope, &rec.perms).await {
+            log::error!("Failed to expire temporary permissions {}: {}", rec.id, e);
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns `current` without the expired permissions `perms`
+fn remove_expired_perms(current: Vec<String>, perms: &[String]) -> Vec<String> {
+    current.into_iter().filter(|p| !perms.contains(p)).collect()
+}
+
+/// Removes the permissions of a single expired entry and rederives the permissions of the affected members
+async fn expire_entry(
+    pool: &sqlx::PgPool,
+    id: Uuid,
+    guild_id: GuildId,
+    scope: TemporaryPermsScope,
+    perms: &[String],
+) -> Result<(), crate::Error> {
+    let mut tx = pool.begin().await?;
+
+    let res = sqlx::query!("DELETE FROM guild_temporary_perms WHERE id = $1", id)
+        .execute(&mut *tx)
+        .await?;
+
+    // The entry has already been handled
+    if res.rows_affected() == 0 {
+        return Ok(());
+    }
+
+    // The members whose permissions must be rederived along with their roles
+    let mut members = Vec::new();
+
+    match scope {
+        TemporaryPermsScope::Role(role_id) => {
+            let current = sqlx::query!(
+                "SELECT perms FROM guild_roles WHERE guild_id = $1 AND role_id = $2 FOR UPDATE",
+                guild_id.to_string(),
+                role_id.to_string()
+            )
+            .fetch_optional(&mut *tx)
+            .await?;
+
+            // The role configuration may have been deleted in the meantime
+            if let Some(current) = current {
+                let new_perms = remove_expired_perms(current.perms, perms);
+
+                sqlx::query!(
+                    "UPDATE guild_roles SET perms = $1 WHERE guild_id = $2 AND role_id = $3",
+                    &new_perms,
+                    guild_id.to_string(),
+                    role_id.to_string()
+                )
+                .execute(&mut *tx)
+                .await?;
+
+                let recs = sqlx::query!(
+                    "SELECT user_id, roles FROM guild_members WHERE guild_id = $1 AND $2 = ANY(roles)",
+                    guild_id.to_string(),
+                    role_id.to_string()
+                )
+                .fetch_all(&mut *tx)
+                .await?;
+
+                for rec in recs {
+                    members.push((rec.user_id.parse::<UserId>()?, rec.roles));
+                }
+            }
+        }
+        TemporaryPermsScope::Member(user_id) => {
+            let current = sqlx::query!(
+                "SELECT perm_overrides FROM guild_members WHERE guild_id = $1 AND user_id = $2 FOR UPDATE",
+                guild_id.to_string(),
+                user_id.to_string()
+            )
+            .fetch_optional(&mut *tx)
+            .await?;
+
+            if let Some(current) = current {
+                let new_overrides = remove_expired_perms(current.perm_overrides, perms);
+
+                sqlx::query!(
+                    "UPDATE guild_members SET perm_overrides = $1 WHERE guild_id = $2 AND user_id = $3",
+                    &new_overrides,
+                    guild_id.to_string(),
+                    user_id.to_string()
+                )
+                .execute(&mut *tx)
+                .await?;
+
+                let rec = sqlx::query!(
+                    "SELECT roles FROM guild_members WHERE guild_id = $1 AND user_id = $2",
+                    guild_id.to_string(),
+                    user_id.to_string()
+                )
+                .fetch_one(&mut *tx)
+                .await?;
+
+                members.push((user_id, rec.roles));
+            }
+        }
+    }
+
+    tx.commit().await?;
+
+    for (user_id, roles) in members {
+        let roles = roles
+            .iter()
+            .filter_map(|r| r.parse::<RoleId>().ok())
+            .collect::<Vec<RoleId>>();
+
+        rederive_perms(pool, guild_id, user_id, &roles).await?;
+    }
+
+    match scope {
+        TemporaryPermsScope::Role(_) => {
+            SILVERPELT_CACHE.invalidate_guild_permissions_local(guild_id)
+        }
+        TemporaryPermsScope::Member(user_id) => {
+            SILVERPELT_CACHE.invalidate_member_local(guild_id, user_id)
+        }
+    }
+
+    log::info!("Expired temporary permissions {} in guild {}", id, guild_id);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn perms(perms: &[&str]) -> Vec<String> {
+        perms.iter().map(|p| p.to_string()).collect()
+    }
+
+    #[test]
+    fn test_remove_expired_perms() {
+        assert_eq!(
+            remove_expired_perms(perms(&["a.b", "c.d", "~e.f"]), &perms(&["c.d", "~e.f"])),
+            perms(&["a.b"])
+        );
+
+        // Permissions removed in the meantime are ignored
+        assert_eq!(
+            remove_expired_perms(perms(&["a.b"]), &perms(&["c.d"])),
+            perms(&["a.b"])
+        );
+
+        // The negation of an expired permission is kept
+        assert_eq!(
+            remove_expired_perms(perms(&["~a.b"]), &perms(&["a.b"])),
+            perms(&["~a.b"])
+        );
+    }
+}
@@ -1,2 +1,4 @@
+pub mod expire_temporary_perms;
+pub mod prune_limit_actions;
 pub mod taskcat;
 pub mod update_status;
@@ -0,0 +1,24 @@
+/// How long limit actions that did not hit any limit are kept for, these are only used to simulate limits
+pub const LIMIT_ACTION_RETENTION_DAYS: i64 = 30;
+
+/// Deletes the recorded limit actions that did not hit any limit once they are past their retention
+///
+/// Actions that hit a limit are kept as they are the cause of past hits and incidents
+pub async fn prune_limit_actions(
+    pool: &sqlx::PgPool,
+    _cache_http: &crate::impls::cache::CacheHttpImpl,
+    _ctx: &serenity::client::Context,
+) -> Result<(), crate::Error> {
+    let res = sqlx::query!(
+        "DELETE FROM limits__user_actions WHERE cardinality(limits_hit) = 0 AND created_at < NOW() - make_interval(days => $1)",
+        LIMIT_ACTION_RETENTION_DAYS as i32
+    )
+    .execute(pool)
+    .await?;
+
+    if res.rows_affected() > 0 {
+        log::info!("Pruned {} old limit actions", res.rows_affected());
+    }
+
+    Ok(())
+}
@@ -12,6 +12,8 @@ static TASK_MUTEX: Lazy<Mutex<i32>> = Lazy::new(|| Mutex::new(0));
 #[strum(serialize_all = "snake_case")]
 pub enum Task {
     UpdateStatus,
+    ExpireTemporaryPerms,
+    PruneLimitActions,
 }
 
 impl Task {
@@ -19,6 +21,8 @@ impl Task {
     pub fn enabled(&self) -> bool {
         match self {
             Task::UpdateStatus => true,
+            Task::ExpireTemporaryPerms => true,
+            Task::PruneLimitActions => true,
         }
     }
 
@@ -26,6 +30,8 @@ impl Task {
     pub fn duration(&self) -> Duration {
         match self {
             Task::UpdateStatus => Duration::from_secs(300),
+            Task::ExpireTemporaryPerms => Duration::from_secs(60),
+            Task::PruneLimitActions => Duration::from_secs(3600),
         }
     }
 
@@ -33,6 +39,8 @@ impl Task {
     pub fn description(&self) -> &'static str {
         match self {
             Task::UpdateStatus => "Updating statuses",
+            Task::ExpireTemporaryPerms => "Expiring temporary permissions",
+            Task::PruneLimitActions => "Pruning old limit actions",
         }
     }
 
@@ -47,6 +55,12 @@ impl Task {
             Task::UpdateStatus => {
                 crate::tasks::update_status::update_status(pool, cache_http, ctx).await
             }
+            Task::ExpireTemporaryPerms => {
+                crate::tasks::expire_temporary_perms::expire_temporary_perms(pool, cache_http, ctx).await
+            }
+            Task::PruneLimitActions => {
+                crate::tasks::prune_limit_actions::prune_limit_actions(pool, cache_http, ctx).await
+            }
         }
     }
 }
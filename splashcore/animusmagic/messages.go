@@ -8,9 +8,20 @@ import (
 
 type ClusterModules = []silverpelt.CanonicalModule
 
+// The schema of an expanded gateway event
+type EventSchema struct {
+	Event  string `json:"event"`
+	Fields []struct {
+		Name     string `json:"name"`
+		Type     string `json:"type"`
+		Optional bool   `json:"optional"`
+	} `json:"fields"`
+}
+
 type BotAnimusMessage struct {
-	Modules     *struct{} `json:"Modules,omitempty"`
-	GuildsExist *struct {
+	Modules      *struct{} `json:"Modules,omitempty"`
+	EventSchemas *struct{} `json:"EventSchemas,omitempty"`
+	GuildsExist  *struct {
 		Guilds []string `json:"guilds"`
 	} `json:"GuildsExist,omitempty"`
 	BaseGuildUserInfo *struct {
@@ -18,15 +29,32 @@ type BotAnimusMessage struct {
 		UserID  string `json:"user_id"`
 	} `json:"BaseGuildUserInfo,omitempty"`
 	CheckCommandPermission *struct {
-		GuildID string `json:"guild_id"`
-		UserID  string `json:"user_id"`
-		Command string `json:"command"`
+		GuildID   string `json:"guild_id"`
+		UserID    string `json:"user_id"`
+		ChannelID string `json:"channel_id,omitempty"`
+		Command   string `json:"command"`
 	} `json:"CheckCommandPermission,omitempty"`
+	ExplainCommandPermission *struct {
+		GuildID   string `json:"guild_id"`
+		UserID    string `json:"user_id"`
+		ChannelID string `json:"channel_id,omitempty"`
+		Command   string `json:"command"`
+	} `json:"ExplainCommandPermission,omitempty"`
 	ToggleModule *struct {
 		GuildID string `json:"guild_id"`
 		Module  string `json:"module"`
 		Enabled bool   `json:"enabled"`
 	} `json:"ToggleModule,omitempty"`
+	InvalidateGuildCache *struct {
+		GuildID string `json:"guild_id"`
+	} `json:"InvalidateGuildCache,omitempty"`
+	InvalidateMemberCache *struct {
+		GuildID string `json:"guild_id"`
+		UserID  string `json:"user_id"`
+	} `json:"InvalidateMemberCache,omitempty"`
+	ReloadModules *struct {
+		ExcludedModules []string `json:"excluded_modules"`
+	} `json:"ReloadModules,omitempty"`
 }
 
 func (b BotAnimusMessage) Message() {}
@@ -42,12 +70,19 @@ type BotAnimusResponse struct {
 	Modules *struct {
 		Modules ClusterModules `json:"modules"`
 	} `json:"Modules,omitempty"`
+	EventSchemas *struct {
+		Events []EventSchema `json:"events"`
+	} `json:"EventSchemas,omitempty"`
 
 	GuildsExist *struct {
 		GuildsExist []uint8 `json:"guilds_exist"`
 	}
 
 	BaseGuildUserInfo *types.UserGuildBaseData
+
+	ExplainCommandPermission *struct {
+		Trace map[string]any `json:"trace"`
+	} `json:"ExplainCommandPermission,omitempty"`
 }
 
 func (b BotAnimusResponse) Response() {}
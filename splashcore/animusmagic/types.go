@@ -87,6 +87,7 @@ const (
 	OpResponse AnimusOp = 0x1
 	OpError    AnimusOp = 0x2
 	OpProbe    AnimusOp = 0x3
+	OpNotify   AnimusOp = 0x4 // A request that expects no response, such as a broadcast to all clusters
 )
 
 func (a AnimusOp) String() string {
@@ -99,6 +100,8 @@ func (a AnimusOp) String() string {
 		return "Error"
 	case OpProbe:
 		return "Probe"
+	case OpNotify:
+		return "Notify"
 	default:
 		return "Unknown"
 	}
@@ -114,6 +117,8 @@ func ByteToAnimusOp(b uint8) (AnimusOp, bool) {
 		return OpError, true
 	case 0x3:
 		return OpProbe, true
+	case 0x4:
+		return OpNotify, true
 	default:
 		return 0, false
 	}
@@ -134,6 +139,8 @@ func StringToAnimusOp(s string) (AnimusOp, bool) {
 		"probe":    OpProbe,
 		"prb":      OpProbe,
 		"ping":     OpProbe,
+		"4":        OpNotify,
+		"notify":   OpNotify,
 	}
 
 	if val, ok := strMap[strings.ToLower(s)]; ok {
@@ -52,7 +52,7 @@ type Config struct {
 	Meta               Meta                `yaml:"meta" validate:"required"`
 	ObjectStorage      ObjectStorageConfig `yaml:"object_storage" validate:"required"`
 	SimpleGatewayProxy SimpleGatewayProxy  `yaml:"simple_gateway_proxy" validate:"required"`
-	SurrealDB          SurrealDB           `yaml:"surreal" validate:"required"`
+	SurrealDB          *SurrealDB          `yaml:"surreal" comment:"Optional, only used by the limits module when the bot is built with the surreal feature"`
 }
 
 type SimpleGatewayProxy struct {
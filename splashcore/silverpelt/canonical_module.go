@@ -13,7 +13,27 @@ type CanonicalModule struct {
 	CommandsConfigurable bool               `json:"commands_configurable"`
 	WebHidden            bool               `json:"web_hidden"`
 	IsDefaultEnabled     bool               `json:"is_default_enabled"`
+	DependsOn            []string           `json:"depends_on"`
+	ConflictsWith        []string           `json:"conflicts_with"`
 	Commands             []CanonicalCommand `json:"commands"`
+	ConfigOptions        []ConfigOption     `json:"config_options"`
+}
+
+// From silverpelt/config_opt
+type WebFieldType struct {
+	Type    string        `json:"type"`
+	Default any           `json:"default"`
+	Min     *int64        `json:"min,omitempty"`     // Integer only
+	Max     *int64        `json:"max,omitempty"`     // Integer only
+	Choices []string      `json:"choices,omitempty"` // Choice only
+	Inner   *WebFieldType `json:"inner,omitempty"`   // List and Optional only
+}
+
+type ConfigOption struct {
+	ID          string       `json:"id"`
+	Name        string       `json:"name"`
+	Description string       `json:"description"`
+	FieldType   WebFieldType `json:"field_type"`
 }
 
 type CanonicalCommand struct {
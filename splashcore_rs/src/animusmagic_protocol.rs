@@ -53,6 +53,8 @@ pub enum AnimusOp {
     Response,
     Error,
     Probe,
+    /// A request that expects no response, such as a broadcast to all clusters
+    Notify,
 }
 
 impl AnimusOp {
@@ -62,6 +64,7 @@ impl AnimusOp {
             AnimusOp::Response => 0x1,
             AnimusOp::Error => 0x2,
             AnimusOp::Probe => 0x3,
+            AnimusOp::Notify => 0x4,
         }
     }
 
@@ -71,6 +74,7 @@ impl AnimusOp {
             0x1 => Some(AnimusOp::Response),
             0x2 => Some(AnimusOp::Error),
             0x3 => Some(AnimusOp::Probe),
+            0x4 => Some(AnimusOp::Notify),
             _ => None,
         }
     }